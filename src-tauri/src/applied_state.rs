@@ -0,0 +1,274 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::file_lock;
+use crate::i18n::Message;
+use crate::paths::Environment;
+use crate::profile_manager;
+
+/// 某个软件当前实际应用的代理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedEntry {
+    pub software: String,
+    pub profile: String,
+    pub applied_at: u64,      // Unix 时间戳（秒）
+    pub content_hash: String, // 应用后配置内容的 SHA-256，用于检测外部修改
+    #[serde(default)]
+    pub config_path: Option<String>, // 应用时写入的配置文件路径
+    #[serde(default)]
+    pub relocated: bool, // 配置目录已变化（如 JetBrains 升级到新版本），读取状态时计算
+    #[serde(default)]
+    pub url_scopes: Vec<String>, // 写入的 Git [http "<url>"] 地址范围，关闭代理时据此移除
+}
+
+/// 开启代理前不存在、由本程序新建的配置文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedFile {
+    pub path: String,
+    pub content_hash: String, // 本程序最后写入的内容，与文件不同说明之后被其他程序修改过
+}
+
+/// 已应用状态（保存在数据目录，不写入 user_config.json）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppliedState {
+    pub entries: Vec<AppliedEntry>,
+    #[serde(default)]
+    pub created_files: Vec<CreatedFile>,
+}
+
+/// 获取已应用状态文件路径
+/// 位置: %LOCALAPPDATA%\proxy-manager\applied_state.json
+fn get_state_path(env: &Environment) -> Option<PathBuf> {
+    env.app_data_dir().map(|p| p.join("applied_state.json"))
+}
+
+/// 状态文件是否已存在（不存在时需要从现有配置初始化）
+pub fn is_initialized() -> bool {
+    get_state_path(&Environment::current())
+        .map(|p| p.exists())
+        .unwrap_or(false)
+}
+
+/// 计算内容的 SHA-256（十六进制）
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// 当前 Unix 时间戳（秒）
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn read_state(path: &PathBuf) -> AppliedState {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_state(path: &PathBuf, state: &AppliedState) -> Result<(), Message> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(state).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string().into())
+}
+
+/// 加载已应用状态
+pub fn load_applied_state() -> AppliedState {
+    match get_state_path(&Environment::current()) {
+        Some(path) => {
+            let _guard = file_lock::lock_path(&path);
+            read_state(&path)
+        }
+        None => AppliedState::default(),
+    }
+}
+
+/// 在同一把锁内完成"读取-修改-保存"
+fn update_applied_state<F>(modify: F) -> Result<AppliedState, Message>
+where
+    F: FnOnce(&mut AppliedState),
+{
+    let path =
+        get_state_path(&Environment::current()).ok_or_else(|| tr!("backup_dir_unavailable"))?;
+    let _guard = file_lock::lock_path(&path)?;
+
+    let mut state = read_state(&path);
+    modify(&mut state);
+    write_state(&path, &state)?;
+
+    Ok(state)
+}
+
+/// 记录软件已应用某个配置组
+pub fn record_applied(
+    software: &str,
+    profile: &str,
+    content: &str,
+    config_path: Option<String>,
+    url_scopes: Vec<String>,
+) -> Result<(), Message> {
+    let entry = AppliedEntry {
+        software: software.to_string(),
+        profile: profile.to_string(),
+        applied_at: now_secs(),
+        content_hash: content_hash(content),
+        config_path,
+        relocated: false,
+        url_scopes,
+    };
+
+    update_applied_state(|state| {
+        state.entries.retain(|e| e.software != software);
+        state.entries.push(entry);
+    })?;
+    Ok(())
+}
+
+/// 移除软件的已应用记录（关闭或重置代理后调用）
+pub fn remove_applied(software: &str) -> Result<(), Message> {
+    update_applied_state(|state| state.entries.retain(|e| e.software != software))?;
+    Ok(())
+}
+
+/// 配置组改名后，把已应用记录中的配置组名称一并改为新名称
+pub fn rename_profile(old_name: &str, new_name: &str) -> Result<(), Message> {
+    update_applied_state(|state| {
+        for entry in &mut state.entries {
+            if profile_manager::names_eq(&entry.profile, old_name) {
+                entry.profile = new_name.to_string();
+            }
+        }
+    })?;
+    Ok(())
+}
+
+/// 把软件的已应用记录恢复为之前的状态（None 表示之前未应用），用于回滚
+pub fn restore_entry(software: &str, previous: Option<AppliedEntry>) -> Result<(), Message> {
+    update_applied_state(|state| {
+        state.entries.retain(|e| e.software != software);
+        state.entries.extend(previous);
+    })?;
+    Ok(())
+}
+
+/// 记录本程序新建的配置文件及写入的内容（再次写入时更新内容）
+pub fn record_created(path: &Path, content: &str) -> Result<(), Message> {
+    let path = path.to_string_lossy().into_owned();
+    let entry = CreatedFile {
+        path: path.clone(),
+        content_hash: content_hash(content),
+    };
+    update_applied_state(|state| {
+        state.created_files.retain(|f| f.path != path);
+        state.created_files.push(entry);
+    })?;
+    Ok(())
+}
+
+/// 移除新建文件的记录（文件被删除或还原后调用）
+pub fn forget_created(path: &Path) -> Result<(), Message> {
+    let path = path.to_string_lossy();
+    update_applied_state(|state| state.created_files.retain(|f| f.path != path))?;
+    Ok(())
+}
+
+/// 配置文件是否由本程序新建
+pub fn is_created(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    load_applied_state()
+        .created_files
+        .iter()
+        .any(|f| f.path == path)
+}
+
+/// 配置文件由本程序新建，且内容仍是本程序最后写入的内容（可以整个删除）
+pub fn is_created_and_unchanged(path: &Path) -> bool {
+    let path_str = path.to_string_lossy();
+    let Some(created) = load_applied_state()
+        .created_files
+        .into_iter()
+        .find(|f| f.path == path_str)
+    else {
+        return false;
+    };
+    fs::read_to_string(path).is_ok_and(|content| content_hash(&content) == created.content_hash)
+}
+
+/// 用扫描得到的记录初始化状态文件（仅在状态文件不存在时使用）
+pub fn initialize(entries: Vec<AppliedEntry>) -> Result<(), Message> {
+    update_applied_state(|state| state.entries = entries)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    fn in_sandbox(f: impl FnOnce(&Path)) {
+        let home = tempfile::tempdir().unwrap();
+        Environment::sandbox(home.path()).run(|| f(home.path()));
+    }
+
+    #[test]
+    fn created_file_with_same_content_is_unchanged_despite_new_mtime() {
+        in_sandbox(|home| {
+            let path = home.join(".npmrc");
+            fs::write(&path, "proxy=http://127.0.0.1:7890").unwrap();
+            record_created(&path, "proxy=http://127.0.0.1:7890").unwrap();
+            assert!(is_created_and_unchanged(&path));
+
+            // 只改变修改时间（如被其他程序原样重写）不算修改
+            let earlier = SystemTime::now() - Duration::from_secs(3600);
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(earlier)
+                .unwrap();
+            assert!(is_created_and_unchanged(&path));
+        });
+    }
+
+    #[test]
+    fn created_file_with_new_content_is_changed() {
+        in_sandbox(|home| {
+            let path = home.join(".npmrc");
+            fs::write(&path, "proxy=http://127.0.0.1:7890").unwrap();
+            record_created(&path, "proxy=http://127.0.0.1:7890").unwrap();
+
+            fs::write(&path, "proxy=http://127.0.0.1:7890\nstrict-ssl=false").unwrap();
+            assert!(!is_created_and_unchanged(&path));
+            assert!(is_created(&path));
+
+            // 再次写入后以新内容为准
+            record_created(&path, "proxy=http://127.0.0.1:7890\nstrict-ssl=false").unwrap();
+            assert!(is_created_and_unchanged(&path));
+        });
+    }
+
+    #[test]
+    fn deleted_or_forgotten_file_is_not_created() {
+        in_sandbox(|home| {
+            let path = home.join(".npmrc");
+            fs::write(&path, "proxy=http://127.0.0.1:7890").unwrap();
+            record_created(&path, "proxy=http://127.0.0.1:7890").unwrap();
+
+            fs::remove_file(&path).unwrap();
+            assert!(!is_created_and_unchanged(&path));
+
+            forget_created(&path).unwrap();
+            assert!(!is_created(&path));
+            assert!(load_applied_state().created_files.is_empty());
+        });
+    }
+}
@@ -0,0 +1,289 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::applied_state;
+use crate::config_manager::{self, ApplyAction, ApplyResult};
+use crate::error_report;
+use crate::file_lock;
+use crate::history::{self, OperationKind};
+use crate::i18n::Message;
+use crate::paths::{self, Environment};
+use crate::profile_manager;
+
+/// 临时开启的代理到期自动关闭后发送的事件，载荷为 AutoReverted
+pub const AUTO_REVERTED_EVENT: &str = "proxy-auto-reverted";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoReverted {
+    pub results: Vec<ApplyResult>,
+}
+
+/// 等待自动关闭的软件 -> 计时器编号（同一软件再次临时开启时替换为新的计时器）
+static PENDING: Mutex<BTreeMap<String, u64>> = Mutex::new(BTreeMap::new());
+
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(1);
+
+/// 自动关闭的到期时间文件（软件 -> Unix 时间戳，秒），退出程序后下次启动时继续处理
+/// 位置: %LOCALAPPDATA%\proxy-manager\pending_reverts.json
+fn deadlines_path() -> Option<PathBuf> {
+    paths::data_dir().map(|p| p.join("pending_reverts.json"))
+}
+
+fn read_deadlines(path: &PathBuf) -> BTreeMap<String, u64> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 修改保存的到期时间（写入失败只记录错误日志：程序运行期间的计时不受影响）
+fn update_deadlines(update: impl FnOnce(&mut BTreeMap<String, u64>)) {
+    let result = (|| -> Result<(), Message> {
+        let path = deadlines_path().ok_or_else(|| tr!("backup_dir_unavailable"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let _guard = file_lock::lock_path(&path)?;
+        let mut deadlines = read_deadlines(&path);
+        update(&mut deadlines);
+        let content = serde_json::to_string_pretty(&deadlines).map_err(|e| e.to_string())?;
+        fs::write(&path, content).map_err(|e| e.to_string().into())
+    })();
+    if let Err(e) = result {
+        error_report::log_error(&format!("保存自动关闭时间失败: {}", e));
+    }
+}
+
+/// 启动时继续处理上次退出前安排的自动关闭：已到期的立即在后台关闭，其余按剩余时间计时
+pub fn resume(app: AppHandle) {
+    resume_pending(move |results| {
+        let _ = app.emit(AUTO_REVERTED_EVENT, AutoReverted { results });
+    });
+}
+
+fn resume_pending(on_reverted: impl Fn(Vec<ApplyResult>) + Clone + Send + 'static) {
+    let Some(path) = deadlines_path() else {
+        return;
+    };
+    let deadlines = {
+        let _guard = file_lock::lock_path(&path);
+        read_deadlines(&path)
+    };
+
+    // 同一时刻到期的软件一起关闭
+    let mut by_deadline: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for (software, deadline) in deadlines {
+        by_deadline.entry(deadline).or_default().push(software);
+    }
+    let now = applied_state::now_secs();
+    for (deadline, software) in by_deadline {
+        let remaining = Duration::from_secs(deadline.saturating_sub(now));
+        start_timer(software, remaining, on_reverted.clone());
+    }
+}
+
+/// 在 duration 后关闭本次开启的软件：只包括成功且修改了配置的软件，
+/// 原本就已开启（未修改配置）的软件到期后不会被关闭
+pub fn schedule_revert(app: AppHandle, results: &[ApplyResult], duration: Duration) {
+    schedule(results, duration, move |results| {
+        let _ = app.emit(AUTO_REVERTED_EVENT, AutoReverted { results });
+    });
+}
+
+/// 安排自动关闭，到期关闭后把结果交给 on_reverted（全部被取消时不调用）
+fn schedule(
+    results: &[ApplyResult],
+    duration: Duration,
+    on_reverted: impl FnOnce(Vec<ApplyResult>) + Send + 'static,
+) {
+    let software: Vec<String> = results
+        .iter()
+        .filter(|r| r.success && r.action == ApplyAction::Changed)
+        .map(|r| r.software.clone())
+        .collect();
+    if software.is_empty() {
+        return;
+    }
+
+    let deadline = applied_state::now_secs() + duration.as_secs();
+    update_deadlines(|deadlines| {
+        for name in &software {
+            deadlines.insert(name.clone(), deadline);
+        }
+    });
+    start_timer(software, duration, on_reverted);
+}
+
+/// 启动计时线程，到期时关闭仍由该计时器负责的软件
+fn start_timer(
+    software: Vec<String>,
+    duration: Duration,
+    on_reverted: impl FnOnce(Vec<ApplyResult>) + Send + 'static,
+) {
+    let timer = NEXT_TIMER.fetch_add(1, Ordering::SeqCst);
+    {
+        let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+        for name in &software {
+            pending.insert(name.clone(), timer);
+        }
+    }
+
+    // 计时线程使用安排时的环境
+    let env = Environment::current();
+    std::thread::spawn(move || {
+        std::thread::sleep(duration);
+
+        // 期间被取消或被新计时器替换的软件不再关闭
+        let due: Vec<String> = {
+            let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+            software
+                .into_iter()
+                .filter(|name| {
+                    let due = pending.get(name) == Some(&timer);
+                    if due {
+                        pending.remove(name);
+                    }
+                    due
+                })
+                .collect()
+        };
+        if due.is_empty() {
+            return;
+        }
+
+        let results = env
+            .run(|| config_manager::disable_proxy(&due, true, None))
+            .unwrap_or_else(|e| {
+                let config = env.run(profile_manager::load_user_config);
+                due.iter()
+                    .map(|name| ApplyResult::from_result(&config, name, Err(e.clone())))
+                    .collect()
+            });
+        env.run(|| {
+            history::record(OperationKind::Disable, None, &results);
+            update_deadlines(|deadlines| {
+                for name in &due {
+                    deadlines.remove(name);
+                }
+            });
+        });
+        on_reverted(results);
+    });
+}
+
+/// 取消软件的自动关闭（手动开启、关闭或重置时调用）
+pub fn cancel(software_list: &[String]) {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    if !software_list.iter().any(|name| pending.contains_key(name)) {
+        return;
+    }
+    for name in software_list {
+        pending.remove(name);
+    }
+    update_deadlines(|deadlines| {
+        for name in software_list {
+            deadlines.remove(name);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_manager::ProxySettings;
+    use std::fs;
+    use std::sync::mpsc;
+
+    const GITCONFIG: &str = "[user]\n\tname = Tester\n";
+
+    fn enable_git(env: &Environment) -> Vec<ApplyResult> {
+        let proxy = ProxySettings {
+            http_proxy: "http://127.0.0.1:7890".to_string(),
+            https_proxy: "http://127.0.0.1:7890".to_string(),
+            ..ProxySettings::default()
+        };
+        env.run(|| config_manager::enable_proxy(&["Git".to_string()], &proxy, "Clash"))
+            .unwrap()
+    }
+
+    #[test]
+    fn config_is_restored_after_deadline() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let gitconfig = home.path().join(".gitconfig");
+        fs::write(&gitconfig, GITCONFIG).unwrap();
+
+        let results = enable_git(&env);
+        assert!(fs::read_to_string(&gitconfig).unwrap().contains("7890"));
+
+        let (tx, rx) = mpsc::channel();
+        env.run(|| {
+            schedule(&results, Duration::from_millis(50), move |results| {
+                let _ = tx.send(results);
+            })
+        });
+        let reverted = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(reverted.len(), 1);
+        assert!(reverted[0].success, "{}", reverted[0].message);
+        assert_eq!(fs::read_to_string(&gitconfig).unwrap(), GITCONFIG);
+    }
+
+    #[test]
+    fn cancelled_revert_leaves_proxy_enabled() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let gitconfig = home.path().join(".gitconfig");
+        fs::write(&gitconfig, GITCONFIG).unwrap();
+
+        let mut results = enable_git(&env);
+        // 使用不会与其他测试冲突的软件名，取消只针对本计时器
+        results[0].software = "Git (cancelled)".to_string();
+        let (tx, rx) = mpsc::channel();
+        env.run(|| {
+            schedule(&results, Duration::from_millis(50), move |results| {
+                let _ = tx.send(results);
+            });
+            cancel(&["Git (cancelled)".to_string()]);
+            assert!(read_deadlines(&deadlines_path().unwrap()).is_empty());
+        });
+
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+        assert!(fs::read_to_string(&gitconfig).unwrap().contains("7890"));
+    }
+
+    #[test]
+    fn overdue_revert_runs_on_resume() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let npmrc = home.path().join(".npmrc");
+        fs::write(&npmrc, "registry=https://registry.npmjs.org/\n").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        env.run(|| {
+            let proxy = ProxySettings {
+                http_proxy: "http://127.0.0.1:7890".to_string(),
+                ..ProxySettings::default()
+            };
+            config_manager::enable_proxy(&["npm".to_string()], &proxy, "Clash").unwrap();
+            // 模拟上次退出前安排、已经到期的自动关闭
+            update_deadlines(|deadlines| {
+                deadlines.insert("npm".to_string(), applied_state::now_secs() - 60);
+            });
+            resume_pending(move |results| {
+                let _ = tx.send(results);
+            });
+        });
+
+        let reverted = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(reverted.len(), 1);
+        assert!(reverted[0].success, "{}", reverted[0].message);
+        assert!(!fs::read_to_string(&npmrc).unwrap().contains("7890"));
+        env.run(|| assert!(read_deadlines(&deadlines_path().unwrap()).is_empty()));
+    }
+}
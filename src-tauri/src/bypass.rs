@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+use crate::i18n::Message;
+
+/// Windows 等使用的"不含点的本地主机名"标记
+pub const LOCAL_MARKER: &str = "<local>";
+
+/// 绕过列表在各软件中的格式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BypassFormat {
+    NoProxy, // 环境变量 NO_PROXY 等：逗号分隔，".example.com" 匹配子域名
+    WinInet, // Windows ProxyOverride：分号分隔，"*.example.com"，"<local>" 表示本地主机名
+    Java,    // Maven/Gradle 的 nonProxyHosts：竖线分隔，"*.example.com"
+    Json,    // VSCode 的 http.noProxy：JSON 字符串数组
+    Firefox, // Firefox 的 network.proxy.no_proxies_on：逗号分隔，".example.com"，支持 "<local>"
+}
+
+/// 规范化的绕过列表：主机模式（子域名统一为 "*.example.com"，小写、去重）
+/// 和是否绕过本地主机名（不含点的主机名，只有 WinINET 和 Firefox 能表示）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BypassList {
+    pub hosts: Vec<String>,
+    pub local: bool,
+}
+
+/// 单个条目的规范形式，空条目返回 None
+fn canonical_host(entry: &str) -> Option<String> {
+    let entry = entry.trim().trim_matches(['"', '\'']).trim().to_lowercase();
+    if entry.is_empty() || entry == "." {
+        return None;
+    }
+    Some(match entry.strip_prefix('.') {
+        Some(domain) => format!("*.{}", domain),
+        None => entry,
+    })
+}
+
+/// 按 /8、/16、/24 对齐的 IPv4 网段改写为通配形式（如 10.0.0.0/8 -> 10.*），
+/// 供不支持 CIDR 的 WinINET 和 Java 使用；其他形式原样返回
+fn cidr_as_wildcard(host: &str) -> String {
+    let Some((addr, prefix)) = host.split_once('/') else {
+        return host.to_string();
+    };
+    let octets: Vec<&str> = addr.split('.').collect();
+    let keep = match prefix {
+        "8" => 1,
+        "16" => 2,
+        "24" => 3,
+        _ => return host.to_string(),
+    };
+    if octets.len() != 4 || octets.iter().any(|o| o.parse::<u8>().is_err()) {
+        return host.to_string();
+    }
+    format!("{}.*", octets[..keep].join("."))
+}
+
+impl BypassList {
+    /// 从条目列表构造（"<local>" 条目设置 local 标记）
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut list = Self::default();
+        for entry in entries {
+            if entry.trim().eq_ignore_ascii_case(LOCAL_MARKER) {
+                list.local = true;
+            } else if let Some(host) = canonical_host(entry) {
+                if !list.hosts.contains(&host) {
+                    list.hosts.push(host);
+                }
+            }
+        }
+        list
+    }
+
+    /// 解析逗号分隔的列表（ProxySettings.no_proxy 的内部形式，可以包含 "<local>"）
+    pub fn from_no_proxy(value: &str) -> Self {
+        Self::from_entries(value.split(','))
+    }
+
+    /// 按软件的格式解析已有的绕过列表
+    pub fn parse(value: &str, format: BypassFormat) -> Result<Self, Message> {
+        Ok(match format {
+            BypassFormat::NoProxy | BypassFormat::Firefox => Self::from_no_proxy(value),
+            BypassFormat::WinInet => Self::from_entries(value.split(';')),
+            BypassFormat::Java => Self::from_entries(value.split('|')),
+            BypassFormat::Json => {
+                let hosts: Vec<String> = serde_json::from_str(value)
+                    .map_err(|e| tr!("bypass_invalid_json", error = e))?;
+                Self::from_entries(hosts.iter().map(String::as_str))
+            }
+        })
+    }
+
+    /// 子域名写为 ".example.com" 的主机列表（curl、Go、Python 等的 no_proxy 语义）
+    pub fn suffix_hosts(&self) -> Vec<String> {
+        self.hosts
+            .iter()
+            .map(|host| match host.strip_prefix("*.") {
+                Some(domain) => format!(".{}", domain),
+                None => host.clone(),
+            })
+            .collect()
+    }
+
+    /// 子域名写为 "*.example.com"、网段写为通配的主机列表
+    pub fn wildcard_hosts(&self) -> Vec<String> {
+        self.hosts
+            .iter()
+            .map(|host| cidr_as_wildcard(host))
+            .collect()
+    }
+
+    /// 按软件的格式生成绕过列表；无法表示本地主机名的格式省略该标记
+    pub fn serialize(&self, format: BypassFormat) -> String {
+        let local = self.local.then(|| LOCAL_MARKER.to_string());
+        match format {
+            BypassFormat::NoProxy => self.suffix_hosts().join(","),
+            BypassFormat::WinInet => self
+                .wildcard_hosts()
+                .into_iter()
+                .chain(local)
+                .collect::<Vec<_>>()
+                .join(";"),
+            BypassFormat::Java => self.wildcard_hosts().join("|"),
+            BypassFormat::Json => serde_json::Value::from(self.suffix_hosts()).to_string(),
+            BypassFormat::Firefox => self
+                .suffix_hosts()
+                .into_iter()
+                .chain(local)
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    /// 是否包含与 entry 等价的条目（如 ".example.com" 与 "*.example.com"）
+    pub fn contains(&self, entry: &str) -> bool {
+        if entry.trim().eq_ignore_ascii_case(LOCAL_MARKER) {
+            return self.local;
+        }
+        canonical_host(entry).is_some_and(|host| self.hosts.contains(&host))
+    }
+}
+
+/// 两个同格式的绕过列表是否等价（不考虑顺序和写法，无法解析时按原文比较），
+/// 用于判断已有的环境变量是否已是目标值
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub fn equivalent(a: &str, b: &str, format: BypassFormat) -> bool {
+    match (BypassList::parse(a, format), BypassList::parse(b, format)) {
+        (Ok(a), Ok(b)) => {
+            a.local == b.local
+                && a.hosts.len() == b.hosts.len()
+                && a.hosts.iter().all(|host| b.hosts.contains(host))
+        }
+        _ => a == b,
+    }
+}
+
+/// 把一种格式的绕过列表转换为另一种格式，用于导入其他软件中已有的设置
+pub fn convert(value: &str, from: BypassFormat, to: BypassFormat) -> Result<String, Message> {
+    Ok(BypassList::parse(value, from)?.serialize(to))
+}
@@ -0,0 +1,101 @@
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+use crate::file_lock;
+use crate::i18n::Message;
+use crate::paths;
+use crate::profile_manager::{self, UserConfig};
+use crate::tray;
+
+/// 配置文件被外部修改且解析成功时发送的事件，载荷为新的 UserConfig
+pub const CONFIG_CHANGED_EVENT: &str = "user-config-changed";
+
+/// 配置文件被外部修改但解析失败时发送的事件，载荷为错误信息
+const CONFIG_INVALID_EVENT: &str = "user-config-invalid";
+
+/// 是否处理配置文件变更（可在设置中关闭）
+static WATCH_ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// 文件监听器需要一直持有，释放后监听停止
+static WATCHER: Mutex<Option<RecommendedWatcher>> = Mutex::new(None);
+
+/// 开启或关闭配置文件监听
+pub fn set_enabled(enabled: bool) {
+    WATCH_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// 开始监听配置文件所在目录（编辑器保存时常常替换整个文件，直接监听文件会丢失事件）
+pub fn start(app: AppHandle) -> Result<(), Message> {
+    let config_path = paths::user_config_path();
+    let watch_dir = config_path
+        .parent()
+        .ok_or_else(|| tr!("config_path_unavailable"))?
+        .to_path_buf();
+    fs::create_dir_all(&watch_dir).map_err(|e| tr!("config_dir_create_failed", error = e))?;
+
+    // 记录当前内容，避免启动后的首次事件被误认为外部修改
+    if let Ok(content) = fs::read_to_string(&config_path) {
+        profile_manager::remember_config_content(&content);
+    }
+
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        let Ok(event) = res else {
+            return;
+        };
+        // 只比较文件名：部分平台上报的是解析过符号链接的路径
+        if !event
+            .paths
+            .iter()
+            .any(|p| p.file_name() == config_path.file_name())
+        {
+            return;
+        }
+
+        // 配置组或映射可能已变化（包括本程序自己的写入），托盘菜单总是需要更新
+        tray::refresh(&app);
+        if WATCH_ENABLED.load(Ordering::SeqCst) {
+            handle_config_change(&app, &config_path);
+        }
+    })
+    .map_err(|e| e.to_string())?;
+
+    watcher
+        .watch(&watch_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| e.to_string())?;
+
+    if let Ok(mut slot) = WATCHER.lock() {
+        *slot = Some(watcher);
+    }
+    Ok(())
+}
+
+/// 处理配置文件变更：内容与本程序最近写入的一致时忽略，否则重新解析并通知前端
+fn handle_config_change(app: &AppHandle, config_path: &Path) {
+    // 与本程序的写入互斥，避免读到写了一半的文件
+    let content = {
+        let _guard = file_lock::lock_file(config_path);
+        match fs::read_to_string(config_path) {
+            Ok(content) => content,
+            Err(_) => return, // 文件被删除或正在替换
+        }
+    };
+
+    if profile_manager::is_known_config_content(&content) {
+        return;
+    }
+    profile_manager::remember_config_content(&content);
+
+    match serde_json::from_str::<UserConfig>(&content) {
+        Ok(config) => {
+            let _ = crate::i18n::set_locale(&config.locale);
+            let _ = app.emit(CONFIG_CHANGED_EVENT, config);
+        }
+        Err(e) => {
+            let _ = app.emit(CONFIG_INVALID_EVENT, tr!("config_parse_failed", error = e));
+        }
+    }
+}
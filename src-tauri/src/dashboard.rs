@@ -0,0 +1,144 @@
+use serde::Serialize;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::applied_state::{self, AppliedEntry, AppliedState};
+use crate::config_manager::{self, SoftwareConfig};
+use crate::history::{self, OperationRecord};
+use crate::paths::Environment;
+use crate::port_detector::{self, PortConflict, VpnStatus};
+use crate::profile_manager::{self, UserConfig};
+use crate::stale_check::{self, StaleReport};
+
+/// 较慢的部分（运行系统命令、读取配置文件）最多等待的时间，超时的部分返回 loading，
+/// 前端可以再调用对应的单独命令获取
+const SECTION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 仪表盘中的一部分：ready 时 data 与对应的单独命令返回值相同
+#[derive(Debug, Serialize)]
+#[serde(tag = "state", content = "data", rename_all = "lowercase")]
+pub enum Section<T> {
+    Ready(T),
+    Loading,
+}
+
+/// 主界面所需的全部状态
+#[derive(Debug, Serialize)]
+pub struct Dashboard {
+    pub software: Vec<SoftwareConfig>,           // 同 get_software_list
+    pub user_config: UserConfig,                 // 同 get_user_config
+    pub vpns: Section<Vec<VpnStatus>>,           // 同 get_vpn_list_with_status
+    pub applied: AppliedState,                   // 同 get_applied_state
+    pub drifted: Section<Vec<String>>,           // 已应用后配置被外部修改的软件
+    pub last_operation: Option<OperationRecord>, // 同 get_operation_history 的最后一条
+    pub stale: Section<Vec<StaleReport>>,        // 同 check_stale_proxies
+    pub port_conflicts: Section<Vec<PortConflict>>, // 同 check_port_conflicts
+}
+
+/// 一部分正在后台进行的刷新（启动时的环境和接收结果的通道）
+/// 上次超时未完成时，下次获取继续等待同一个线程，而不是再启动一个
+struct InFlight<T>(Mutex<Option<(Environment, mpsc::Receiver<T>)>>);
+
+impl<T: Send + 'static> InFlight<T> {
+    const fn new() -> Self {
+        InFlight(Mutex::new(None))
+    }
+
+    /// 当前环境下没有正在进行的刷新时，在后台线程（使用当前环境）启动一次
+    fn start(&self, run: impl FnOnce() -> T + Send + 'static) {
+        let env = Environment::current();
+        let mut slot = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if slot
+            .as_ref()
+            .is_some_and(|(started_in, _)| *started_in == env)
+        {
+            return;
+        }
+        let (sender, receiver) = mpsc::channel();
+        let worker_env = env.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(worker_env.run(run));
+        });
+        *slot = Some((env, receiver));
+    }
+
+    /// 在截止时间前等待结果；超时的刷新保留给下次获取继续等待
+    fn wait(&self, deadline: Instant) -> Section<T> {
+        let mut slot = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((_, receiver)) = slot.as_ref() else {
+            return Section::Loading;
+        };
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(value) => {
+                *slot = None;
+                Section::Ready(value)
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => Section::Loading,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                *slot = None;
+                Section::Loading
+            }
+        }
+    }
+}
+
+static VPNS: InFlight<Vec<VpnStatus>> = InFlight::new();
+static STALE: InFlight<Vec<StaleReport>> = InFlight::new();
+static PORT_CONFLICTS: InFlight<Vec<PortConflict>> = InFlight::new();
+static DRIFTED: InFlight<Vec<String>> = InFlight::new();
+
+/// 已应用状态中配置内容与应用时不一致的软件
+fn drifted_software(applied: &[AppliedEntry]) -> Vec<String> {
+    let config = profile_manager::load_user_config();
+    applied
+        .iter()
+        .filter(|entry| {
+            config_manager::read_software_config(&config, &entry.software, None, false)
+                .is_ok_and(|content| applied_state::content_hash(&content) != entry.content_hash)
+        })
+        .map(|entry| entry.software.clone())
+        .collect()
+}
+
+/// 汇总主界面所需的状态：互不依赖的部分并行获取，每部分最多等待 SECTION_TIMEOUT
+pub fn get_dashboard() -> Dashboard {
+    let user_config = profile_manager::load_user_config();
+    let applied = config_manager::load_applied_status(&user_config);
+
+    let deadline = Instant::now() + SECTION_TIMEOUT;
+    VPNS.start(port_detector::get_vpn_list_with_status);
+    STALE.start(stale_check::check_stale_proxies);
+    let profile_ports = profile_manager::profile_ports(&user_config);
+    PORT_CONFLICTS.start(move || port_detector::check_port_conflicts(&profile_ports));
+    let entries = applied.entries.clone();
+    DRIFTED.start(move || drifted_software(&entries));
+
+    let software = config_manager::get_software_list_with_custom(&user_config);
+    let last_operation = history::last_operation();
+
+    Dashboard {
+        software,
+        user_config,
+        vpns: VPNS.wait(deadline),
+        applied,
+        drifted: DRIFTED.wait(deadline),
+        last_operation,
+        stale: STALE.wait(deadline),
+        port_conflicts: PORT_CONFLICTS.wait(deadline),
+    }
+}
+
+/// 后台任务失败时返回的仪表盘：只包含无需等待的部分
+pub fn get_dashboard_loading() -> Dashboard {
+    let user_config = profile_manager::load_user_config();
+    Dashboard {
+        software: config_manager::get_software_list_with_custom(&user_config),
+        vpns: Section::Loading,
+        applied: config_manager::load_applied_status(&user_config),
+        user_config,
+        drifted: Section::Loading,
+        last_operation: history::last_operation(),
+        stale: Section::Loading,
+        port_conflicts: Section::Loading,
+    }
+}
@@ -0,0 +1,408 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::i18n::Message;
+
+/// 以管理员权限重新启动自身时使用的参数，后接请求文件路径
+pub const ELEVATED_ARG: &str = "--apply-elevated";
+
+/// 需要管理员权限执行的操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ElevatedOp {
+    WriteFile { path: String, content: String },
+}
+
+/// 提权进程返回的单个操作结果（与请求中的操作一一对应）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElevatedOpResult {
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+thread_local! {
+    /// 当前线程正在进行的批量操作（为 None 时不收集，直接写入）
+    static PENDING: RefCell<Option<Vec<ElevatedOp>>> = const { RefCell::new(None) };
+}
+
+/// 写入探测：目标文件（或不存在时其最近的已存在目录）当前用户没有写入权限时返回 true
+pub fn needs_elevation(path: &Path) -> bool {
+    if path.exists() {
+        return matches!(
+            OpenOptions::new().append(true).open(path),
+            Err(e) if e.kind() == ErrorKind::PermissionDenied
+        );
+    }
+
+    let Some(dir) = path.ancestors().skip(1).find(|dir| dir.is_dir()) else {
+        return false;
+    };
+    let probe = dir.join(format!(".proxy-manager-probe-{}", std::process::id()));
+    match fs::write(&probe, "") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            false
+        }
+        Err(e) => e.kind() == ErrorKind::PermissionDenied,
+    }
+}
+
+/// 批量操作：期间需要提权的写入先收集起来，结束时只弹出一次提权确认
+/// 离开作用域前应调用 finish，否则收集的写入会被丢弃
+pub struct Batch {
+    started: bool, // 外层已有批量操作时，本批次并入外层，由外层执行
+}
+
+impl Batch {
+    pub fn begin() -> Self {
+        let started = PENDING.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            if pending.is_some() {
+                return false;
+            }
+            *pending = Some(Vec::new());
+            true
+        });
+        Batch { started }
+    }
+
+    /// 以管理员权限执行收集的写入，返回 文件路径 -> 失败原因（写入成功的文件不在其中）
+    pub fn finish(mut self) -> HashMap<PathBuf, Message> {
+        let ops = self.take_pending();
+        if ops.is_empty() {
+            return HashMap::new();
+        }
+
+        let results = match run_elevated(&ops) {
+            Ok(results) => results,
+            Err(e) => vec![
+                ElevatedOpResult {
+                    success: false,
+                    error: Some(e.to_string()),
+                };
+                ops.len()
+            ],
+        };
+
+        let mut failures = HashMap::new();
+        for (index, op) in ops.into_iter().enumerate() {
+            let error = match results.get(index) {
+                Some(result) if result.success => continue,
+                Some(result) => result.error.clone().unwrap_or_default().into(),
+                None => tr!("elevation_no_result"),
+            };
+            let ElevatedOp::WriteFile { path, .. } = op;
+            failures.insert(PathBuf::from(path), error);
+        }
+        failures
+    }
+
+    fn take_pending(&mut self) -> Vec<ElevatedOp> {
+        if !std::mem::take(&mut self.started) {
+            return vec![];
+        }
+        PENDING
+            .with(|pending| pending.borrow_mut().take())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Batch {
+    fn drop(&mut self) {
+        self.take_pending();
+    }
+}
+
+/// 写入配置文件：批量操作期间没有写入权限时推迟到批次结束后提权写入，否则直接写入
+pub fn write_file(path: &Path, content: &str) -> Result<(), Message> {
+    let deferred = PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        match pending.as_mut() {
+            Some(writes) if needs_elevation(path) => {
+                writes.push(ElevatedOp::WriteFile {
+                    path: path.to_string_lossy().into_owned(),
+                    content: content.to_string(),
+                });
+                true
+            }
+            _ => false,
+        }
+    });
+    if deferred {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, content).map_err(|e| e.to_string().into())
+}
+
+/// 提权请求文件对应的结果文件（与请求文件位于同一私有目录）
+fn result_path(request_path: &Path) -> PathBuf {
+    request_path.with_file_name("result.json")
+}
+
+/// 在临时目录中新建只有当前用户可以访问的目录（unix 上权限为 0700），用于交换请求和结果；
+/// 目录已存在时失败，避免使用其他用户预先创建的目录
+fn create_private_dir() -> Result<PathBuf, Message> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let dir = std::env::temp_dir().join(format!(
+        "proxy-manager-elevated-{}-{}",
+        std::process::id(),
+        nanos
+    ));
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder.create(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// 新建文件并写入内容：文件已存在（包括符号链接）时失败，不会跟随或覆盖已有的路径
+fn write_new_file(path: &Path, content: &str, mode: u32) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(mode);
+    #[cfg(not(unix))]
+    let _ = mode;
+    options.open(path)?.write_all(content.as_bytes())
+}
+
+/// 把操作写入私有目录中的请求文件，以管理员权限重新启动自身执行，并读取返回的结果
+fn run_elevated(ops: &[ElevatedOp]) -> Result<Vec<ElevatedOpResult>, Message> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let dir = create_private_dir()?;
+    let result = run_elevated_in(&exe, &dir, ops);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn run_elevated_in(
+    exe: &Path,
+    dir: &Path,
+    ops: &[ElevatedOp],
+) -> Result<Vec<ElevatedOpResult>, Message> {
+    let request_path = dir.join("request.json");
+    let request = serde_json::to_string(ops).map_err(|e| e.to_string())?;
+    write_new_file(&request_path, &request, 0o600).map_err(|e| e.to_string())?;
+
+    match elevated_command(exe, &request_path).status() {
+        Ok(status) if status.success() => {}
+        Ok(_) => return Err(tr!("elevation_cancelled")),
+        Err(e) => return Err(tr!("elevation_failed", error = e)),
+    }
+
+    let response =
+        fs::read_to_string(result_path(&request_path)).map_err(|_| tr!("elevation_no_result"))?;
+    serde_json::from_str(&response).map_err(|_| tr!("elevation_no_result"))
+}
+
+/// Windows：通过 PowerShell 的 Start-Process -Verb RunAs 触发 UAC 提示
+#[cfg(target_os = "windows")]
+fn elevated_command(exe: &Path, request_path: &Path) -> Command {
+    let quote = |s: &Path| format!("'{}'", s.to_string_lossy().replace('\'', "''"));
+    let script = format!(
+        "$p = Start-Process -FilePath {} -ArgumentList '{}',{} -Verb RunAs -Wait -PassThru -WindowStyle Hidden; exit $p.ExitCode",
+        quote(exe),
+        ELEVATED_ARG,
+        // 路径可能包含空格，作为参数传递时需要再加一层双引号
+        quote(Path::new(&format!("\"{}\"", request_path.to_string_lossy())))
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+    command
+}
+
+/// macOS：通过 osascript 弹出管理员密码提示
+#[cfg(target_os = "macos")]
+fn elevated_command(exe: &Path, request_path: &Path) -> Command {
+    let shell_quote = |s: &Path| format!("'{}'", s.to_string_lossy().replace('\'', "'\\''"));
+    let shell = format!(
+        "{} {} {}",
+        shell_quote(exe),
+        ELEVATED_ARG,
+        shell_quote(request_path)
+    );
+    let script = format!(
+        "do shell script \"{}\" with administrator privileges",
+        shell.replace('\\', "\\\\").replace('"', "\\\"")
+    );
+    let mut command = Command::new("osascript");
+    command.args(["-e", &script]);
+    command
+}
+
+/// Linux：通过 pkexec 弹出 polkit 认证提示
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn elevated_command(exe: &Path, request_path: &Path) -> Command {
+    let mut command = Command::new("pkexec");
+    command.arg(exe).arg(ELEVATED_ARG).arg(request_path);
+    command
+}
+
+/// 命令行中的提权请求文件路径（以提权模式启动时）
+pub fn elevated_request_arg() -> Option<PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == ELEVATED_ARG {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// 发起提权的用户（pkexec 和 sudo 通过环境变量传递；无法得知时为 None）
+#[cfg(unix)]
+fn caller_uid() -> Option<u32> {
+    ["PKEXEC_UID", "SUDO_UID"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok()?.parse().ok())
+}
+
+/// 读取请求文件前的校验：文件和所在目录都不能是符号链接，目录和文件只有所有者可以访问，
+/// 所有者与目录的所有者（以及已知的发起用户）一致；否则可能是其他用户伪造或替换的请求
+fn read_request_file(request_path: &Path) -> Result<String, Message> {
+    let untrusted = || tr!("elevation_request_untrusted", path = request_path.display());
+    let file_meta = fs::symlink_metadata(request_path).map_err(|e| e.to_string())?;
+    if !file_meta.file_type().is_file() {
+        return Err(untrusted());
+    }
+
+    #[cfg(unix)]
+    {
+        let dir = request_path.parent().ok_or_else(untrusted)?;
+        let dir_meta = fs::symlink_metadata(dir).map_err(|e| e.to_string())?;
+        let private = dir_meta.file_type().is_dir()
+            && dir_meta.mode() & 0o077 == 0
+            && file_meta.mode() & 0o077 == 0
+            && file_meta.uid() == dir_meta.uid()
+            && caller_uid().is_none_or(|uid| uid == file_meta.uid());
+        if !private {
+            return Err(untrusted());
+        }
+    }
+
+    let mut file = fs::File::open(request_path).map_err(|e| e.to_string())?;
+    // 打开的必须是刚才检查的那个文件
+    #[cfg(unix)]
+    {
+        let opened = file.metadata().map_err(|e| e.to_string())?;
+        if (opened.dev(), opened.ino()) != (file_meta.dev(), file_meta.ino()) {
+            return Err(untrusted());
+        }
+    }
+    let mut request = String::new();
+    file.read_to_string(&mut request)
+        .map_err(|e| e.to_string())?;
+    Ok(request)
+}
+
+/// 提权进程：执行请求文件中的操作，把结果写入结果文件（不启动界面）
+pub fn apply_elevated_request(request_path: &Path) -> Result<(), Message> {
+    let request = read_request_file(request_path)?;
+    let ops: Vec<ElevatedOp> = serde_json::from_str(&request).map_err(|e| e.to_string())?;
+
+    let results: Vec<ElevatedOpResult> = ops
+        .iter()
+        .map(|op| {
+            let result = match op {
+                ElevatedOp::WriteFile { path, content } => {
+                    let path = Path::new(path);
+                    path.parent()
+                        .map(fs::create_dir_all)
+                        .unwrap_or(Ok(()))
+                        .and_then(|_| fs::write(path, content))
+                }
+            };
+            ElevatedOpResult {
+                success: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            }
+        })
+        .collect();
+
+    // 结果文件必须是新建的：已存在的文件或符号链接不会被跟随或覆盖
+    let response = serde_json::to_string(&results).map_err(|e| e.to_string())?;
+    write_new_file(&result_path(request_path), &response, 0o644).map_err(|e| e.to_string().into())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    fn request(dir: &Path, target: &Path) -> PathBuf {
+        let ops = vec![ElevatedOp::WriteFile {
+            path: target.to_string_lossy().into_owned(),
+            content: "proxy=http://127.0.0.1:7890".to_string(),
+        }];
+        let path = dir.join("request.json");
+        write_new_file(&path, &serde_json::to_string(&ops).unwrap(), 0o600).unwrap();
+        path
+    }
+
+    #[test]
+    fn request_in_private_dir_is_applied() {
+        let dir = create_private_dir().unwrap();
+        let target = dir.join(".npmrc");
+        let request_path = request(&dir, &target);
+
+        apply_elevated_request(&request_path).unwrap();
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "proxy=http://127.0.0.1:7890"
+        );
+        let results: Vec<ElevatedOpResult> =
+            serde_json::from_str(&fs::read_to_string(result_path(&request_path)).unwrap()).unwrap();
+        assert!(results[0].success);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn request_readable_by_others_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+        let target = dir.path().join(".npmrc");
+        let request_path = request(dir.path(), &target);
+
+        assert_eq!(
+            apply_elevated_request(&request_path),
+            Err(tr!(
+                "elevation_request_untrusted",
+                path = request_path.display()
+            ))
+        );
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn symlinked_request_and_existing_result_are_not_followed() {
+        let dir = create_private_dir().unwrap();
+        let target = dir.join(".npmrc");
+        let real = request(&dir, &target);
+        let linked = dir.join("linked.json");
+        symlink(&real, &linked).unwrap();
+        assert!(apply_elevated_request(&linked).is_err());
+        assert!(!target.exists());
+
+        // 结果路径已被替换为指向其他文件的符号链接：不覆盖链接的目标
+        let victim = dir.join("victim");
+        fs::write(&victim, "keep").unwrap();
+        symlink(&victim, result_path(&real)).unwrap();
+        assert!(apply_elevated_request(&real).is_err());
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Emitter};
+
+use crate::applied_state;
+use crate::paths;
+
+/// 发生未处理的错误（如 panic）时发送的事件，载荷为 AppError
+const APP_ERROR_EVENT: &str = "app-error";
+
+/// 错误日志超过该大小时轮换为 error.log.old（1 MB）
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// 用于发送事件的应用句柄（setup 完成前为空，此时只写日志）
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// 发送给前端的错误信息（只写入本地日志，不会上传到任何地方）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppError {
+    pub kind: String, // "panic" 或 "error"
+    pub message: String,
+    pub log_path: Option<String>,
+}
+
+/// 错误日志路径
+/// 位置: %LOCALAPPDATA%\proxy-manager\error.log
+pub fn get_log_path() -> Option<PathBuf> {
+    paths::data_dir().map(|p| p.join("error.log"))
+}
+
+/// 追加一条错误日志（失败只打印到标准错误，不能再引发 panic）
+pub fn log_error(message: &str) {
+    let Some(log_path) = get_log_path() else {
+        eprintln!("{}", message);
+        return;
+    };
+    if let Some(parent) = log_path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if fs::metadata(&log_path).map(|m| m.len()).unwrap_or(0) > MAX_LOG_SIZE {
+        let _ = fs::rename(&log_path, log_path.with_extension("log.old"));
+    }
+
+    let line = format!("[{}] {}\n", applied_state::now_secs(), message);
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .and_then(|mut file| file.write_all(line.as_bytes()));
+    if let Err(e) = written {
+        eprintln!("写入错误日志失败: {}: {}", e, message);
+    }
+}
+
+/// 记录错误并通知前端显示（用于后台线程等没有调用方接收 Err 的场合）
+pub fn report_error(message: &str) {
+    log_error(message);
+    emit_error("error", message);
+}
+
+fn emit_error(kind: &str, message: &str) {
+    if let Some(app) = APP.get() {
+        let _ = app.emit(
+            APP_ERROR_EVENT,
+            AppError {
+                kind: kind.to_string(),
+                message: message.to_string(),
+                log_path: get_log_path().map(|p| p.to_string_lossy().into_owned()),
+            },
+        );
+    }
+}
+
+/// 安装 panic 钩子：写入错误日志并发送 app-error 事件，之后仍交给默认钩子处理
+/// 托盘程序没有控制台，否则 panic 只会让窗口直接消失
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let message = info.to_string();
+        log_error(&message);
+        emit_error("panic", &message);
+        default_hook(info);
+    }));
+}
+
+/// 设置发送事件使用的应用句柄（在 setup 中调用）
+pub fn attach(app: AppHandle) {
+    let _ = APP.set(app);
+}
@@ -0,0 +1,112 @@
+use fs2::FileExt;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::i18n::Message;
+
+/// 等待锁的最长时间，超时返回"操作进行中"而不是让界面卡死
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 当前进程内正在被写入的路径
+struct LockTable {
+    held: Mutex<HashSet<PathBuf>>,
+    released: Condvar,
+}
+
+fn lock_table() -> &'static LockTable {
+    static TABLE: OnceLock<LockTable> = OnceLock::new();
+    TABLE.get_or_init(|| LockTable {
+        held: Mutex::new(HashSet::new()),
+        released: Condvar::new(),
+    })
+}
+
+/// 进程内路径锁，离开作用域时自动释放
+pub struct PathGuard {
+    path: PathBuf,
+}
+
+impl Drop for PathGuard {
+    fn drop(&mut self) {
+        let table = lock_table();
+        if let Ok(mut held) = table.held.lock() {
+            held.remove(&self.path);
+        }
+        table.released.notify_all();
+    }
+}
+
+/// 获取指定路径的进程内互斥锁（同一路径同一时间只允许一个写入者）
+pub fn lock_path(path: &Path) -> Result<PathGuard, Message> {
+    let table = lock_table();
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    let mut held = table
+        .held
+        .lock()
+        .map_err(|_| tr!("operation_in_progress"))?;
+
+    while held.contains(path) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(tr!("operation_in_progress"));
+        }
+        held = table
+            .released
+            .wait_timeout(held, remaining)
+            .map_err(|_| tr!("operation_in_progress"))?
+            .0;
+    }
+
+    held.insert(path.to_path_buf());
+    Ok(PathGuard {
+        path: path.to_path_buf(),
+    })
+}
+
+/// 跨进程的文件锁（防止同时运行的第二个实例写入同一文件），离开作用域时自动释放
+pub struct FileGuard {
+    file: File,
+    _path_guard: PathGuard,
+}
+
+impl Drop for FileGuard {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// 同时获取进程内锁和 `<path>.lock` 上的建议性文件锁
+pub fn lock_file(path: &Path) -> Result<FileGuard, Message> {
+    let path_guard = lock_path(path)?;
+
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    let lock_path = PathBuf::from(lock_path);
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .map_err(|e| e.to_string())?;
+
+    let deadline = Instant::now() + LOCK_TIMEOUT;
+    while file.try_lock_exclusive().is_err() {
+        if Instant::now() >= deadline {
+            return Err(tr!("operation_in_progress"));
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    Ok(FileGuard {
+        file,
+        _path_guard: path_guard,
+    })
+}
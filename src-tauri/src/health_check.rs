@@ -0,0 +1,452 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::applied_state;
+use crate::config_manager::ApplyResult;
+use crate::error_report;
+use crate::file_lock;
+use crate::history::{self, OperationKind};
+use crate::i18n::Message;
+use crate::paths;
+use crate::profile_manager::{self, ProxyProfile, ProxyScheme};
+
+/// 配置组连续检查失败达到 UNHEALTHY_AFTER 次时发送的事件，载荷为 ProfileUnhealthy
+pub const PROFILE_UNHEALTHY_EVENT: &str = "profile-unhealthy";
+
+/// 开启了 auto_failover 的软件切换到其他配置组后发送的事件，载荷为 ProfileFailover
+pub const PROFILE_FAILOVER_EVENT: &str = "profile-failover";
+
+/// 连续失败多少次后认为配置组不可用
+const UNHEALTHY_AFTER: u32 = 3;
+
+/// 最多保留的检查记录数，超出后丢弃最早的记录
+const MAX_SAMPLES: usize = 2000;
+
+/// 单次检查的连接和读写超时
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 未开启健康检查时重新读取设置的间隔
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+/// 一次检查的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSample {
+    pub timestamp: u64, // Unix 时间戳（秒）
+    pub profile: String,
+    pub latency_ms: Option<u64>, // 失败时为空
+    pub ok: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileUnhealthy {
+    pub profile: String,
+    pub failures: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProfileFailover {
+    pub from: String,
+    pub to: String,
+    pub results: Vec<ApplyResult>,
+}
+
+/// 已加载的检查记录（None 表示尚未从文件读取）
+static HISTORY: Mutex<Option<VecDeque<HealthSample>>> = Mutex::new(None);
+
+/// 配置组 -> 连续失败次数
+static FAILURES: Mutex<Option<HashMap<String, u32>>> = Mutex::new(None);
+
+/// 检查记录文件路径
+/// 位置: %LOCALAPPDATA%\proxy-manager\health_history.json
+fn history_path() -> Option<PathBuf> {
+    paths::data_dir().map(|p| p.join("health_history.json"))
+}
+
+fn read_history() -> VecDeque<HealthSample> {
+    history_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_history(history: &VecDeque<HealthSample>) -> Result<(), Message> {
+    let path = history_path().ok_or_else(|| tr!("backup_dir_unavailable"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let _guard = file_lock::lock_path(&path)?;
+    let content = serde_json::to_string(history).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string().into())
+}
+
+/// 追加检查记录并保存
+fn append_samples(samples: Vec<HealthSample>) -> Result<(), Message> {
+    let mut history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    let history = history.get_or_insert_with(read_history);
+    history.extend(samples);
+    while history.len() > MAX_SAMPLES {
+        history.pop_front();
+    }
+    write_history(history)
+}
+
+/// 配置组在 since（Unix 时间戳，秒）之后的检查记录，按时间先后排列
+pub fn get_profile_health(profile_name: &str, since: u64) -> Vec<HealthSample> {
+    let mut history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    history
+        .get_or_insert_with(read_history)
+        .iter()
+        .filter(|s| profile_manager::names_eq(&s.profile, profile_name) && s.timestamp >= since)
+        .cloned()
+        .collect()
+}
+
+/// 通过代理向目标地址发起一次隧道连接（HTTP CONNECT 或 SOCKS5 CONNECT，不发送任何数据），
+/// 返回建立隧道所用的时间。HTTPS 代理需要 TLS，只检查能否建立 TCP 连接
+fn check_profile(profile: &ProxyProfile, target: &str) -> Result<Duration, Message> {
+    let (target_host, target_port) = target
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host.trim_matches(['[', ']']), port.parse::<u16>().ok()?)))
+        .ok_or_else(|| tr!("health_target_invalid", target = target))?;
+
+    let addr = (profile.host.as_str(), profile.port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| tr!("health_host_unresolvable", host = profile.host))?;
+
+    let started = Instant::now();
+    let mut stream = TcpStream::connect_timeout(&addr, CHECK_TIMEOUT).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(CHECK_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    stream
+        .set_write_timeout(Some(CHECK_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+
+    match profile.proxy_scheme {
+        ProxyScheme::Https => {}
+        ProxyScheme::Http => {
+            let authority =
+                profile_manager::format_host(target_host) + ":" + &target_port.to_string();
+            let request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", authority);
+            stream
+                .write_all(request.as_bytes())
+                .map_err(|e| e.to_string())?;
+            // 只读取状态行的开头，如 "HTTP/1.1 200"
+            let mut status = [0u8; 12];
+            stream.read_exact(&mut status).map_err(|e| e.to_string())?;
+            let status = String::from_utf8_lossy(&status);
+            if !status.starts_with("HTTP/") || !status.ends_with(" 200") {
+                return Err(tr!("health_connect_rejected", status = status.trim()));
+            }
+        }
+        ProxyScheme::Socks5 | ProxyScheme::Socks5h => {
+            stream
+                .write_all(&[0x05, 0x01, 0x00])
+                .map_err(|e| e.to_string())?;
+            let mut reply = [0u8; 2];
+            stream.read_exact(&mut reply).map_err(|e| e.to_string())?;
+            if reply != [0x05, 0x00] {
+                return Err(tr!("health_connect_rejected", status = "SOCKS5 auth"));
+            }
+
+            let host = target_host.as_bytes();
+            let length = u8::try_from(host.len())
+                .map_err(|_| tr!("health_target_invalid", target = target))?;
+            let mut request = vec![0x05, 0x01, 0x00, 0x03, length];
+            request.extend_from_slice(host);
+            request.extend_from_slice(&target_port.to_be_bytes());
+            stream.write_all(&request).map_err(|e| e.to_string())?;
+            let mut reply = [0u8; 2];
+            stream.read_exact(&mut reply).map_err(|e| e.to_string())?;
+            if reply[1] != 0x00 {
+                return Err(tr!(
+                    "health_connect_rejected",
+                    status = format!("SOCKS5 {}", reply[1])
+                ));
+            }
+        }
+    }
+    Ok(started.elapsed())
+}
+
+/// 配置组不可用时，把正在使用它且开启了 auto_failover 的软件切换到第一个检查通过的其他配置组，
+/// 保存映射并重新应用；没有这样的软件或没有可用的配置组时返回 None
+fn fail_over(unhealthy: &str, target: &str) -> Option<ProfileFailover> {
+    let config = profile_manager::load_user_config();
+    let applied = applied_state::load_applied_state().entries;
+    let software: Vec<String> = config
+        .mappings
+        .iter()
+        .filter(|m| {
+            m.options.auto_failover && profile_manager::names_eq(&m.profile_name, unhealthy)
+        })
+        .filter(|m| {
+            applied.iter().any(|entry| {
+                entry.software == m.software_name
+                    && profile_manager::names_eq(&entry.profile, unhealthy)
+            })
+        })
+        .map(|m| m.software_name.clone())
+        .collect();
+    if software.is_empty() {
+        return None;
+    }
+    let replacement = config
+        .profiles
+        .iter()
+        .filter(|p| !profile_manager::names_eq(&p.name, unhealthy))
+        .find(|p| check_profile(p, target).is_ok())?
+        .name
+        .clone();
+
+    let config = profile_manager::update_user_config(|config| {
+        for mapping in config
+            .mappings
+            .iter_mut()
+            .filter(|m| software.contains(&m.software_name))
+        {
+            mapping.profile_name = replacement.clone();
+        }
+        Ok(())
+    });
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            error_report::log_error(&format!("自动切换配置组失败: {}", e));
+            return None;
+        }
+    };
+    let mappings: Vec<_> = config
+        .mappings
+        .into_iter()
+        .filter(|m| software.contains(&m.software_name))
+        .collect();
+
+    // 只更换配置组，临时开启的软件到期后仍按原计划关闭，因此不取消自动关闭
+    let results = crate::apply_mappings(&mappings);
+    history::record(OperationKind::Enable, None, &results);
+    Some(ProfileFailover {
+        from: unhealthy.to_string(),
+        to: replacement,
+        results,
+    })
+}
+
+/// 检查所有正在使用的配置组（已应用到至少一个软件），记录结果，
+/// 连续失败达到 UNHEALTHY_AFTER 次时发送 profile-unhealthy 事件，并按软件选项自动切换配置组
+fn run_round(app: &AppHandle, target: &str) {
+    let config = profile_manager::load_user_config();
+    let mut in_use: Vec<String> = applied_state::load_applied_state()
+        .entries
+        .into_iter()
+        .map(|entry| entry.profile)
+        .collect();
+    in_use.sort();
+    in_use.dedup();
+
+    let timestamp = applied_state::now_secs();
+    let samples: Vec<HealthSample> = in_use
+        .iter()
+        .filter_map(|name| profile_manager::find_profile(&config, name))
+        .map(|profile| {
+            let latency = check_profile(profile, target).ok();
+            HealthSample {
+                timestamp,
+                profile: profile.name.clone(),
+                latency_ms: latency.map(|d| d.as_millis() as u64),
+                ok: latency.is_some(),
+            }
+        })
+        .collect();
+
+    let mut unhealthy = Vec::new();
+    {
+        let mut failures = FAILURES.lock().unwrap_or_else(|e| e.into_inner());
+        let failures = failures.get_or_insert_with(HashMap::new);
+        for sample in &samples {
+            let count = failures.entry(sample.profile.clone()).or_default();
+            *count = if sample.ok { 0 } else { *count + 1 };
+            // 只在刚达到次数时通知一次，恢复后重新计数
+            if *count == UNHEALTHY_AFTER {
+                let _ = app.emit(
+                    PROFILE_UNHEALTHY_EVENT,
+                    ProfileUnhealthy {
+                        profile: sample.profile.clone(),
+                        failures: *count,
+                    },
+                );
+                unhealthy.push(sample.profile.clone());
+            }
+        }
+    }
+
+    for profile in unhealthy {
+        if let Some(failover) = fail_over(&profile, target) {
+            let _ = app.emit(PROFILE_FAILOVER_EVENT, failover);
+        }
+    }
+
+    if !samples.is_empty() {
+        let _ = append_samples(samples);
+    }
+}
+
+/// 启动后台健康检查：每次循环重新读取设置，关闭时不发起任何连接
+pub fn start(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let settings = profile_manager::load_user_config().health_check;
+        if !settings.enabled {
+            std::thread::sleep(IDLE_POLL);
+            continue;
+        }
+        run_round(&app, &settings.target);
+        std::thread::sleep(Duration::from_secs(
+            u64::from(settings.interval_minutes.max(1)) * 60,
+        ));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_manager::{self, ProxySettings};
+    use crate::paths::Environment;
+    use crate::profile_manager::{MappingOptions, SoftwareProxyMapping};
+    use std::net::TcpListener;
+
+    fn profile(port: u16, proxy_scheme: ProxyScheme) -> ProxyProfile {
+        ProxyProfile {
+            name: "Local".to_string(),
+            host: "127.0.0.1".to_string(),
+            port,
+            socks_port: None,
+            vpn: None,
+            bypass: None,
+            proxy_scheme,
+        }
+    }
+
+    /// 在本机启动只接受一个连接的假代理：读取 request_len 字节的请求后依次写入 replies，
+    /// 返回端口和收到的请求
+    fn fake_proxy(
+        request_len: usize,
+        replies: &'static [&'static [u8]],
+    ) -> (u16, std::thread::JoinHandle<Vec<u8>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            for reply in replies {
+                let mut buf = vec![0u8; request_len.saturating_sub(received.len())];
+                let n = stream.read(&mut buf).unwrap();
+                received.extend_from_slice(&buf[..n]);
+                stream.write_all(reply).unwrap();
+            }
+            received
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn http_proxy_tunnels_to_port_80() {
+        let (port, proxy) = fake_proxy(1024, &[b"HTTP/1.1 200 Connection established\r\n\r\n"]);
+        check_profile(&profile(port, ProxyScheme::Http), "example.com:80").unwrap();
+        let request = String::from_utf8(proxy.join().unwrap()).unwrap();
+        assert_eq!(
+            request,
+            "CONNECT example.com:80 HTTP/1.1\r\nHost: example.com:80\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn socks5_proxy_tunnels_to_port_80() {
+        let (port, proxy) = fake_proxy(
+            3 + 5 + "example.com".len() + 2,
+            &[&[0x05, 0x00], &[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0]],
+        );
+        check_profile(&profile(port, ProxyScheme::Socks5), "example.com:80").unwrap();
+        let mut expected = vec![0x05, 0x01, 0x00, 0x05, 0x01, 0x00, 0x03, 11];
+        expected.extend_from_slice(b"example.com");
+        expected.extend_from_slice(&[0, 80]);
+        assert_eq!(proxy.join().unwrap(), expected);
+    }
+
+    #[test]
+    fn rejected_connect_is_an_error() {
+        let (port, proxy) = fake_proxy(1024, &[b"HTTP/1.1 403 Forbidden\r\n\r\n"]);
+        let error = check_profile(&profile(port, ProxyScheme::Http), "example.com:80").unwrap_err();
+        assert!(error.as_str().contains("403"), "{}", error);
+        proxy.join().unwrap();
+    }
+
+    #[test]
+    fn target_without_port_is_invalid() {
+        assert!(check_profile(&profile(1, ProxyScheme::Http), "example.com").is_err());
+    }
+    #[test]
+    fn unhealthy_profile_fails_over_to_a_working_one() {
+        let home = tempfile::tempdir().unwrap();
+        let gitconfig = home.path().join(".gitconfig");
+        fs::write(&gitconfig, "[user]\n\tname = Tester\n").unwrap();
+        let (port, proxy) = fake_proxy(1024, &[b"HTTP/1.1 200 Connection established\r\n\r\n"]);
+
+        Environment::sandbox(home.path()).run(|| {
+            profile_manager::update_user_config(|config| {
+                config.profiles = vec![
+                    ProxyProfile {
+                        name: "Clash".to_string(),
+                        ..profile(1, ProxyScheme::Http)
+                    },
+                    ProxyProfile {
+                        name: "Office".to_string(),
+                        ..profile(port, ProxyScheme::Http)
+                    },
+                ];
+                config.mappings = vec![SoftwareProxyMapping {
+                    software_name: "Git".to_string(),
+                    profile_name: "Clash".to_string(),
+                    proxy_localhost: false,
+                    no_proxy_override: None,
+                    options: MappingOptions {
+                        auto_failover: true,
+                        ..Default::default()
+                    },
+                }];
+                Ok(())
+            })
+            .unwrap();
+            let settings = ProxySettings {
+                http_proxy: "http://127.0.0.1:1".to_string(),
+                ..Default::default()
+            };
+            config_manager::enable_proxy(&["Git".to_string()], &settings, "Clash").unwrap();
+
+            let failover = fail_over("Clash", "example.com:80").unwrap();
+            assert_eq!(failover.to, "Office");
+            assert!(
+                failover.results.iter().all(|r| r.success),
+                "{:?}",
+                failover.results
+            );
+            assert_eq!(
+                profile_manager::load_user_config().mappings[0].profile_name,
+                "Office"
+            );
+        });
+
+        proxy.join().unwrap();
+        assert!(fs::read_to_string(&gitconfig)
+            .unwrap()
+            .contains(&format!("127.0.0.1:{}", port)));
+    }
+}
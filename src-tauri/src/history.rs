@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::applied_state;
+use crate::config_manager::ApplyResult;
+use crate::error_report;
+use crate::file_lock;
+use crate::i18n::Message;
+use crate::paths;
+
+/// 最多保留的操作记录数，超出后丢弃最早的记录
+const MAX_RECORDS: usize = 200;
+
+/// 操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Enable,
+    Disable,
+    Reset,
+    Scene, // 应用场景（开启和关闭合并为一条记录）
+}
+
+/// 一次操作的记录：一次批量开启、关闭、重置或应用场景对应一条
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationRecord {
+    pub timestamp: u64, // Unix 时间戳（秒）
+    pub kind: OperationKind,
+    #[serde(default)]
+    pub scene: Option<String>, // 应用的场景名称
+    pub succeeded: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+/// 操作记录文件路径
+/// 位置: %LOCALAPPDATA%\proxy-manager\operation_history.json
+fn history_path() -> Option<PathBuf> {
+    paths::data_dir().map(|p| p.join("operation_history.json"))
+}
+
+fn read_history(path: &PathBuf) -> Vec<OperationRecord> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// 加载操作记录，按时间先后排列
+pub fn load_history() -> Vec<OperationRecord> {
+    match history_path() {
+        Some(path) => {
+            let _guard = file_lock::lock_path(&path);
+            read_history(&path)
+        }
+        None => Vec::new(),
+    }
+}
+
+/// 最近一次操作
+pub fn last_operation() -> Option<OperationRecord> {
+    load_history().pop()
+}
+
+/// 记录一次操作（写入失败只记录错误日志，不影响操作本身的结果）
+pub fn record(kind: OperationKind, scene: Option<&str>, results: &[ApplyResult]) {
+    let (succeeded, failed): (Vec<&ApplyResult>, Vec<&ApplyResult>) =
+        results.iter().partition(|r| r.success);
+    let record = OperationRecord {
+        timestamp: applied_state::now_secs(),
+        kind,
+        scene: scene.map(str::to_string),
+        succeeded: succeeded.into_iter().map(|r| r.software.clone()).collect(),
+        failed: failed.into_iter().map(|r| r.software.clone()).collect(),
+    };
+    if let Err(e) = append(record) {
+        error_report::log_error(&format!("记录操作历史失败: {}", e));
+    }
+}
+
+fn append(record: OperationRecord) -> Result<(), Message> {
+    let path = history_path().ok_or_else(|| tr!("backup_dir_unavailable"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let _guard = file_lock::lock_path(&path)?;
+
+    let mut history = read_history(&path);
+    history.push(record);
+    let excess = history.len().saturating_sub(MAX_RECORDS);
+    history.drain(..excess);
+
+    let content = serde_json::to_string_pretty(&history).map_err(|e| e.to_string())?;
+    fs::write(&path, content).map_err(|e| e.to_string().into())
+}
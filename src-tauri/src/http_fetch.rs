@@ -0,0 +1,21 @@
+use std::process::Command;
+
+use crate::i18n::Message;
+
+/// 下载 URL 的内容。使用系统自带的 curl（Windows 10 起、macOS 和常见 Linux 发行版均自带），
+/// 会遵循 HTTPS_PROXY 等环境变量；headers 为 "名称: 值" 形式的请求头
+pub fn get(url: &str, timeout_secs: u32, headers: &[&str]) -> Result<Vec<u8>, Message> {
+    let timeout = timeout_secs.to_string();
+    let mut command = Command::new("curl");
+    command
+        .args(["-fsSL", "--max-time", &timeout])
+        .args(["-A", concat!("proxy-manager/", env!("CARGO_PKG_VERSION"))]);
+    for header in headers {
+        command.args(["-H", header]);
+    }
+    let output = command.arg(url).output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().into());
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,236 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::{OnceLock, RwLock};
+
+/// 默认语言，缺失翻译时也回退到该语言
+pub const DEFAULT_LOCALE: &str = "zh-CN";
+
+/// 支持的语言列表
+pub const SUPPORTED_LOCALES: &[&str] = &["zh-CN", "en-US"];
+
+static CURRENT_LOCALE: RwLock<String> = RwLock::new(String::new());
+static TABLES: OnceLock<HashMap<&'static str, HashMap<String, String>>> = OnceLock::new();
+
+/// 按键名翻译消息，参数写法：`tr!("profile_not_found", name = profile_name)`
+macro_rules! tr {
+    ($key:expr) => {
+        $crate::i18n::Message::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::Message::translate($key, &[$((stringify!($name), $value.to_string())),+])
+    };
+}
+
+/// 加载内置的消息表
+fn tables() -> &'static HashMap<&'static str, HashMap<String, String>> {
+    TABLES.get_or_init(|| {
+        let mut tables = HashMap::new();
+        tables.insert(
+            "zh-CN",
+            serde_json::from_str(include_str!("../locales/zh-CN.json")).unwrap_or_default(),
+        );
+        tables.insert(
+            "en-US",
+            serde_json::from_str(include_str!("../locales/en-US.json")).unwrap_or_default(),
+        );
+        tables
+    })
+}
+
+/// 获取当前语言
+pub fn current_locale() -> String {
+    let locale = CURRENT_LOCALE.read().map(|l| l.clone()).unwrap_or_default();
+    if locale.is_empty() {
+        DEFAULT_LOCALE.to_string()
+    } else {
+        locale
+    }
+}
+
+/// 设置当前语言
+pub fn set_locale(locale: &str) -> Result<(), Message> {
+    if !SUPPORTED_LOCALES.contains(&locale) {
+        return Err(tr!("unsupported_locale", locale = locale));
+    }
+
+    if let Ok(mut current) = CURRENT_LOCALE.write() {
+        *current = locale.to_string();
+    }
+
+    Ok(())
+}
+
+/// 翻译消息并替换 `{name}` 形式的参数，缺失翻译时回退到 zh-CN，再回退到键名本身
+pub fn translate(key: &str, params: &BTreeMap<String, String>) -> String {
+    let tables = tables();
+    let template = tables
+        .get(current_locale().as_str())
+        .and_then(|table| table.get(key))
+        .or_else(|| tables.get(DEFAULT_LOCALE).and_then(|table| table.get(key)))
+        .map(String::as_str)
+        .unwrap_or(key);
+    substitute(template, params)
+}
+
+/// 一次扫描替换模板中的参数：替换进来的值不会再被当作模板处理，未知的 `{...}` 原样保留
+fn substitute(template: &str, params: &BTreeMap<String, String>) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| params.get(&after[..end]).map(|value| (end, value)));
+        match value {
+            Some((end, value)) => {
+                text.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// 返回给前端的消息（操作结果和错误）：message 为当前语言的文本，
+/// message_key 和 params 为生成它的键名和参数，供前端按键名显示；
+/// 不是由 tr! 生成的文本（如系统错误、多条消息拼接）没有键名，只能使用 message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Message {
+    pub message: String,
+    pub message_key: Option<String>,
+    #[serde(default)]
+    pub params: BTreeMap<String, String>,
+    #[serde(default)]
+    pub detail: Option<String>, // 处理建议（如以管理员身份运行），与 message 分开显示
+}
+
+/// 命令返回给前端的错误
+pub type CommandError = Message;
+
+impl Message {
+    /// 按键名翻译并记录键名和参数（由 tr! 调用）
+    pub fn translate(key: &str, params: &[(&str, String)]) -> Self {
+        let params: BTreeMap<String, String> = params
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect();
+        Message {
+            message: translate(key, &params),
+            message_key: Some(key.to_string()),
+            params,
+            detail: None,
+        }
+    }
+
+    /// 附上处理建议
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// 把多条消息拼接为一条（拼接后没有对应的键名）
+    pub fn join(messages: &[Message], separator: &str) -> Self {
+        messages
+            .iter()
+            .map(|m| m.message.as_str())
+            .collect::<Vec<_>>()
+            .join(separator)
+            .into()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl AsRef<str> for Message {
+    fn as_ref(&self) -> &str {
+        &self.message
+    }
+}
+
+impl From<String> for Message {
+    fn from(message: String) -> Self {
+        Message {
+            message,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&str> for Message {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl From<std::io::Error> for Message {
+    fn from(error: std::io::Error) -> Self {
+        error.to_string().into()
+    }
+}
+
+impl From<Message> for String {
+    fn from(message: Message) -> Self {
+        message.message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(list: &[(&str, &str)]) -> BTreeMap<String, String> {
+        list.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn messages_carry_key_and_params() {
+        let message = tr!(
+            "snapshot_restore_failed",
+            software = "Git",
+            error = "权限不足: C:\\x"
+        );
+        assert_eq!(
+            message.message_key.as_deref(),
+            Some("snapshot_restore_failed")
+        );
+        assert_eq!(
+            message.params,
+            params(&[("software", "Git"), ("error", "权限不足: C:\\x")])
+        );
+        assert!(message.message.contains("权限不足: C:\\x"));
+
+        let joined = Message::join(&[tr!("proxy_enabled"), tr!("proxy_unchanged")], " ");
+        assert_eq!(joined.message_key, None);
+        assert_eq!(Message::from("permission denied").message_key, None);
+    }
+
+    #[test]
+    fn params_are_substituted_once() {
+        let text = substitute(
+            "{software}: {error}",
+            &params(&[("software", "{error}"), ("error", "{software}")]),
+        );
+        assert_eq!(text, "{error}: {software}");
+        assert_eq!(
+            substitute("{unknown} {a}", &params(&[("a", "1")])),
+            "{unknown} 1"
+        );
+    }
+}
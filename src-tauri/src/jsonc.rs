@@ -0,0 +1,359 @@
+use serde::Serialize;
+use std::ops::Range;
+
+use crate::i18n::Message;
+
+// 带注释的 JSON（JSONC，如 VSCode、Windows Terminal 的 settings.json）的文本级编辑：
+// 只改动目标键所在的文本，文件中的注释、缩进和其他内容保持不变
+
+/// 把注释和尾随逗号替换为空格：字符偏移不变，结果可以直接交给 serde_json 解析
+pub fn blank_comments(content: &str) -> String {
+    let bytes = content.as_bytes();
+    let mut out = bytes.to_vec();
+    let mut in_string = false;
+    let mut pending_comma = None; // 其后只有空白和注释的逗号，遇到 } 或 ] 时为尾随逗号
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            match b {
+                b'\\' => i += 1,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        match b {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    out[i] = b' ';
+                    i += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = content[i + 2..]
+                    .find("*/")
+                    .map(|p| i + 2 + p + 2)
+                    .unwrap_or(bytes.len());
+                for (offset, byte) in out[i..end].iter_mut().enumerate() {
+                    if bytes[i + offset] != b'\n' {
+                        *byte = b' ';
+                    }
+                }
+                i = end;
+                continue;
+            }
+            b'"' => {
+                in_string = true;
+                pending_comma = None;
+            }
+            b',' => pending_comma = Some(i),
+            b'}' | b']' => {
+                if let Some(comma) = pending_comma.take() {
+                    out[comma] = b' ';
+                }
+            }
+            b if b.is_ascii_whitespace() => {}
+            _ => pending_comma = None,
+        }
+        i += 1;
+    }
+
+    // 只替换了完整的注释（以 ASCII 字符结尾），结果仍是合法的 UTF-8
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// 解析 JSONC 内容，空内容视为空对象
+pub fn parse(content: &str) -> Result<serde_json::Value, Message> {
+    if content.trim().is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    serde_json::from_str(&blank_comments(content))
+        .map_err(|e| tr!("settings_json_invalid", error = e))
+}
+
+/// 对象的一个成员在文本中的位置
+struct Member {
+    key: String,
+    key_start: usize,
+    value: Range<usize>,
+}
+
+fn skip_ws(text: &[u8], mut i: usize) -> usize {
+    while i < text.len() && text[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+/// 从引号开始的字符串的结束位置（不含）
+fn string_end(text: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < text.len() {
+        match text[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// 从 start 开始的值的结束位置（不含）
+fn value_end(text: &[u8], start: usize) -> Option<usize> {
+    match *text.get(start)? {
+        b'"' => string_end(text, start),
+        b'{' | b'[' => {
+            let mut depth = 0;
+            let mut i = start;
+            while i < text.len() {
+                match text[i] {
+                    b'"' => {
+                        i = string_end(text, i)?;
+                        continue;
+                    }
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Some(i + 1);
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            None
+        }
+        _ => {
+            let end = (start..text.len())
+                .find(|&i| matches!(text[i], b',' | b'}' | b']') || text[i].is_ascii_whitespace())
+                .unwrap_or(text.len());
+            (end > start).then_some(end)
+        }
+    }
+}
+
+/// 对象（text[start] 为 '{'）的成员，以及右括号的位置
+fn object_members(text: &[u8], start: usize) -> Option<(Vec<Member>, usize)> {
+    let mut members = Vec::new();
+    let mut i = skip_ws(text, start + 1);
+    loop {
+        match *text.get(i)? {
+            b'}' => return Some((members, i)),
+            b',' => i = skip_ws(text, i + 1),
+            b'"' => {
+                let key_end = string_end(text, i)?;
+                let key: String = serde_json::from_slice(&text[i..key_end]).ok()?;
+                let colon = skip_ws(text, key_end);
+                if text.get(colon) != Some(&b':') {
+                    return None;
+                }
+                let value_start = skip_ws(text, colon + 1);
+                let end = value_end(text, value_start)?;
+                members.push(Member {
+                    key,
+                    key_start: i,
+                    value: value_start..end,
+                });
+                i = skip_ws(text, end);
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn invalid(reason: &str) -> Message {
+    tr!("settings_json_invalid", error = reason)
+}
+
+/// 根对象左括号的位置
+fn root_object(text: &[u8]) -> Result<usize, Message> {
+    let start = skip_ws(text, 0);
+    match text.get(start) {
+        Some(b'{') => Ok(start),
+        _ => Err(invalid("not an object")),
+    }
+}
+
+/// pos 所在行的缩进
+fn indent_of(content: &str, pos: usize) -> &str {
+    let line_start = content[..pos].rfind('\n').map(|p| p + 1).unwrap_or(0);
+    let line = &content[line_start..];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// 文件使用的缩进单位（第一个有缩进的行），没有时使用 4 个空格
+fn indent_unit(content: &str) -> &str {
+    content
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start_matches([' ', '\t']).len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("    ")
+}
+
+/// 按文件的缩进格式化值，续行对齐到 indent
+fn render(value: &serde_json::Value, indent: &str, unit: &str) -> Result<String, Message> {
+    let mut buf = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(unit.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut buf, formatter);
+    value
+        .serialize(&mut serializer)
+        .map_err(|e| e.to_string())?;
+    let rendered = String::from_utf8(buf).map_err(|e| e.to_string())?;
+    Ok(rendered.replace('\n', &format!("\n{}", indent)))
+}
+
+fn splice(content: &str, range: Range<usize>, replacement: &str) -> String {
+    format!(
+        "{}{}{}",
+        &content[..range.start],
+        replacement,
+        &content[range.end..]
+    )
+}
+
+/// 设置 path 处的值，缺少的中间对象会被创建
+/// 路径上已有的值不是对象时返回错误，而不是覆盖用户的配置
+pub fn set(content: &str, path: &[&str], value: &serde_json::Value) -> Result<String, Message> {
+    let content = if content.trim().is_empty() {
+        "{}"
+    } else {
+        content
+    };
+    let text = blank_comments(content);
+    let text = text.as_bytes();
+    let unit = indent_unit(content);
+    let mut object = root_object(text)?;
+
+    for (depth, key) in path.iter().enumerate() {
+        let (members, close) =
+            object_members(text, object).ok_or_else(|| invalid("unexpected token"))?;
+        let rest = &path[depth + 1..];
+        let Some(member) = members.iter().find(|m| m.key == *key) else {
+            let nested = rest.iter().rev().fold(
+                value.clone(),
+                |value, key| serde_json::json!({ *key: value }),
+            );
+            return insert_member(content, &members, object, close, key, &nested);
+        };
+        if rest.is_empty() {
+            let indent = indent_of(content, member.key_start);
+            return Ok(splice(
+                content,
+                member.value.clone(),
+                &render(value, indent, unit)?,
+            ));
+        }
+        if text[member.value.start] != b'{' {
+            return Err(invalid(&format!("\"{}\" is not an object", key)));
+        }
+        object = member.value.start;
+    }
+    Ok(content.to_string())
+}
+
+/// 在对象末尾添加成员，沿用已有成员的缩进
+fn insert_member(
+    content: &str,
+    members: &[Member],
+    object: usize,
+    close: usize,
+    key: &str,
+    value: &serde_json::Value,
+) -> Result<String, Message> {
+    let unit = indent_unit(content);
+    let key = serde_json::to_string(key).map_err(|e| e.to_string())?;
+
+    if let Some(last) = members.last() {
+        let indent = indent_of(content, last.key_start);
+        let entry = format!(",\n{}{}: {}", indent, key, render(value, indent, unit)?);
+        return Ok(splice(content, last.value.end..last.value.end, &entry));
+    }
+
+    let outer = indent_of(content, object);
+    let indent = format!("{}{}", outer, unit);
+    let entry = format!("{}: {}", key, render(value, &indent, unit)?);
+    // 空对象中可能只有注释，此时插入到左括号之后，保留注释
+    if content[object + 1..close].trim().is_empty() {
+        Ok(splice(
+            content,
+            object + 1..close,
+            &format!("\n{}{}\n{}", indent, entry, outer),
+        ))
+    } else {
+        Ok(splice(
+            content,
+            object + 1..object + 1,
+            &format!("\n{}{}", indent, entry),
+        ))
+    }
+}
+
+/// 删除 path 处的成员（连同其逗号），不存在时内容不变
+pub fn remove(content: &str, path: &[&str]) -> Result<String, Message> {
+    let Some((last_key, parents)) = path.split_last() else {
+        return Ok(content.to_string());
+    };
+    if content.trim().is_empty() {
+        return Ok(content.to_string());
+    }
+    let blanked = blank_comments(content);
+    let text = blanked.as_bytes();
+    let mut object = root_object(text)?;
+
+    for key in parents {
+        let (members, _) =
+            object_members(text, object).ok_or_else(|| invalid("unexpected token"))?;
+        match members.iter().find(|m| m.key == *key) {
+            Some(member) if text[member.value.start] == b'{' => object = member.value.start,
+            _ => return Ok(content.to_string()),
+        }
+    }
+
+    let (members, _) = object_members(text, object).ok_or_else(|| invalid("unexpected token"))?;
+    let Some(member) = members.iter().find(|m| m.key == *last_key) else {
+        return Ok(content.to_string());
+    };
+
+    let after = skip_ws(text, member.value.end);
+    let range = if text.get(after) == Some(&b',') {
+        // 成员独占一行时连同缩进、逗号和换行一起删除
+        let line_start = content[..member.key_start]
+            .rfind('\n')
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        if blanked[line_start..member.key_start].trim().is_empty() {
+            let line_end = blanked[after + 1..]
+                .find('\n')
+                .map(|p| after + 1 + p + 1)
+                .unwrap_or(blanked.len());
+            let end = if blanked[after + 1..line_end].trim().is_empty() {
+                line_end
+            } else {
+                after + 1
+            };
+            line_start..end
+        } else {
+            member.key_start..skip_ws(text, after + 1)
+        }
+    } else {
+        // 最后一个成员：连同前一个成员后的逗号一起删除；唯一的成员独占一行时连同该行一起删除
+        match text[..member.key_start]
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+        {
+            Some(comma) if text[comma] == b',' => comma..member.value.end,
+            _ => match content[..member.key_start].rfind('\n') {
+                Some(newline) if blanked[newline..member.key_start].trim().is_empty() => {
+                    newline..member.value.end
+                }
+                _ => member.key_start..member.value.end,
+            },
+        }
+    };
+    Ok(splice(content, range, ""))
+}
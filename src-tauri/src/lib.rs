@@ -1,18 +1,63 @@
-mod config_manager;
+#[macro_use]
+mod i18n;
+pub mod applied_state;
+mod auto_revert;
+mod bypass;
+pub mod config_manager;
+mod config_watcher;
+mod dashboard;
+mod elevation;
+mod error_report;
+mod file_lock;
+mod health_check;
+mod history;
+mod http_fetch;
+mod jsonc;
+mod onboarding;
+mod pac;
+pub mod paths;
 mod port_detector;
-mod profile_manager;
+pub mod profile_manager;
+mod progress;
+mod settings_transfer;
+mod shims;
+mod stale_check;
+mod support_bundle;
+mod tray;
+mod update_check;
 
-use config_manager::{ProxySettings, SoftwareConfig};
-use port_detector::{DetectionResult, VpnConfig};
+use applied_state::{AppliedEntry, AppliedState};
+use bypass::BypassFormat;
+use config_manager::{
+    ApplyAction, ApplyResult, BackupEntry, DisableStrategy, EffectiveEnvVar, EnvVarStyle,
+    ExistingProxy, GitScope, IdeaInstall, ProxySettings, SoftwareConfig,
+};
+use dashboard::Dashboard;
+use health_check::HealthSample;
+use history::{OperationKind, OperationRecord};
+use i18n::{CommandError, Message};
+use onboarding::RecommendedSetup;
+use pac::ResolvedProxy;
+use paths::StoragePaths;
+use port_detector::{
+    BindFilter, DetectedPort, DetectionDiagnostics, DetectionResult, PortConflict, VpnConfig,
+    VpnStatus,
+};
 use profile_manager::{
-    ClosePreference, CustomSoftware, ProxyProfile, SoftwareProxyMapping, UserConfig,
+    ClosePreference, CustomSoftware, CustomSoftwareImport, DeleteProfileError, HealthCheckSettings,
+    MappingOptions, PortRange, ProfileFieldDiff, ProfileSaveResult, ProxyProfile, PrunedMappings,
+    Scene, SoftwareProxyMapping, UserConfig,
 };
-use std::collections::HashMap;
+use settings_transfer::{ExportOptions, SettingsImport};
+use stale_check::StaleReport;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
 use tauri::{
-    menu::{Menu, MenuItem},
     tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
     Emitter, Manager,
 };
+use tauri_plugin_opener::OpenerExt;
+use update_check::UpdateInfo;
 
 // ============ Tauri 命令 ============
 
@@ -22,31 +67,169 @@ fn get_vpn_list() -> Vec<VpnConfig> {
     port_detector::get_vpn_configs()
 }
 
-/// 根据 VPN 名称检测端口
+/// 获取预设的 VPN 列表及其运行状态（运行中的排在前面）
 #[tauri::command]
-fn detect_port(vpn_name: String) -> DetectionResult {
-    port_detector::detect_port_by_vpn_name(&vpn_name)
+async fn get_vpn_list_with_status() -> Vec<VpnStatus> {
+    tauri::async_runtime::spawn_blocking(port_detector::get_vpn_list_with_status)
+        .await
+        .unwrap_or_default()
 }
 
-/// 获取支持的软件列表（包含预设和自定义）
+/// 根据 VPN 名称检测端口（在后台线程运行系统命令，不阻塞 IPC）
+/// bind_filter 按监听地址筛选端口，默认全部返回
 #[tauri::command]
-fn get_software_list() -> Vec<SoftwareConfig> {
-    let mut list = config_manager::get_software_list();
+async fn detect_port(vpn_name: String, bind_filter: Option<BindFilter>) -> DetectionResult {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut result = port_detector::detect_port_by_vpn_name(&vpn_name);
+        bind_filter.unwrap_or_default().apply(&mut result.ports);
+        result
+    })
+    .await
+    .unwrap_or_else(|e| DetectionResult {
+        success: false,
+        message: e.to_string(),
+        ports: vec![],
+        config_file: None,
+    })
+}
 
-    // 添加自定义软件
-    let user_config = profile_manager::load_user_config();
-    for custom in user_config.custom_software {
-        list.push(SoftwareConfig {
-            name: custom.name,
-            config_type: custom.config_type,
-            enabled: true,
-            installed: true, // 自定义软件默认标记为已安装
-            config_path: Some(custom.config_path),
-            is_custom: true,
-        });
+/// 检测端口并返回诊断信息（包括系统命令的原始输出），用于问题报告
+#[tauri::command]
+async fn detect_port_verbose(vpn_name: String) -> Result<DetectionDiagnostics, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || port_detector::detect_port_verbose(&vpn_name))
+        .await
+        .map_err(|e| CommandError::from(e.to_string()))
+}
+
+/// 检测所有预设的 VPN，返回 VPN 名称 -> 检测结果
+#[tauri::command]
+async fn detect_all_vpns(bind_filter: Option<BindFilter>) -> BTreeMap<String, DetectionResult> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut all = port_detector::detect_all_vpns();
+        for result in all.values_mut() {
+            bind_filter.unwrap_or_default().apply(&mut result.ports);
+        }
+        all
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// 通过 Clash 的外部控制器读取实际使用的端口
+#[tauri::command]
+async fn detect_via_clash_api(
+    controller: String,
+    secret: Option<String>,
+    bind_filter: Option<BindFilter>,
+) -> DetectionResult {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut result = port_detector::detect_via_clash_api(&controller, secret.as_deref());
+        bind_filter.unwrap_or_default().apply(&mut result.ports);
+        result
+    })
+    .await
+    .unwrap_or_else(|e| DetectionResult {
+        success: false,
+        message: e.to_string(),
+        ports: vec![],
+        config_file: None,
+    })
+}
+
+/// 扫描本机所有像代理的监听端口（不限于预设的 VPN）
+#[tauri::command]
+async fn scan_proxy_ports(bind_filter: Option<BindFilter>) -> Vec<DetectedPort> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut ports = port_detector::scan_proxy_ports();
+        bind_filter.unwrap_or_default().apply(&mut ports);
+        ports
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// 下载 PAC 文件，提取其中的代理（test_url 会使用的排在最前），用于新建配置组
+#[tauri::command]
+async fn resolve_pac(
+    pac_url: String,
+    test_url: String,
+) -> Result<Vec<ResolvedProxy>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || pac::resolve_pac(&pac_url, &test_url))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 列出引用了不存在的软件或配置组的映射
+#[tauri::command]
+fn list_orphaned_mappings() -> Vec<SoftwareProxyMapping> {
+    profile_manager::list_orphaned_mappings()
+}
+
+/// 删除引用了不存在的软件或配置组的映射
+#[tauri::command]
+fn prune_orphaned_mappings() -> Result<PrunedMappings, CommandError> {
+    profile_manager::prune_orphaned_mappings()
+}
+
+/// 首次使用：检测正在运行的 VPN 并生成推荐配置（不保存）
+#[tauri::command]
+async fn generate_recommended_setup() -> Result<RecommendedSetup, CommandError> {
+    tauri::async_runtime::spawn_blocking(onboarding::generate_recommended_setup)
+        .await
+        .map_err(|e| CommandError::from(e.to_string()))
+}
+
+/// 保存推荐配置（新增配置组并更新映射，一次性写入）
+#[tauri::command]
+fn apply_recommended_setup(setup: RecommendedSetup) -> Result<UserConfig, CommandError> {
+    onboarding::apply_recommended_setup(setup)
+}
+
+/// 检查端口冲突（多个进程监听同一端口，或配置组端口无进程监听）
+#[tauri::command]
+async fn check_port_conflicts() -> Vec<PortConflict> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let config = profile_manager::load_user_config();
+        port_detector::check_port_conflicts(&profile_manager::profile_ports(&config))
+    })
+    .await
+    .unwrap_or_default()
+}
+
+/// 将检测到的指定类型端口（"http" / "socks"）应用到代理配置组
+#[tauri::command]
+fn apply_detected_port(
+    profile_name: String,
+    detection: DetectionResult,
+    port_type: String,
+) -> Result<UserConfig, CommandError> {
+    let detected = port_detector::select_detected_port(&detection.ports, &port_type)?;
+
+    let config = profile_manager::load_user_config();
+    let mut profile = profile_manager::find_profile(&config, &profile_name)
+        .cloned()
+        .ok_or_else(|| tr!("profile_not_found", name = profile_name))?;
+    if port_type == "socks" {
+        profile.socks_port = Some(detected.port);
+    } else {
+        profile.port = detected.port;
     }
 
-    list
+    profile_manager::update_profile(&profile_name, profile).map(|result| result.config)
+}
+
+/// 获取支持的软件列表（包含预设和自定义）
+#[tauri::command]
+fn get_software_list() -> Vec<SoftwareConfig> {
+    config_manager::get_software_list_with_custom(&profile_manager::load_user_config())
+}
+
+/// 主界面所需的全部状态，较慢的部分超时后标记为 loading
+#[tauri::command]
+async fn get_dashboard() -> Dashboard {
+    tauri::async_runtime::spawn_blocking(dashboard::get_dashboard)
+        .await
+        .unwrap_or_else(|_| dashboard::get_dashboard_loading())
 }
 
 /// 获取用户配置（代理配置组 + 软件映射）
@@ -55,21 +238,68 @@ fn get_user_config() -> UserConfig {
     profile_manager::load_user_config()
 }
 
-/// 保存用户配置
+/// 加载配置时发现的问题（如只有大小写不同的重名配置组），没有问题时为空
+#[tauri::command]
+fn get_config_load_status() -> Vec<String> {
+    profile_manager::name_collisions(&profile_manager::load_user_config())
+}
+
+/// 校验并保存用户配置，返回规范化后的配置
+#[tauri::command]
+fn save_user_config(config: UserConfig) -> Result<UserConfig, CommandError> {
+    profile_manager::save_user_config(config)
+}
+
+/// 添加代理配置组（开启了地址检查时附带非致命的警告）
+#[tauri::command]
+async fn add_proxy_profile(profile: ProxyProfile) -> Result<ProfileSaveResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || profile_manager::add_profile(profile))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 更新代理配置组，名称改变时同时更新映射（开启了地址检查时附带非致命的警告）
 #[tauri::command]
-fn save_user_config(config: UserConfig) -> Result<(), String> {
-    profile_manager::save_user_config(&config)
+async fn update_proxy_profile(
+    old_name: String,
+    profile: ProxyProfile,
+) -> Result<ProfileSaveResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        profile_manager::update_profile(&old_name, profile)
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
-/// 添加代理配置组
+/// 比较两个配置组，返回取值不同的字段
 #[tauri::command]
-fn add_proxy_profile(profile: ProxyProfile) -> Result<UserConfig, String> {
-    profile_manager::add_profile(profile)
+fn diff_profiles(a: String, b: String) -> Result<Vec<ProfileFieldDiff>, CommandError> {
+    profile_manager::diff_profiles(&a, &b)
 }
 
 /// 删除代理配置组
 #[tauri::command]
-fn delete_proxy_profile(profile_name: String) -> Result<UserConfig, String> {
+fn delete_proxy_profile(profile_name: String) -> Result<UserConfig, DeleteProfileError> {
+    profile_manager::delete_profile(&profile_name)
+}
+
+/// 删除代理配置组：先关闭应用了该配置组的软件的代理，全部成功后再删除
+#[tauri::command]
+fn delete_proxy_profile_force(profile_name: String) -> Result<UserConfig, DeleteProfileError> {
+    let applied = profile_manager::applied_software_of(&profile_name);
+    if !applied.is_empty() {
+        auto_revert::cancel(&applied);
+        let results = config_manager::disable_proxy(&applied, false, None)?;
+        history::record(OperationKind::Disable, None, &results);
+        let failures: Vec<String> = results
+            .into_iter()
+            .filter(|r| !r.success)
+            .map(|r| format!("{}: {}", r.software, r.message))
+            .collect();
+        if !failures.is_empty() {
+            return Err(tr!("profile_disable_failed", failures = failures.join("; ")).into());
+        }
+    }
     profile_manager::delete_profile(&profile_name)
 }
 
@@ -78,86 +308,750 @@ fn delete_proxy_profile(profile_name: String) -> Result<UserConfig, String> {
 fn update_software_mapping(
     software_name: String,
     profile_name: String,
-) -> Result<UserConfig, String> {
+) -> Result<UserConfig, CommandError> {
     profile_manager::update_software_mapping(&software_name, &profile_name)
 }
 
+/// 更新软件级别的选项（证书校验、代理范围、环境变量风格、额外绕过地址等）
+#[tauri::command]
+fn update_mapping_options(
+    software_name: String,
+    options: MappingOptions,
+) -> Result<UserConfig, CommandError> {
+    profile_manager::update_mapping_options(&software_name, options)
+}
+
+/// 设置软件的本地地址（localhost/127.0.0.1/::1）是否也走代理
+#[tauri::command]
+fn set_mapping_proxy_localhost(
+    software_name: String,
+    proxy_localhost: bool,
+) -> Result<UserConfig, CommandError> {
+    profile_manager::set_mapping_proxy_localhost(&software_name, proxy_localhost)
+}
+
+/// 设置软件的 no_proxy 覆盖列表（为空时恢复默认列表）
+#[tauri::command]
+fn set_mapping_no_proxy_override(
+    software_name: String,
+    no_proxy: Option<String>,
+) -> Result<UserConfig, CommandError> {
+    profile_manager::set_mapping_no_proxy_override(&software_name, no_proxy)
+}
+
 /// 开启代理（使用配置组）
+/// atomic: 为 true 时只要有一个软件失败，就把已成功的软件回滚到开启前的状态，
+/// 回滚的软件附上导致回滚的失败原因，回滚失败的软件单独报告
+/// transactional: 与 atomic 相同（任一为 true 即启用）
+/// 写入和写入后验证（会等待复查）在后台线程执行，不阻塞主线程
+#[tauri::command]
+async fn enable_proxy_with_profiles(
+    software_mappings: Vec<SoftwareProxyMapping>,
+    atomic: Option<bool>,
+    transactional: Option<bool>,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        enable_with_profiles(software_mappings, atomic, transactional)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 按映射开启代理并记录到操作历史（atomic/transactional 的含义见 enable_proxy_with_profiles）
+fn enable_with_profiles(
+    software_mappings: Vec<SoftwareProxyMapping>,
+    atomic: Option<bool>,
+    transactional: Option<bool>,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    let results = enable_with_rollback(software_mappings, atomic, transactional)?;
+    history::record(OperationKind::Enable, None, &results);
+    Ok(results)
+}
+
+/// 按映射开启代理，需要时在部分失败后回滚已成功的软件
+fn enable_with_rollback(
+    software_mappings: Vec<SoftwareProxyMapping>,
+    atomic: Option<bool>,
+    transactional: Option<bool>,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    let software: Vec<String> = software_mappings
+        .iter()
+        .map(|m| m.software_name.clone())
+        .collect();
+    auto_revert::cancel(&software);
+
+    if !(atomic.unwrap_or(false) || transactional.unwrap_or(false)) {
+        return Ok(apply_mappings(&software_mappings));
+    }
+
+    let config = profile_manager::load_user_config();
+    let points: Vec<_> = software_mappings
+        .iter()
+        .map(|m| config_manager::rollback_point(&config, &m.software_name))
+        .collect();
+    let mut results = apply_mappings(&software_mappings);
+    if results.iter().all(|r| r.success) {
+        return Ok(results);
+    }
+
+    let failures = results
+        .iter()
+        .filter(|r| !r.success)
+        .map(|r| format!("{}: {}", r.software, r.message))
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    // 回滚已成功的软件：回滚成功的标记为未生效，回滚失败的保留为已开启并附上原因
+    // 未修改配置的软件没有刷新当前备份，无需（也不能）回滚
+    for result in results
+        .iter_mut()
+        .filter(|r| r.success && r.action == ApplyAction::Changed)
+    {
+        let Some(point) = points.iter().find(|p| p.software == result.software) else {
+            continue;
+        };
+        match config_manager::rollback(&config, point) {
+            Ok(message) => {
+                result.success = false;
+                result.set_message(
+                    format!("{} {}", message, tr!("rollback_cause", failures = failures)).into(),
+                );
+            }
+            Err(e) => result.set_message(tr!("rollback_failed", error = e)),
+        }
+    }
+    Ok(results)
+}
+
+/// 在后台开启代理并立即返回批次 ID，进度通过 apply-progress 事件发送，
+/// 完成后发送 apply-batch-complete 事件，也可以用 get_batch_result 读取结果
+#[tauri::command]
+fn start_enable_proxy_batch(
+    app: tauri::AppHandle,
+    software_mappings: Vec<SoftwareProxyMapping>,
+    atomic: Option<bool>,
+    transactional: Option<bool>,
+) -> String {
+    progress::start_batch(app, move || {
+        enable_with_profiles(software_mappings, atomic, transactional)
+    })
+}
+
+/// 临时开启代理：duration_secs 秒后自动关闭本次开启的软件，并发送 proxy-auto-reverted 事件
+/// 到期前手动开启、关闭或重置这些软件会取消自动关闭
 #[tauri::command]
-fn enable_proxy_with_profiles(
+async fn enable_temporarily(
+    app: tauri::AppHandle,
     software_mappings: Vec<SoftwareProxyMapping>,
-) -> Result<Vec<String>, String> {
+    duration_secs: u64,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    if duration_secs == 0 {
+        return Err(tr!("invalid_temporary_duration"));
+    }
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        let results = apply_mappings(&software_mappings);
+        history::record(OperationKind::Enable, None, &results);
+        results
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    auto_revert::schedule_revert(app, &results, Duration::from_secs(duration_secs));
+    Ok(results)
+}
+
+/// 按软件能力分别开启：支持 SOCKS 的软件使用 socks_profile，其余使用 http_profile
+/// 作用于当前映射中的软件（映射到 direct 的除外），保留各软件的选项，不修改保存的映射
+/// 结果中的 profile 为每个软件实际使用的配置组
+#[tauri::command]
+async fn enable_smart(
+    http_profile: String,
+    socks_profile: String,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || apply_smart(&http_profile, &socks_profile))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 按软件能力选择配置组并开启（见 enable_smart）
+fn apply_smart(http_profile: &str, socks_profile: &str) -> Result<Vec<ApplyResult>, CommandError> {
+    let config = profile_manager::load_user_config();
+    for name in [http_profile, socks_profile] {
+        if profile_manager::find_profile(&config, name).is_none() {
+            return Err(tr!("profile_not_found", name = name));
+        }
+    }
+
+    let mappings: Vec<SoftwareProxyMapping> = config
+        .mappings
+        .iter()
+        .filter(|m| !profile_manager::names_eq(&m.profile_name, profile_manager::DIRECT_PROFILE))
+        .map(|m| {
+            let supports_socks =
+                config_manager::software_capabilities(&config, &m.software_name).supports_socks;
+            SoftwareProxyMapping {
+                profile_name: match supports_socks {
+                    true => socks_profile.to_string(),
+                    false => http_profile.to_string(),
+                },
+                ..m.clone()
+            }
+        })
+        .collect();
+
+    let software: Vec<String> = mappings.iter().map(|m| m.software_name.clone()).collect();
+    auto_revert::cancel(&software);
+    let results = apply_mappings(&mappings);
+    history::record(OperationKind::Enable, None, &results);
+    Ok(results)
+}
+
+/// 按映射为每个软件开启对应配置组的代理
+fn apply_mappings(software_mappings: &[SoftwareProxyMapping]) -> Vec<ApplyResult> {
     let config = profile_manager::load_user_config();
-    let profiles: HashMap<String, ProxyProfile> = config
+    // 按规范化的名称查找（忽略大小写和首尾空白）
+    let profiles: HashMap<String, &ProxyProfile> = config
         .profiles
-        .into_iter()
-        .map(|p| (p.name.clone(), p))
+        .iter()
+        .map(|p| (p.name.trim().to_lowercase(), p))
         .collect();
 
-    let mut results = Vec::new();
+    // 先为每个映射计算代理设置，再作为一个批次开启（需要提权时只提示一次）
+    // 映射到 direct 的软件另外作为一个批次关闭代理，在结果中用 Err(None) 占位
+    let mut prepared = Vec::new();
+    let mut direct = Vec::new();
 
     for mapping in software_mappings {
-        if let Some(profile) = profiles.get(&mapping.profile_name) {
+        if profile_manager::names_eq(&mapping.profile_name, profile_manager::DIRECT_PROFILE) {
+            direct.push(mapping.software_name.clone());
+            prepared.push(Err(None));
+        } else if let Some(profile) = profiles.get(&mapping.profile_name.trim().to_lowercase()) {
+            // 支持 SOCKS 的软件优先使用 SOCKS 端口，其余使用 HTTP 端口
+            let proxy_url = match profile.socks_url() {
+                Some(socks_url) if config_manager::prefers_socks(&mapping.software_name) => {
+                    socks_url
+                }
+                _ => profile.proxy_url(),
+            };
+            // 合并配置组默认值与软件级别的选项
+            let options = &mapping.options;
+            let no_proxy = match &mapping.no_proxy_override {
+                // 软件级别的覆盖列表优先于默认列表
+                Some(no_proxy) => no_proxy.clone(),
+                None => {
+                    let mut no_proxy = Vec::new();
+                    if !mapping.proxy_localhost {
+                        no_proxy.push(config_manager::LOCALHOST_NO_PROXY.to_string());
+                    }
+                    // 配置组的绕过列表在前，软件级别的额外地址在后，重复的只保留一次
+                    no_proxy.extend(profile_manager::merge_bypass(&[
+                        profile.bypass.as_deref().unwrap_or_default(),
+                        &options.extra_bypass,
+                    ]));
+                    no_proxy.join(",")
+                }
+            };
+
             let proxy_settings = ProxySettings {
-                http_proxy: format!("http://{}:{}", profile.host, profile.port),
-                https_proxy: format!("http://{}:{}", profile.host, profile.port),
-                no_proxy: "localhost,127.0.0.1,::1".to_string(),
+                http_proxy: proxy_url.clone(),
+                https_proxy: proxy_url,
+                no_proxy,
+                all_proxy: profile.socks_url(),
+                env_var_style: options.env_var_style.unwrap_or(config.env_var_style),
+                strict_ssl: options.strict_ssl,
+                url_scopes: options.url_scopes.clone(),
+                shim_commands: match options.use_shims {
+                    true => options.shim_commands.clone(),
+                    false => vec![],
+                },
             };
 
-            match config_manager::enable_proxy(
-                std::slice::from_ref(&mapping.software_name),
-                &proxy_settings,
-            ) {
-                Ok(mut msgs) => results.append(&mut msgs),
-                Err(e) => results.push(format!("✗ {}: {}", mapping.software_name, e)),
-            }
+            prepared.push(Ok((mapping, proxy_settings, profile.name.as_str())));
         } else {
-            results.push(format!(
-                "✗ {}: 未找到配置 '{}'",
-                mapping.software_name, mapping.profile_name
-            ));
+            prepared.push(Err(Some(
+                ApplyResult::from_result(
+                    &config,
+                    &mapping.software_name,
+                    Err(tr!(
+                        "mapping_profile_not_found",
+                        name = mapping.profile_name
+                    )),
+                )
+                .with_profile(&mapping.profile_name),
+            )));
         }
     }
 
+    let requests: Vec<_> = prepared
+        .iter()
+        .filter_map(|p| p.as_ref().ok())
+        .map(|(mapping, proxy_settings, profile_name)| {
+            (
+                mapping.software_name.as_str(),
+                proxy_settings,
+                *profile_name,
+            )
+        })
+        .collect();
+    let mut applied = config_manager::enable_proxy_each(&requests).into_iter();
+    let mut directed = config_manager::set_direct_each(&direct).into_iter();
+
+    prepared
+        .into_iter()
+        .filter_map(|p| match p {
+            Ok(_) => applied.next(),
+            Err(None) => directed.next(),
+            Err(Some(result)) => Some(result),
+        })
+        .collect()
+}
+
+/// 获取各软件当前实际应用的配置组
+#[tauri::command]
+fn get_applied_state() -> AppliedState {
+    config_manager::load_applied_status(&profile_manager::load_user_config())
+}
+
+/// 首次运行时根据现有配置尽力推断已应用状态：
+/// 映射中的软件配置里包含对应配置组的代理地址，即视为已应用
+fn scan_applied_state() -> Vec<AppliedEntry> {
+    let config = profile_manager::load_user_config();
+
+    config
+        .mappings
+        .iter()
+        .filter_map(|mapping| {
+            let profile = profile_manager::find_profile(&config, &mapping.profile_name)?;
+            let content =
+                config_manager::read_software_config(&config, &mapping.software_name, None, false)
+                    .ok()?;
+            let host = profile_manager::format_host(&profile.host);
+            let applied = std::iter::once(profile.port)
+                .chain(profile.socks_port)
+                .any(|port| content.contains(&format!("{}:{}", host, port)));
+            applied.then(|| AppliedEntry {
+                software: mapping.software_name.clone(),
+                profile: profile.name.clone(),
+                applied_at: applied_state::now_secs(),
+                content_hash: applied_state::content_hash(&content),
+                config_path: config_manager::software_config_path(&config, &mapping.software_name),
+                relocated: false,
+                url_scopes: vec![],
+            })
+        })
+        .collect()
+}
+
+/// JetBrains 升级后迁移代理设置：按原来的映射（没有映射时按已应用的配置组）
+/// 开启新版本配置目录中的代理，成功后删除旧目录中的设置
+#[tauri::command]
+fn migrate_jetbrains_config(software: String) -> Result<Vec<ApplyResult>, CommandError> {
+    let config = profile_manager::load_user_config();
+    let entry = config_manager::load_applied_status(&config)
+        .entries
+        .into_iter()
+        .find(|entry| entry.software == software && entry.relocated)
+        .ok_or_else(|| tr!("jetbrains_not_relocated", software = software))?;
+    let old_path = entry.config_path.unwrap_or_default();
+
+    let mapping = config
+        .mappings
+        .into_iter()
+        .find(|m| m.software_name == software)
+        .unwrap_or(SoftwareProxyMapping {
+            software_name: software.clone(),
+            profile_name: entry.profile,
+            proxy_localhost: false,
+            no_proxy_override: None,
+            options: Default::default(),
+        });
+    let mut results = apply_mappings(&[mapping]);
+    if results.iter().all(|r| r.success) {
+        results.push(config_manager::clean_relocated_config(&software, &old_path));
+    }
+    Ok(results)
+}
+
+/// 列出已保存的全量快照（时间戳，新的在前）
+#[tauri::command]
+fn list_full_snapshots() -> Vec<u64> {
+    config_manager::list_full_snapshots()
+}
+
+/// 把所有软件的代理状态恢复到快照时刻（首次开启代理前自动保存）
+#[tauri::command]
+fn restore_full_snapshot(timestamp: u64) -> Result<Vec<ApplyResult>, CommandError> {
+    config_manager::restore_full_snapshot(timestamp)
+}
+
+/// 转换绕过列表的格式，如把 Windows 的 ProxyOverride 导入为配置组的绕过列表
+#[tauri::command]
+fn convert_bypass_list(
+    value: String,
+    from: BypassFormat,
+    to: BypassFormat,
+) -> Result<String, CommandError> {
+    bypass::convert(&value, from, to)
+}
+
+/// 检查已应用的代理端口是否与关联 VPN 当前监听的端口一致
+#[tauri::command]
+fn check_stale_proxies() -> Vec<StaleReport> {
+    stale_check::check_stale_proxies()
+}
+
+/// 一键更新：用检测到的新端口更新配置组，并重新应用到受影响的软件
+#[tauri::command]
+fn refresh_stale_proxies() -> Result<Vec<ApplyResult>, CommandError> {
+    let reports = stale_check::check_stale_proxies();
+    let software = stale_check::update_stale_profiles(&reports)?;
+
+    let mappings: Vec<SoftwareProxyMapping> = profile_manager::load_user_config()
+        .mappings
+        .into_iter()
+        .filter(|m| software.contains(&m.software_name))
+        .collect();
+    auto_revert::cancel(&software);
+    let results = apply_mappings(&mappings);
+    history::record(OperationKind::Enable, None, &results);
+    Ok(results)
+}
+
+/// 获取所有场景
+#[tauri::command]
+fn list_scenes() -> Vec<Scene> {
+    profile_manager::load_user_config().scenes
+}
+
+/// 将当前的软件映射保存为场景，未映射的软件记为该场景下关闭代理
+#[tauri::command]
+fn save_current_as_scene(name: String) -> Result<UserConfig, CommandError> {
+    let config = profile_manager::load_user_config();
+    let disabled_software = config_manager::get_software_list_with_custom(&config)
+        .into_iter()
+        .map(|s| s.name)
+        .filter(|name| !config.mappings.iter().any(|m| &m.software_name == name))
+        .collect();
+
+    profile_manager::save_scene(Scene {
+        name,
+        mappings: config.mappings,
+        disabled_software,
+    })
+}
+
+/// 应用场景：按场景映射开启代理，并关闭场景中记为不使用代理、当前已开启代理的软件
+/// 保存场景之后才安装的软件不在场景中，保持原样
+#[tauri::command]
+async fn apply_scene(name: String) -> Result<Vec<ApplyResult>, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || activate_scene(&name))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 应用场景（命令和托盘菜单共用），整个场景在操作历史中记为一条
+fn activate_scene(name: &str) -> Result<Vec<ApplyResult>, Message> {
+    let config = profile_manager::load_user_config();
+    let scene = config
+        .scenes
+        .iter()
+        .find(|s| profile_manager::names_eq(&s.name, name))
+        .cloned()
+        .ok_or_else(|| tr!("scene_not_found", name = name))?;
+
+    let missing = profile_manager::missing_scene_profiles(&config, &scene);
+    if !missing.is_empty() {
+        return Err(tr!("scene_profile_missing", profiles = missing.join(", ")));
+    }
+
+    // 按已应用状态判断哪些软件开启了代理（包括手动开启、没有映射的软件），
+    // 只关闭其中在场景中记为不使用代理的软件，避免还原从未开启过代理的软件
+    let to_disable: Vec<String> = config_manager::load_applied_status(&config)
+        .entries
+        .into_iter()
+        .map(|entry| entry.software)
+        .filter(|software| scene.disabled_software.contains(software))
+        .collect();
+
+    // 场景接管这些软件，之前安排的自动关闭不再执行
+    let software: Vec<String> = scene
+        .mappings
+        .iter()
+        .map(|m| m.software_name.clone())
+        .chain(to_disable.iter().cloned())
+        .collect();
+    auto_revert::cancel(&software);
+
+    let mut results = apply_mappings(&scene.mappings);
+    if !to_disable.is_empty() {
+        results.extend(config_manager::disable_proxy(&to_disable, false, None)?);
+    }
+
+    profile_manager::update_user_config(|config| {
+        config.mappings = scene.mappings.clone();
+        Ok(())
+    })?;
+
+    history::record(OperationKind::Scene, Some(&scene.name), &results);
     Ok(results)
 }
 
+/// 获取操作历史（按时间先后排列）
+#[tauri::command]
+fn get_operation_history() -> Vec<OperationRecord> {
+    history::load_history()
+}
+
+/// 删除场景
+#[tauri::command]
+fn delete_scene(name: String) -> Result<UserConfig, CommandError> {
+    profile_manager::delete_scene(&name)
+}
+
 /// 开启代理（旧接口，保持兼容）
 #[tauri::command]
-fn enable_proxy(
+async fn enable_proxy(
     software_list: Vec<String>,
     proxy_host: String,
     proxy_port: u16,
-) -> Result<Vec<String>, String> {
+) -> Result<Vec<ApplyResult>, CommandError> {
+    let proxy_host = profile_manager::format_host(&proxy_host);
     let proxy_settings = ProxySettings {
         http_proxy: format!("http://{}:{}", proxy_host, proxy_port),
         https_proxy: format!("http://{}:{}", proxy_host, proxy_port),
-        no_proxy: "localhost,127.0.0.1,::1".to_string(),
+        no_proxy: config_manager::LOCALHOST_NO_PROXY.to_string(),
+        all_proxy: None,
+        env_var_style: profile_manager::load_user_config().env_var_style,
+        strict_ssl: None,
+        url_scopes: vec![],
+        shim_commands: vec![],
     };
-    config_manager::enable_proxy(&software_list, &proxy_settings)
+    let label = proxy_settings.http_proxy.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        auto_revert::cancel(&software_list);
+        let results = config_manager::enable_proxy(&software_list, &proxy_settings, &label)?;
+        history::record(OperationKind::Enable, None, &results);
+        Ok(results)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 列出配置文件需要管理员权限才能写入的软件（执行前提示用户）
+#[tauri::command]
+fn check_elevation_required(software_list: Vec<String>) -> Vec<String> {
+    config_manager::software_requiring_elevation(&software_list)
 }
 
 /// 关闭代理
+/// recreate_missing: 配置文件已被删除时是否从备份重建，默认重建
+/// strategy: "restore" 还原开启前的配置 | "strip" 直接删除代理设置，默认使用各软件选项
 #[tauri::command]
-fn disable_proxy(software_list: Vec<String>) -> Result<Vec<String>, String> {
-    config_manager::disable_proxy(&software_list)
+fn disable_proxy(
+    software_list: Vec<String>,
+    recreate_missing: Option<bool>,
+    strategy: Option<DisableStrategy>,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    auto_revert::cancel(&software_list);
+    let results =
+        config_manager::disable_proxy(&software_list, recreate_missing.unwrap_or(true), strategy)?;
+    history::record(OperationKind::Disable, None, &results);
+    Ok(results)
 }
 
 /// 重置到初始状态（还原首次备份的配置）
+/// recreate_missing: 配置文件已被删除时是否从备份重建，默认重建
+#[tauri::command]
+fn reset_proxy(
+    software_list: Vec<String>,
+    recreate_missing: Option<bool>,
+) -> Result<Vec<ApplyResult>, CommandError> {
+    auto_revert::cancel(&software_list);
+    let results =
+        config_manager::reset_to_original(&software_list, recreate_missing.unwrap_or(true))?;
+    history::record(OperationKind::Reset, None, &results);
+    Ok(results)
+}
+
+/// 在后台关闭代理并立即返回批次 ID（参数同 disable_proxy）
+#[tauri::command]
+fn start_disable_proxy_batch(
+    app: tauri::AppHandle,
+    software_list: Vec<String>,
+    recreate_missing: Option<bool>,
+    strategy: Option<DisableStrategy>,
+) -> String {
+    progress::start_batch(app, move || {
+        disable_proxy(software_list, recreate_missing, strategy)
+    })
+}
+
+/// 在后台重置到初始状态并立即返回批次 ID（参数同 reset_proxy）
+#[tauri::command]
+fn start_reset_proxy_batch(
+    app: tauri::AppHandle,
+    software_list: Vec<String>,
+    recreate_missing: Option<bool>,
+) -> String {
+    progress::start_batch(app, move || reset_proxy(software_list, recreate_missing))
+}
+
+/// 读取后台批次的结果：仍在执行时返回 null，读取后结果被移除
+#[tauri::command]
+fn get_batch_result(batch_id: String) -> Result<Option<Vec<ApplyResult>>, CommandError> {
+    progress::get_batch_result(&batch_id)
+}
+
+/// 将操作结果整理为文本报告（供前端复制到剪贴板）
+#[tauri::command]
+fn format_results_report(results: Vec<ApplyResult>) -> String {
+    config_manager::format_results_report(&results)
+}
+
+/// 手动备份当前配置到历史备份（不修改任何配置）
+#[tauri::command]
+fn create_backup(software_list: Vec<String>) -> Vec<ApplyResult> {
+    config_manager::create_backup(&software_list)
+}
+
+/// 检查所有备份的完整性，并在可以安全补建时补建缺失的初始备份
+#[tauri::command]
+fn repair_backups() -> Vec<String> {
+    config_manager::repair_backups()
+}
+
+/// 导出诊断包（脱敏后的配置、备份和错误日志），返回 zip 文件路径
+#[tauri::command]
+fn export_support_bundle() -> Result<String, CommandError> {
+    support_bundle::export_support_bundle()
+}
+
+/// 列出软件的历史备份
+#[tauri::command]
+fn list_backup_history(software_name: String) -> Vec<BackupEntry> {
+    config_manager::list_backup_history(&software_name)
+}
+
+/// 查看备份内容，which: "original" | "current" | 历史备份 ID
+#[tauri::command]
+fn get_backup_content(software_name: String, which: String) -> Result<String, CommandError> {
+    config_manager::get_backup_content(&software_name, &which)
+}
+
+/// 对比备份与当前配置，返回统一 diff
+#[tauri::command]
+fn diff_backup_against_live(software_name: String, which: String) -> Result<String, CommandError> {
+    let user_config = profile_manager::load_user_config();
+    let custom_path = user_config
+        .custom_software
+        .iter()
+        .find(|s| s.name == software_name)
+        .map(|s| s.config_path.as_str());
+
+    config_manager::diff_backup_against_live(&user_config, &software_name, &which, custom_path)
+}
+
+/// 用系统默认编辑器打开软件的配置文件
+#[tauri::command]
+fn open_config_file(app: tauri::AppHandle, software_name: String) -> Result<(), CommandError> {
+    let user_config = profile_manager::load_user_config();
+    let custom_path = user_config
+        .custom_software
+        .iter()
+        .find(|s| s.name == software_name)
+        .map(|s| s.config_path.as_str());
+
+    let config_path =
+        config_manager::resolve_config_path(&user_config, &software_name, custom_path)
+            .ok_or_else(|| tr!("config_path_unavailable"))?;
+    if !config_path.exists() {
+        return Err(tr!("config_file_not_found", path = config_path.display()));
+    }
+
+    app.opener()
+        .open_path(config_path.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string().into())
+}
+
+/// 用系统文件管理器打开备份目录
+#[tauri::command]
+fn open_backup_directory(app: tauri::AppHandle) -> Result<(), CommandError> {
+    let backup_dir = config_manager::backup_directory()?;
+    app.opener()
+        .open_path(backup_dir.to_string_lossy(), None::<&str>)
+        .map_err(|e| e.to_string().into())
+}
+
+/// 读取软件配置文件的当前内容（只读），redact 为 true 时隐藏令牌和密码
+#[tauri::command]
+fn read_software_config(software_name: String, redact: bool) -> Result<String, CommandError> {
+    let user_config = profile_manager::load_user_config();
+    let custom_path = user_config
+        .custom_software
+        .iter()
+        .find(|s| s.name == software_name)
+        .map(|s| s.config_path.as_str());
+
+    config_manager::read_software_config(&user_config, &software_name, custom_path, redact)
+}
+
+/// 新启动的进程会继承的代理环境变量（直接读取注册表等来源，用于确认写入已生效）
 #[tauri::command]
-fn reset_proxy(software_list: Vec<String>) -> Result<Vec<String>, String> {
-    config_manager::reset_to_original(&software_list)
+fn get_effective_env_proxy() -> Result<Vec<EffectiveEnvVar>, CommandError> {
+    config_manager::get_effective_env_proxy()
+}
+
+/// 将 `git config` 写入的已有 Git 代理设置纳入管理
+#[tauri::command]
+fn migrate_git_proxy() -> Result<String, CommandError> {
+    config_manager::migrate_git_proxy().map(String::from)
 }
 
 /// 添加自定义软件
 #[tauri::command]
-fn add_custom_software(software: CustomSoftware) -> Result<UserConfig, String> {
+fn add_custom_software(software: CustomSoftware) -> Result<UserConfig, CommandError> {
     profile_manager::add_custom_software(software)
 }
 
+/// 批量导入自定义软件，返回更新后的配置和每项的结果
+#[tauri::command]
+fn import_custom_software(
+    list: Vec<CustomSoftware>,
+    skip_existing: bool,
+) -> Result<CustomSoftwareImport, CommandError> {
+    profile_manager::import_custom_software(list, skip_existing)
+}
+
+/// 导出迁移包（配置组、映射，可选自定义软件和场景），返回 zip 文件路径
+#[tauri::command]
+fn export_settings(
+    options: ExportOptions,
+    destination: Option<String>,
+) -> Result<String, CommandError> {
+    settings_transfer::export_settings(options, destination)
+}
+
+/// 导入迁移包并合并到当前配置（不会自动开启代理）
+#[tauri::command]
+fn import_settings(path: String) -> Result<SettingsImport, CommandError> {
+    settings_transfer::import_settings(&path)
+}
+
+/// 扫描各软件配置中的代理设置（包括其他工具遗留的）
+#[tauri::command]
+fn scan_existing_proxies() -> Vec<ExistingProxy> {
+    config_manager::scan_existing_proxies()
+}
+
+/// 彻底清除软件中的代理设置（包括其他工具写入的），清除前先保存历史备份
+#[tauri::command]
+fn deep_clean(software_list: Vec<String>) -> Vec<ApplyResult> {
+    auto_revert::cancel(&software_list);
+    config_manager::deep_clean(&software_list)
+}
+
 /// 删除自定义软件
 #[tauri::command]
-fn delete_custom_software(software_name: String) -> Result<UserConfig, String> {
+fn delete_custom_software(software_name: String) -> Result<UserConfig, CommandError> {
     profile_manager::delete_custom_software(&software_name)
 }
 
@@ -182,25 +1076,304 @@ fn get_close_preference() -> ClosePreference {
 
 /// 保存关闭行为偏好
 #[tauri::command]
-fn save_close_preference(preference: ClosePreference) -> Result<(), String> {
-    let mut config = profile_manager::load_user_config();
-    config.close_preference = preference;
-    profile_manager::save_user_config(&config)
+fn save_close_preference(preference: ClosePreference) -> Result<(), CommandError> {
+    profile_manager::update_user_config(|config| {
+        config.close_preference = preference;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// 设置关闭行为（"minimize" 或 "exit"）
+#[tauri::command]
+fn set_close_action(action: String) -> Result<(), CommandError> {
+    profile_manager::set_close_action(&action)?;
+    Ok(())
+}
+
+/// 设置是否记住关闭行为
+#[tauri::command]
+fn set_remember_close(remember: bool) -> Result<(), CommandError> {
+    profile_manager::set_remember_close(remember)?;
+    Ok(())
+}
+
+/// 设置环境变量写入风格（UppercaseOnly / Both / All）
+#[tauri::command]
+fn set_env_var_style(style: EnvVarStyle) -> Result<UserConfig, CommandError> {
+    profile_manager::set_env_var_style(style)
+}
+
+/// 列出检测到的 IDEA 版本及当前选中的版本
+#[tauri::command]
+fn list_idea_installs() -> Vec<IdeaInstall> {
+    let preferred = profile_manager::load_user_config().idea_install;
+    config_manager::list_idea_installs(preferred.as_deref())
+}
+
+/// 手动指定写入代理设置的 IDEA 版本，传 null 恢复自动选择
+#[tauri::command]
+fn set_idea_install(name: Option<String>) -> Result<UserConfig, CommandError> {
+    if let Some(name) = &name {
+        let installs = config_manager::list_idea_installs(None);
+        if !installs.iter().any(|install| &install.name == name) {
+            return Err(tr!("idea_install_not_found", name = name));
+        }
+    }
+    profile_manager::set_idea_install(name)
+}
+
+/// 设置 Git 代理写入的配置层级："global" | "system" | "local"（local 需要仓库目录）
+#[tauri::command]
+fn set_git_scope(scope: GitScope, repo_path: Option<String>) -> Result<UserConfig, CommandError> {
+    let repo_path = match scope {
+        GitScope::Local => {
+            let repo = repo_path
+                .map(|repo| repo.trim().to_string())
+                .filter(|repo| !repo.is_empty())
+                .ok_or_else(|| tr!("git_local_repo_required"))?;
+            if config_manager::git_local_config_path(std::path::Path::new(&repo)).is_none() {
+                return Err(tr!("git_repo_not_found", path = repo));
+            }
+            Some(repo)
+        }
+        _ => None,
+    };
+    profile_manager::set_git_scope(scope, repo_path)
 }
 
+/// 设置配置已是目标值时是否跳过写入（不改变文件修改时间）
+#[tauri::command]
+fn set_skip_unchanged_writes(enabled: bool) -> Result<UserConfig, CommandError> {
+    profile_manager::set_skip_unchanged_writes(enabled)
+}
+
+/// 设置保存配置组时是否检查地址能否解析、端口是否有程序监听
+#[tauri::command]
+fn set_check_profile_endpoints(enabled: bool) -> Result<UserConfig, CommandError> {
+    profile_manager::set_check_profile_endpoints(enabled)
+}
+
+/// 设置开启代理后是否重新读取配置验证写入是否生效
+#[tauri::command]
+fn set_verify_after_apply(enabled: bool) -> Result<UserConfig, CommandError> {
+    profile_manager::set_verify_after_apply(enabled)
+}
+
+/// 设置启动时是否隐藏窗口（只显示托盘图标）
+#[tauri::command]
+fn set_start_hidden(enabled: bool) -> Result<UserConfig, CommandError> {
+    profile_manager::set_start_hidden(enabled)
+}
+
+/// 设置按进程名检测端口时接受的端口范围（默认 1001-65534）
+#[tauri::command]
+fn set_detection_port_range(range: PortRange) -> Result<UserConfig, CommandError> {
+    profile_manager::set_detection_port_range(range)
+}
+
+/// 设置后台健康检查（开启、间隔分钟数、测试目标）
+#[tauri::command]
+fn set_health_check(settings: HealthCheckSettings) -> Result<UserConfig, CommandError> {
+    profile_manager::set_health_check(settings)
+}
+
+/// 获取配置组在 since（Unix 时间戳，秒，默认 24 小时前）之后的健康检查记录
+#[tauri::command]
+fn get_profile_health(profile_name: String, since: Option<u64>) -> Vec<HealthSample> {
+    let since = since.unwrap_or_else(|| applied_state::now_secs().saturating_sub(24 * 60 * 60));
+    health_check::get_profile_health(&profile_name, since)
+}
+
+/// 检查是否有新版本（在后台线程请求发布接口）
+#[tauri::command]
+async fn check_for_update() -> Result<UpdateInfo, CommandError> {
+    tauri::async_runtime::spawn_blocking(update_check::check_for_update)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// 手动指定软件的配置文件路径（path 为空时恢复自动查找），开启/关闭代理、备份和状态检测都使用该路径
+#[tauri::command]
+fn set_config_path_override(
+    software_name: String,
+    path: Option<String>,
+) -> Result<UserConfig, CommandError> {
+    let software =
+        config_manager::get_software_list_with_custom(&profile_manager::load_user_config())
+            .into_iter()
+            .find(|software| profile_manager::names_eq(&software.name, &software_name))
+            .ok_or_else(|| tr!("software_not_found", name = software_name))?;
+    // 环境变量、系统代理等软件没有配置文件
+    if matches!(
+        software.config_type.as_str(),
+        "env" | "launchd" | "registry"
+    ) {
+        return Err(tr!(
+            "config_path_override_unsupported",
+            name = software.name
+        ));
+    }
+    let path = path.filter(|p| !p.trim().is_empty());
+    profile_manager::set_config_path_override(&software_name, path)
+}
+
+/// 设置软件是否受保护：受保护的软件在重置和彻底清除时跳过
+#[tauri::command]
+fn set_software_protected(
+    software_name: String,
+    protected: bool,
+) -> Result<UserConfig, CommandError> {
+    let known = config_manager::get_software_list_with_custom(&profile_manager::load_user_config())
+        .iter()
+        .any(|software| profile_manager::names_eq(&software.name, &software_name));
+    if !known {
+        return Err(tr!("software_not_found", name = software_name));
+    }
+    profile_manager::set_software_protected(&software_name, protected)
+}
+
+/// 是否以安全模式启动（--safe-mode 或环境变量 PROXY_MANAGER_SAFE_MODE）
+#[tauri::command]
+fn is_safe_mode() -> bool {
+    paths::is_safe_mode()
+}
+
+/// 设置启动时是否自动检查新版本
+#[tauri::command]
+fn set_auto_check_updates(enabled: bool) -> Result<UserConfig, CommandError> {
+    profile_manager::set_auto_check_updates(enabled)
+}
+
+/// 开启或关闭配置文件的外部修改监听
+#[tauri::command]
+fn set_config_watch_enabled(enabled: bool) -> Result<UserConfig, CommandError> {
+    let config = profile_manager::set_config_watch_enabled(enabled)?;
+    config_watcher::set_enabled(enabled);
+    Ok(config)
+}
+
+/// 获取包装脚本目录（使用包装脚本时需要加入 PATH）
+#[tauri::command]
+fn get_shim_directory() -> Option<String> {
+    shims::shim_dir().map(|dir| dir.to_string_lossy().into_owned())
+}
+
+/// 获取当前使用的配置和数据存储位置
+#[tauri::command]
+fn get_storage_paths() -> StoragePaths {
+    paths::storage_paths()
+}
+
+/// 获取配置文件的位置
+#[tauri::command]
+fn get_config_location() -> String {
+    paths::user_config_path().to_string_lossy().into_owned()
+}
+
+/// 把旧位置（~/.proxy-manager）的配置迁移到系统配置目录，返回是否进行了迁移
+#[tauri::command]
+fn migrate_legacy_config() -> Result<bool, CommandError> {
+    paths::migrate_legacy_config()
+}
+
+/// 开启或关闭便携模式（配置保存在可执行文件旁）
+#[tauri::command]
+fn set_portable_mode(enabled: bool) -> Result<StoragePaths, CommandError> {
+    paths::set_portable_mode(enabled)
+}
+
+/// 获取当前界面语言
+#[tauri::command]
+fn get_locale() -> String {
+    i18n::current_locale()
+}
+
+/// 设置界面语言（zh-CN / en-US）
+#[tauri::command]
+fn set_locale(locale: String) -> Result<(), CommandError> {
+    i18n::set_locale(&locale)?;
+    profile_manager::update_user_config(|config| {
+        config.locale = locale;
+        Ok(())
+    })?;
+    Ok(())
+}
+
+/// 开机自启动时附加的命令行参数
+const AUTOSTART_ARG: &str = "--autostart";
+
+/// 以安全模式启动时发送的事件（前端加载较晚时可调用 is_safe_mode 查询）
+const SAFE_MODE_EVENT: &str = "safe-mode";
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    tauri::Builder::default()
+    // 尽早安装，setup 过程中的 panic 也能写入错误日志
+    error_report::install_panic_hook();
+
+    // 以管理员权限重新启动的进程只执行请求中的写入，不启动界面
+    if let Some(request_path) = elevation::elevated_request_arg() {
+        if let Err(e) = elevation::apply_elevated_request(&request_path) {
+            error_report::log_error(&format!("提权执行失败: {}", e));
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let result = tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
+            error_report::attach(app.handle().clone());
+
+            // 安全模式：不执行任何迁移、扫描和后台任务，配置文件只读（见 paths::is_safe_mode）
+            let safe_mode = paths::is_safe_mode();
+
+            // 旧版本升级：把 ~/.proxy-manager 中的配置迁移到系统配置目录
+            if !safe_mode {
+                if let Err(e) = paths::migrate_legacy_config() {
+                    error_report::report_error(e.as_str());
+                }
+            }
+
+            // 加载界面语言，未知语言回退到默认语言
+            let user_config = profile_manager::load_user_config();
+            let _ = i18n::set_locale(&user_config.locale);
+
+            if safe_mode {
+                let _ = app.emit(SAFE_MODE_EVENT, ());
+            } else {
+                // 监听配置文件的外部修改（例如通过 dotfiles 同步或手动编辑）
+                config_watcher::set_enabled(user_config.config_watch_enabled);
+                if let Err(e) = config_watcher::start(app.handle().clone()) {
+                    error_report::report_error(&format!("监听配置文件失败: {}", e));
+                }
+
+                // 旧版本升级：从现有配置初始化已应用状态
+                if !applied_state::is_initialized() {
+                    let _ = applied_state::initialize(scan_applied_state());
+                }
+
+                // 上次退出前安排的临时代理自动关闭（已到期的立即关闭）
+                auto_revert::resume(app.handle().clone());
+
+                // 后台健康检查（未开启时只定期读取设置）
+                health_check::start(app.handle().clone());
+
+                // 自动检查新版本（默认关闭）
+                if user_config.auto_check_updates {
+                    update_check::check_in_background(app.handle().clone());
+                }
+            }
+
             // 创建托盘菜单
-            let show_item = MenuItem::with_id(app, "show", "显示窗口", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "退出", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show_item, &quit_item])?;
+            let menu = tray::build_menu(app.handle())?;
 
-            // 创建系统托盘
-            let _tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
+            // 创建系统托盘（缺少默认图标时不设置图标，而不是让程序启动失败）
+            let mut tray_builder = TrayIconBuilder::with_id(tray::TRAY_ID);
+            if let Some(icon) = app.default_window_icon() {
+                tray_builder = tray_builder.icon(icon.clone());
+            }
+            let _tray = tray_builder
                 .menu(&menu)
                 .show_menu_on_left_click(false)
                 .tooltip("Proxy Manager")
@@ -214,7 +1387,7 @@ pub fn run() {
                     "quit" => {
                         app.exit(0);
                     }
-                    _ => {}
+                    id => tray::handle_menu_event(app, id),
                 })
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::Click {
@@ -232,35 +1405,266 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            // 窗口默认不可见，设置了启动时隐藏或由开机自启动时保持隐藏，避免窗口闪现
+            // 安全模式下总是显示窗口
+            let autostarted = std::env::args().any(|arg| arg == AUTOSTART_ARG);
+            if safe_mode || (!user_config.start_hidden && !autostarted) {
+                if let Some(window) = app.get_webview_window("main") {
+                    let _ = window.show();
+                }
+            }
+
             Ok(())
         })
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                // 阻止默认关闭行为，发送事件到前端让前端处理
+                // 用户已记住选择时直接执行，不依赖前端（前端无响应时也能正常关闭）
+                let preference = profile_manager::load_user_config().close_preference;
+                if preference.remember && preference.action == "exit" {
+                    window.app_handle().exit(0);
+                    return;
+                }
+
                 api.prevent_close();
-                let _ = window.emit("close-requested", ());
+                if preference.remember && preference.action == "minimize" {
+                    let _ = window.hide();
+                } else {
+                    // 未记住选择，发送事件到前端让用户选择
+                    let _ = window.emit("close-requested", ());
+                }
             }
         })
         .invoke_handler(tauri::generate_handler![
             get_vpn_list,
+            get_vpn_list_with_status,
             detect_port,
+            detect_port_verbose,
+            detect_all_vpns,
+            check_port_conflicts,
+            scan_proxy_ports,
+            detect_via_clash_api,
+            resolve_pac,
+            generate_recommended_setup,
+            list_orphaned_mappings,
+            prune_orphaned_mappings,
+            apply_recommended_setup,
+            apply_detected_port,
             get_software_list,
+            get_dashboard,
             get_user_config,
+            get_config_load_status,
             save_user_config,
             add_proxy_profile,
+            update_proxy_profile,
+            diff_profiles,
             delete_proxy_profile,
+            delete_proxy_profile_force,
             update_software_mapping,
+            set_mapping_proxy_localhost,
+            set_mapping_no_proxy_override,
+            update_mapping_options,
             enable_proxy,
             enable_proxy_with_profiles,
+            start_enable_proxy_batch,
+            enable_temporarily,
+            enable_smart,
+            get_applied_state,
+            migrate_jetbrains_config,
+            convert_bypass_list,
+            list_full_snapshots,
+            restore_full_snapshot,
+            check_stale_proxies,
+            refresh_stale_proxies,
+            list_scenes,
+            save_current_as_scene,
+            apply_scene,
+            get_operation_history,
+            delete_scene,
+            check_elevation_required,
             disable_proxy,
             reset_proxy,
+            start_disable_proxy_batch,
+            start_reset_proxy_batch,
+            get_batch_result,
+            migrate_git_proxy,
+            read_software_config,
+            get_effective_env_proxy,
+            format_results_report,
+            create_backup,
+            repair_backups,
+            export_support_bundle,
+            list_backup_history,
+            get_backup_content,
+            diff_backup_against_live,
+            open_config_file,
+            open_backup_directory,
             add_custom_software,
+            import_custom_software,
+            export_settings,
+            import_settings,
+            scan_existing_proxies,
+            deep_clean,
             delete_custom_software,
             exit_app,
             hide_window,
             get_close_preference,
-            save_close_preference
+            save_close_preference,
+            set_close_action,
+            set_remember_close,
+            set_env_var_style,
+            list_idea_installs,
+            set_idea_install,
+            set_git_scope,
+            set_config_watch_enabled,
+            set_detection_port_range,
+            set_health_check,
+            get_profile_health,
+            check_for_update,
+            set_auto_check_updates,
+            set_config_path_override,
+            is_safe_mode,
+            set_software_protected,
+            set_start_hidden,
+            set_skip_unchanged_writes,
+            set_verify_after_apply,
+            set_check_profile_endpoints,
+            get_shim_directory,
+            get_storage_paths,
+            get_config_location,
+            migrate_legacy_config,
+            set_portable_mode,
+            get_locale,
+            set_locale
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .run(tauri::generate_context!());
+
+    if let Err(e) = result {
+        error_report::log_error(&format!("error while running tauri application: {}", e));
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paths::Environment;
+    use std::fs;
+
+    fn mapping(software_name: &str) -> SoftwareProxyMapping {
+        SoftwareProxyMapping {
+            software_name: software_name.to_string(),
+            profile_name: "Office".to_string(),
+            proxy_localhost: false,
+            no_proxy_override: None,
+            options: Default::default(),
+        }
+    }
+
+    #[test]
+    fn transactional_batch_restores_earlier_software_when_one_fails() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let gitconfig = home.path().join(".gitconfig");
+        let npmrc = home.path().join(".npmrc");
+        let aria2_conf = home.path().join(".aria2/aria2.conf");
+        let gemrc = home.path().join(".gemrc");
+        fs::write(&gitconfig, "[user]\n\tname = Tester\n").unwrap();
+        fs::write(&npmrc, "registry=https://registry.npmjs.org/\n").unwrap();
+        // 不是 YAML 映射的 .gemrc 无法写入代理，使批次中的第 4 个软件失败
+        fs::write(&gemrc, "- not a mapping\n").unwrap();
+        let before = [&gitconfig, &npmrc, &gemrc].map(|path| fs::read(path).unwrap());
+
+        let results = env.run(|| {
+            profile_manager::add_profile(ProxyProfile {
+                name: "Office".to_string(),
+                host: "127.0.0.1".to_string(),
+                port: 7890,
+                socks_port: None,
+                vpn: None,
+                bypass: None,
+                proxy_scheme: Default::default(),
+            })
+            .unwrap();
+            let mappings = ["Git", "npm", "aria2", "RubyGems"].map(mapping).to_vec();
+            enable_with_profiles(mappings, None, Some(true)).unwrap()
+        });
+
+        assert!(results.iter().all(|r| !r.success), "{:?}", results);
+        for result in &results[..3] {
+            assert!(result.message.contains("RubyGems"), "{}", result.message);
+        }
+        let after = [&gitconfig, &npmrc, &gemrc].map(|path| fs::read(path).unwrap());
+        assert_eq!(after, before);
+        assert!(!aria2_conf.exists());
+        assert!(env
+            .run(|| config_manager::load_applied_status(&profile_manager::load_user_config()))
+            .entries
+            .is_empty());
+    }
+
+    #[test]
+    fn applying_scene_disables_software_marked_off_and_records_one_entry() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let gitconfig = home.path().join(".gitconfig");
+        let npmrc = home.path().join(".npmrc");
+        fs::write(&gitconfig, "[user]\n\tname = Tester\n").unwrap();
+        fs::write(&npmrc, "registry=https://registry.npmjs.org/\n").unwrap();
+        let original_gitconfig = fs::read(&gitconfig).unwrap();
+
+        env.run(|| {
+            profile_manager::add_profile(ProxyProfile {
+                name: "Office".to_string(),
+                host: "127.0.0.1".to_string(),
+                port: 7890,
+                socks_port: None,
+                vpn: None,
+                bypass: None,
+                proxy_scheme: Default::default(),
+            })
+            .unwrap();
+            // 手动开启、没有映射的软件也应按场景关闭
+            let proxy = ProxySettings {
+                http_proxy: "http://127.0.0.1:7890".to_string(),
+                ..Default::default()
+            };
+            config_manager::enable_proxy(&["Git".to_string()], &proxy, "Office").unwrap();
+            profile_manager::save_scene(Scene {
+                name: " Travel ".to_string(),
+                mappings: vec![mapping("npm")],
+                disabled_software: vec!["Git".to_string()],
+            })
+            .unwrap();
+
+            let results = activate_scene("travel").unwrap();
+            assert!(results.iter().all(|r| r.success), "{:?}", results);
+
+            let history = history::load_history();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].kind, OperationKind::Scene);
+            assert_eq!(history[0].scene.as_deref(), Some("Travel"));
+            assert_eq!(history[0].succeeded, ["npm", "Git"]);
+        });
+
+        assert_eq!(fs::read(&gitconfig).unwrap(), original_gitconfig);
+        assert!(fs::read_to_string(&npmrc).unwrap().contains("7890"));
+    }
+    #[test]
+    fn disabling_is_the_last_operation() {
+        let home = tempfile::tempdir().unwrap();
+        fs::write(home.path().join(".gitconfig"), "[user]\n\tname = Tester\n").unwrap();
+
+        Environment::sandbox(home.path()).run(|| {
+            let proxy = ProxySettings {
+                http_proxy: "http://127.0.0.1:7890".to_string(),
+                ..Default::default()
+            };
+            config_manager::enable_proxy(&["Git".to_string()], &proxy, "Clash").unwrap();
+            disable_proxy(vec!["Git".to_string()], None, None).unwrap();
+
+            let last = history::last_operation().unwrap();
+            assert_eq!(last.kind, OperationKind::Disable);
+            assert_eq!(last.succeeded, ["Git"]);
+        });
+    }
 }
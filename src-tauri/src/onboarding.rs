@@ -0,0 +1,303 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config_manager::{self, SoftwareConfig};
+use crate::i18n::Message;
+use crate::port_detector::{self, DetectionResult};
+use crate::profile_manager::{
+    self, MappingOptions, ProxyProfile, ProxyScheme, SoftwareProxyMapping, UserConfig,
+};
+
+/// 首次使用时推荐的配置（只是建议，不会自动保存）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecommendedSetup {
+    pub vpn: Option<String>,                 // 检测到正在运行的 VPN
+    pub profiles: Vec<ProxyProfile>,         // 需要新增的配置组
+    pub mappings: Vec<SoftwareProxyMapping>, // 每个已安装软件的映射
+}
+
+/// 检测正在运行的 VPN 并生成推荐配置
+pub fn generate_recommended_setup() -> RecommendedSetup {
+    // 按预设顺序排列，推荐时选择第一个正在运行的 VPN
+    let mut all = port_detector::detect_all_vpns();
+    let detections: Vec<(String, DetectionResult)> = port_detector::get_vpn_configs()
+        .into_iter()
+        .filter_map(|vpn| all.remove_entry(&vpn.name))
+        .collect();
+    let config = profile_manager::load_user_config();
+    let software = config_manager::get_software_list(&config);
+
+    recommend_setup(&detections, &software, &config.profiles)
+}
+
+/// 推荐逻辑：
+/// - 选择第一个正在运行（有真实监听端口）的 VPN，优先使用其 HTTP 端口
+/// - 已有同名配置组时不覆盖：端口相同则直接使用，不同则换一个不冲突的名称
+/// - 只为已安装的软件生成映射；没有运行中的 VPN 时使用已有的第一个配置组
+pub fn recommend_setup(
+    detections: &[(String, DetectionResult)],
+    software: &[SoftwareConfig],
+    existing: &[ProxyProfile],
+) -> RecommendedSetup {
+    let running = detections
+        .iter()
+        .find(|(_, detection)| detection.ports.iter().any(|p| p.pid != 0));
+
+    let mut profiles = Vec::new();
+    let profile_name = match running {
+        Some((vpn, detection)) => {
+            let http = port_detector::select_detected_port(&detection.ports, "http").ok();
+            let socks = port_detector::select_detected_port(&detection.ports, "socks").ok();
+            let port = http
+                .or(socks)
+                .or_else(|| detection.ports.iter().find(|p| p.pid != 0))
+                .map(|p| p.port);
+
+            match port {
+                Some(port) => {
+                    // 不论端口监听在哪个地址，本机都通过回环地址连接
+                    let mut profile = ProxyProfile {
+                        name: vpn.clone(),
+                        host: "127.0.0.1".to_string(),
+                        port,
+                        socks_port: socks.map(|p| p.port).filter(|p| *p != port),
+                        vpn: Some(vpn.clone()),
+                        bypass: None,
+                        proxy_scheme: ProxyScheme::Http,
+                    };
+                    profile_manager::seed_profile_bypass(&mut profile);
+                    let name = unique_profile_name(&profile, existing);
+                    if !existing
+                        .iter()
+                        .any(|p| profile_manager::names_eq(&p.name, &name))
+                    {
+                        profiles.push(ProxyProfile {
+                            name: name.clone(),
+                            ..profile
+                        });
+                    }
+                    Some(name)
+                }
+                None => existing.first().map(|p| p.name.clone()),
+            }
+        }
+        None => existing.first().map(|p| p.name.clone()),
+    };
+
+    let mappings = match &profile_name {
+        Some(profile_name) => software
+            .iter()
+            .filter(|s| s.installed)
+            .map(|s| SoftwareProxyMapping {
+                software_name: s.name.clone(),
+                profile_name: profile_name.clone(),
+                proxy_localhost: false,
+                no_proxy_override: None,
+                options: MappingOptions::default(),
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    RecommendedSetup {
+        vpn: running.map(|(vpn, _)| vpn.clone()),
+        profiles,
+        mappings,
+    }
+}
+
+/// 已有同名且地址相同的配置组时直接使用；名称被占用但地址不同时追加序号
+fn unique_profile_name(profile: &ProxyProfile, existing: &[ProxyProfile]) -> String {
+    let same_address = |p: &ProxyProfile| p.host == profile.host && p.port == profile.port;
+
+    let mut name = profile.name.clone();
+    let mut index = 2;
+    while let Some(taken) = existing
+        .iter()
+        .find(|p| profile_manager::names_eq(&p.name, &name))
+    {
+        if same_address(taken) {
+            break;
+        }
+        name = format!("{} ({})", profile.name, index);
+        index += 1;
+    }
+    name
+}
+
+/// 保存推荐配置：在同一次写入中新增配置组并更新映射，任何一项校验失败都不会保存
+pub fn apply_recommended_setup(setup: RecommendedSetup) -> Result<UserConfig, Message> {
+    let profiles = setup
+        .profiles
+        .into_iter()
+        .map(profile_manager::validate_profile)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    profile_manager::update_user_config(|config| {
+        for profile in profiles {
+            if profile_manager::find_profile(config, &profile.name).is_some() {
+                return Err(tr!("profile_exists", name = profile.name));
+            }
+            config.profiles.push(profile);
+        }
+
+        for mapping in setup.mappings {
+            if !profile_manager::profile_exists(config, &mapping.profile_name) {
+                return Err(tr!("profile_not_found", name = mapping.profile_name));
+            }
+            match config
+                .mappings
+                .iter_mut()
+                .find(|m| m.software_name == mapping.software_name)
+            {
+                Some(existing) => existing.profile_name = mapping.profile_name,
+                None => config.mappings.push(mapping),
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paths::Environment;
+    use crate::port_detector::DetectedPort;
+
+    fn port(port: u16, port_type: &str, pid: u32) -> DetectedPort {
+        DetectedPort {
+            port,
+            port_type: port_type.to_string(),
+            process_name: "vpn".to_string(),
+            pid,
+            bind_scope: Default::default(),
+        }
+    }
+
+    fn detection(ports: Vec<DetectedPort>) -> DetectionResult {
+        DetectionResult {
+            success: true,
+            message: String::new(),
+            ports,
+            config_file: None,
+        }
+    }
+
+    fn software(name: &str, installed: bool) -> SoftwareConfig {
+        SoftwareConfig {
+            name: name.to_string(),
+            config_type: "ini".to_string(),
+            enabled: false,
+            installed,
+            config_exists: installed,
+            config_path: None,
+            is_custom: false,
+            capabilities: Default::default(),
+            warning: None,
+        }
+    }
+
+    fn profile(name: &str, port: u16) -> ProxyProfile {
+        ProxyProfile {
+            name: name.to_string(),
+            host: "127.0.0.1".to_string(),
+            port,
+            socks_port: None,
+            vpn: None,
+            bypass: None,
+            proxy_scheme: ProxyScheme::Http,
+        }
+    }
+
+    #[test]
+    fn running_vpn_http_port_is_preferred() {
+        let detections = [
+            (
+                "V2rayN".to_string(),
+                detection(vec![port(10808, "socks", 0)]),
+            ),
+            (
+                "Clash".to_string(),
+                detection(vec![port(7891, "socks", 42), port(7890, "http", 42)]),
+            ),
+        ];
+        let setup = recommend_setup(&detections, &[software("Git", true)], &[]);
+
+        assert_eq!(setup.vpn.as_deref(), Some("Clash"));
+        assert_eq!(setup.profiles.len(), 1);
+        assert_eq!(setup.profiles[0].name, "Clash");
+        assert_eq!(setup.profiles[0].port, 7890);
+        assert_eq!(setup.profiles[0].socks_port, Some(7891));
+        assert_eq!(setup.mappings[0].profile_name, "Clash");
+    }
+
+    #[test]
+    fn software_not_installed_is_skipped() {
+        let detections = [("Clash".to_string(), detection(vec![port(7890, "http", 1)]))];
+        let list = [software("Git", true), software("npm", false)];
+        let setup = recommend_setup(&detections, &list, &[]);
+
+        let names: Vec<_> = setup
+            .mappings
+            .iter()
+            .map(|m| m.software_name.as_str())
+            .collect();
+        assert_eq!(names, ["Git"]);
+    }
+
+    #[test]
+    fn existing_profile_with_same_name_is_not_overwritten() {
+        let detections = [("Clash".to_string(), detection(vec![port(7890, "http", 1)]))];
+        let list = [software("Git", true)];
+
+        // 同名同地址：直接使用已有的配置组
+        let setup = recommend_setup(&detections, &list, &[profile("clash", 7890)]);
+        assert!(setup.profiles.is_empty());
+        assert_eq!(setup.mappings[0].profile_name, "Clash");
+
+        // 同名不同端口：换一个不冲突的名称
+        let existing = [profile("Clash", 1080), profile("Clash (2)", 1081)];
+        let setup = recommend_setup(&detections, &list, &existing);
+        assert_eq!(setup.profiles[0].name, "Clash (3)");
+        assert_eq!(setup.mappings[0].profile_name, "Clash (3)");
+    }
+
+    #[test]
+    fn without_running_vpn_first_existing_profile_is_used() {
+        let detections = [("Clash".to_string(), detection(vec![port(7890, "http", 0)]))];
+        let list = [software("Git", true)];
+
+        let setup = recommend_setup(&detections, &list, &[profile("Office", 3128)]);
+        assert_eq!(setup.vpn, None);
+        assert!(setup.profiles.is_empty());
+        assert_eq!(setup.mappings[0].profile_name, "Office");
+
+        assert!(recommend_setup(&detections, &list, &[]).mappings.is_empty());
+    }
+
+    #[test]
+    fn invalid_setup_is_not_saved() {
+        let home = tempfile::tempdir().unwrap();
+        Environment::sandbox(home.path()).run(|| {
+            let before = profile_manager::load_user_config();
+            let setup = RecommendedSetup {
+                vpn: None,
+                profiles: vec![profile("Office", 3128)],
+                mappings: vec![SoftwareProxyMapping {
+                    software_name: "Git".to_string(),
+                    profile_name: "Missing".to_string(),
+                    proxy_localhost: false,
+                    no_proxy_override: None,
+                    options: MappingOptions::default(),
+                }],
+            };
+            assert_eq!(
+                apply_recommended_setup(setup).err(),
+                Some(tr!("profile_not_found", name = "Missing"))
+            );
+
+            let after = profile_manager::load_user_config();
+            assert_eq!(after.profiles.len(), before.profiles.len());
+            assert!(profile_manager::find_profile(&after, "Office").is_none());
+        });
+    }
+}
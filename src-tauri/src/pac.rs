@@ -0,0 +1,266 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::http_fetch;
+use crate::i18n::Message;
+use crate::port_detector;
+
+/// 下载 PAC 文件的超时（秒）
+const PAC_FETCH_TIMEOUT_SECS: u32 = 10;
+
+/// PAC 文件中解析出的代理
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedProxy {
+    pub kind: String, // "proxy"（HTTP）、"https"、"socks"、"socks4"、"socks5" 或 "direct"
+    pub host: Option<String>, // direct 时为空；IPv6 地址不含方括号
+    pub port: Option<u16>,
+    pub matched: bool, // 按 test_url 推断为该 URL 会使用的结果
+}
+
+/// 解析 PAC 返回值中的一项，如 "PROXY proxy.corp.com:8080"、"DIRECT"
+fn parse_proxy_entry(entry: &str) -> Option<ResolvedProxy> {
+    let mut parts = entry.split_whitespace();
+    let kind = parts.next()?.to_lowercase();
+    if kind == "direct" {
+        return Some(ResolvedProxy {
+            kind,
+            host: None,
+            port: None,
+            matched: false,
+        });
+    }
+    if !matches!(
+        kind.as_str(),
+        "proxy" | "http" | "https" | "socks" | "socks4" | "socks5"
+    ) {
+        return None;
+    }
+    let (host, port) = parts.next()?.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return None;
+    }
+    Some(ResolvedProxy {
+        kind: if kind == "http" {
+            "proxy".to_string()
+        } else {
+            kind
+        },
+        host: Some(host.to_string()),
+        port: Some(port.parse().ok()?),
+        matched: false,
+    })
+}
+
+/// 解析 PAC 返回值（多项以分号分隔），不是代理列表时返回 None
+fn parse_proxy_list(value: &str) -> Option<Vec<ResolvedProxy>> {
+    let entries: Vec<&str> = value
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .collect();
+    if entries.is_empty() {
+        return None;
+    }
+    entries.into_iter().map(parse_proxy_entry).collect()
+}
+
+/// 脚本中的字符串字面量（跳过注释），返回 (起始位置, 内容)
+fn string_literals(script: &str) -> Vec<(usize, String)> {
+    let bytes = script.as_bytes();
+    let mut literals = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = script[i..].find('\n').map_or(bytes.len(), |end| i + end);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = script[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| i + 2 + end + 2);
+            }
+            quote @ (b'"' | b'\'') => {
+                let start = i;
+                let mut content = String::new();
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    if let Some(c) = script.get(i..).and_then(|rest| rest.chars().next()) {
+                        content.push(c);
+                        i += c.len_utf8();
+                    } else {
+                        i += 1;
+                    }
+                }
+                literals.push((start, content));
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    literals
+}
+
+/// 从测试 URL 中取出主机名（小写）
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.trim().split_once("://")?.1;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host_port.strip_prefix('[') {
+        Some(inner) => inner.split_once(']')?.0,
+        None => host_port
+            .rsplit_once(':')
+            .map_or(host_port, |(host, _)| host),
+    };
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+/// PAC 中常见的主机判断函数（只识别以字符串常量为参数的形式）
+/// 返回 None 表示无法识别，调用方不据此推断
+fn eval_predicate(predicate: &str, host: &str, url: &str) -> Option<bool> {
+    let predicate = predicate.trim().trim_matches(['(', ')']).trim();
+    let (name, args) = predicate.split_once('(')?;
+    let args: Vec<&str> = args
+        .trim_end_matches(')')
+        .split(',')
+        .map(|arg| arg.trim().trim_matches(['"', '\'']))
+        .collect();
+    let glob = |pattern: &str, text: &str| {
+        let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+        let text: Vec<char> = text.to_lowercase().chars().collect();
+        port_detector::glob_match(&pattern, &text)
+    };
+    match (name.trim(), args.as_slice()) {
+        ("isPlainHostName", ["host"]) => Some(!host.contains('.')),
+        ("dnsDomainIs", ["host", domain]) => Some(host.ends_with(&domain.to_lowercase())),
+        ("localHostOrDomainIs", ["host", full]) => {
+            let full = full.to_lowercase();
+            Some(host == full || (!host.contains('.') && full.starts_with(&format!("{}.", host))))
+        }
+        ("shExpMatch", ["host", pattern]) => Some(glob(pattern, host)),
+        ("shExpMatch", ["url", pattern]) => Some(glob(pattern, url)),
+        _ => None,
+    }
+}
+
+/// 计算 if 条件：只支持以 || 连接的可识别判断（见 eval_predicate）
+fn eval_condition(condition: &str, host: &str, url: &str) -> Option<bool> {
+    if condition.contains("&&") || condition.contains('!') {
+        return None;
+    }
+    let mut result = false;
+    for predicate in condition.split("||") {
+        result |= eval_predicate(predicate, host, url)?;
+    }
+    Some(result)
+}
+
+/// 语句片段中最后一个 if 的条件（括号内的内容），没有 if 时返回 None
+fn last_if_condition(segment: &str) -> Option<&str> {
+    // 只匹配关键字 if（前面不是标识符字符，后面紧跟括号），跳过字符串中的 ".gif" 之类
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let (start, _) = segment.rmatch_indices("if").find(|(start, _)| {
+        let before = segment[..*start].chars().next_back();
+        let after = segment[start + 2..].trim_start().chars().next();
+        !before.is_some_and(is_ident) && after == Some('(')
+    })?;
+    let open = start + segment[start..].find('(')?;
+    let mut depth = 0;
+    for (offset, c) in segment[open..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&segment[open + 1..open + offset]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 提取 PAC 中的所有代理（按出现顺序去重）。未嵌入 JS 引擎，不执行脚本：
+/// 按顺序查看 `if (...) return "...";` 形式的语句，条件可识别且对 test_url 成立的第一条
+/// （或没有条件的 return）视为该 URL 会使用的结果，其中的代理标记为 matched 并排在最前
+fn extract_proxies(script: &str, test_url: &str) -> Vec<ResolvedProxy> {
+    let host = url_host(test_url).unwrap_or_default();
+    let mut matched: Option<Vec<ResolvedProxy>> = None;
+    let mut all: Vec<ResolvedProxy> = Vec::new();
+    let mut segment_start = 0;
+
+    for (offset, literal) in string_literals(script) {
+        let Some(list) = parse_proxy_list(&literal) else {
+            continue;
+        };
+        let before = script[..offset].trim_end();
+        if matched.is_none() && before.ends_with("return") {
+            let segment = &script[segment_start..before.len() - "return".len()];
+            let applies = match last_if_condition(segment) {
+                Some(condition) => eval_condition(condition, &host, test_url) == Some(true),
+                None => true,
+            };
+            if applies {
+                matched = Some(list.clone());
+            }
+            // 下一条语句从本条返回值之后开始
+            segment_start = offset;
+        }
+        for proxy in list {
+            if !all.contains(&proxy) {
+                all.push(proxy);
+            }
+        }
+    }
+
+    let mut result: Vec<ResolvedProxy> = matched
+        .unwrap_or_default()
+        .into_iter()
+        .map(|proxy| ResolvedProxy {
+            matched: true,
+            ..proxy
+        })
+        .collect();
+    for proxy in all {
+        if !result
+            .iter()
+            .any(|p| p.kind == proxy.kind && p.host == proxy.host && p.port == proxy.port)
+        {
+            result.push(proxy);
+        }
+    }
+    result
+}
+
+/// 读取 PAC 文件：支持 http(s) URL、file:// URL 和本地路径
+fn fetch_pac(pac_url: &str) -> Result<String, Message> {
+    let pac_url = pac_url.trim();
+    let local = pac_url.strip_prefix("file://").unwrap_or(pac_url);
+    if !pac_url.contains("://") || pac_url.starts_with("file://") {
+        return fs::read_to_string(Path::new(local))
+            .map_err(|e| tr!("pac_fetch_failed", url = pac_url, error = e));
+    }
+    let content = http_fetch::get(pac_url, PAC_FETCH_TIMEOUT_SECS, &[])
+        .map_err(|e| tr!("pac_fetch_failed", url = pac_url, error = e))?;
+    Ok(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// 下载 PAC 文件并提取可以填入配置组的代理
+pub fn resolve_pac(pac_url: &str, test_url: &str) -> Result<Vec<ResolvedProxy>, Message> {
+    let script = fetch_pac(pac_url)?;
+    if !script.contains("FindProxyForURL") {
+        return Err(tr!("pac_invalid", url = pac_url));
+    }
+    let proxies = extract_proxies(&script, test_url);
+    if proxies.is_empty() {
+        return Err(tr!("pac_no_proxy_found"));
+    }
+    Ok(proxies)
+}
@@ -0,0 +1,368 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::file_lock;
+use crate::i18n::Message;
+
+/// 应用目录名称
+const APP_DIR_NAME: &str = "proxy-manager";
+
+/// 覆盖配置目录的环境变量
+const CONFIG_DIR_ENV: &str = "PROXY_MANAGER_CONFIG_DIR";
+
+/// 以安全模式启动的命令行参数和环境变量（环境变量为非空且不为 "0" 时生效）
+const SAFE_MODE_ARG: &str = "--safe-mode";
+const SAFE_MODE_ENV: &str = "PROXY_MANAGER_SAFE_MODE";
+
+/// 便携模式标记文件：可执行文件旁存在该文件时，配置和数据都保存在可执行文件目录
+const PORTABLE_MARKER: &str = "portable";
+
+/// 配置文件名
+const USER_CONFIG_FILE: &str = "user_config.json";
+
+/// 迁移后旧配置文件的新名称（保留以便手动恢复）
+const MIGRATED_CONFIG_FILE: &str = "user_config.json.migrated";
+
+/// 迁移后在旧目录留下的说明文件，内容为新的配置文件路径
+const MOVED_POINTER_FILE: &str = "MOVED.txt";
+
+/// 当前使用的存储位置（供设置页面展示）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoragePaths {
+    pub config_path: String,
+    pub data_dir: String,
+    pub portable_mode: bool,
+}
+
+/// 查找各软件配置文件和本程序数据时使用的系统目录和环境变量
+/// 默认来自 dirs 和进程的环境变量；沙盒（见 Environment::sandbox）整体位于指定目录下，
+/// 用于测试时不接触真实的用户配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct Environment {
+    pub home_dir: Option<PathBuf>,
+    pub config_dir: Option<PathBuf>, // %APPDATA% 或 ~/Library/Application Support 或 ~/.config
+    pub data_local_dir: Option<PathBuf>, // %LOCALAPPDATA% 或 ~/Library/Application Support 或 ~/.local/share
+    vars: Option<BTreeMap<String, OsString>>, // None 表示读取进程的环境变量；沙盒只使用显式设置的变量
+}
+
+thread_local! {
+    /// 当前线程使用的环境（为 None 时使用当前用户的真实目录）
+    static CURRENT: RefCell<Option<Environment>> = const { RefCell::new(None) };
+}
+
+impl Environment {
+    /// 当前用户的真实目录
+    pub fn system() -> Self {
+        Environment {
+            home_dir: dirs::home_dir(),
+            config_dir: dirs::config_dir(),
+            data_local_dir: dirs::data_local_dir(),
+            vars: None,
+        }
+    }
+
+    /// 以 home 为用户目录的沙盒，配置和数据目录按当前系统的布局放在 home 下
+    pub fn sandbox(home: &Path) -> Self {
+        let (config_dir, data_local_dir) = if cfg!(target_os = "windows") {
+            (home.join("AppData/Roaming"), home.join("AppData/Local"))
+        } else if cfg!(target_os = "macos") {
+            let support = home.join("Library/Application Support");
+            (support.clone(), support)
+        } else {
+            (home.join(".config"), home.join(".local/share"))
+        };
+        Environment {
+            home_dir: Some(home.to_path_buf()),
+            config_dir: Some(config_dir),
+            data_local_dir: Some(data_local_dir),
+            vars: Some(BTreeMap::new()),
+        }
+    }
+
+    /// 设置沙盒中的环境变量（如 GIT_CONFIG_GLOBAL），对真实环境无效
+    pub fn with_var(mut self, name: &str, value: impl Into<OsString>) -> Self {
+        if let Some(vars) = &mut self.vars {
+            vars.insert(name.to_string(), value.into());
+        }
+        self
+    }
+
+    /// 读取环境变量（沙盒中只能读取显式设置的变量）
+    pub fn var(&self, name: &str) -> Option<OsString> {
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => env::var_os(name),
+        }
+    }
+
+    /// 本程序的配置目录，优先级：环境变量 > 便携模式（沙盒中不适用） > 系统配置目录
+    /// 位置: %APPDATA%\proxy-manager\ 或 ~/.config/proxy-manager/ 或 ~/Library/Application Support/proxy-manager/
+    pub fn app_config_dir(&self) -> PathBuf {
+        if let Some(dir) = self.var(CONFIG_DIR_ENV).filter(|v| !v.is_empty()) {
+            return PathBuf::from(dir);
+        }
+        if self.vars.is_none() && is_portable_mode() {
+            if let Some(dir) = exe_dir() {
+                return dir;
+            }
+        }
+        self.config_dir
+            .as_ref()
+            .map(|dir| dir.join(APP_DIR_NAME))
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// 本程序的数据目录（备份、已应用状态等），便携模式下位于可执行文件目录的 data 子目录
+    /// 位置: %LOCALAPPDATA%\proxy-manager\
+    pub fn app_data_dir(&self) -> Option<PathBuf> {
+        if self.vars.is_none() && is_portable_mode() {
+            if let Some(dir) = exe_dir() {
+                return Some(dir.join("data"));
+            }
+        }
+        self.data_local_dir
+            .as_ref()
+            .map(|dir| dir.join(APP_DIR_NAME))
+    }
+
+    /// 用户配置文件路径
+    pub fn user_config_path(&self) -> PathBuf {
+        self.app_config_dir().join(USER_CONFIG_FILE)
+    }
+
+    /// 旧版本的配置文件路径: ~/.proxy-manager/user_config.json
+    pub fn legacy_user_config_path(&self) -> Option<PathBuf> {
+        self.home_dir
+            .as_ref()
+            .map(|home| home.join(".proxy-manager").join(USER_CONFIG_FILE))
+    }
+
+    /// 当前线程使用的环境
+    pub fn current() -> Self {
+        CURRENT
+            .with(|current| current.borrow().clone())
+            .unwrap_or_else(Environment::system)
+    }
+
+    /// 在当前线程中以该环境执行 f，结束（包括 panic）后恢复原来的环境
+    /// 需要在其他线程中继续使用时，由启动线程的一方取出 current() 传入
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<Environment>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                CURRENT.with(|current| *current.borrow_mut() = previous);
+            }
+        }
+
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(self.clone()))));
+        f()
+    }
+}
+
+/// 用户目录
+pub fn home_dir() -> Option<PathBuf> {
+    Environment::current().home_dir
+}
+
+/// 可执行文件所在目录
+fn exe_dir() -> Option<PathBuf> {
+    env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+}
+
+/// 是否处于便携模式
+pub fn is_portable_mode() -> bool {
+    exe_dir()
+        .map(|dir| dir.join(PORTABLE_MARKER).exists())
+        .unwrap_or(false)
+}
+
+/// 是否以安全模式启动：启动时不执行任何自动操作，配置文件只读
+/// 用于已保存的配置或代理导致网络异常时恢复
+pub fn is_safe_mode() -> bool {
+    env::args().any(|arg| arg == SAFE_MODE_ARG)
+        || env::var_os(SAFE_MODE_ENV).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// 当前环境的数据目录（见 Environment::app_data_dir）
+pub fn data_dir() -> Option<PathBuf> {
+    Environment::current().app_data_dir()
+}
+
+/// 当前环境的用户配置文件路径
+pub fn user_config_path() -> PathBuf {
+    Environment::current().user_config_path()
+}
+
+/// 当前环境中旧版本的配置文件路径
+pub fn legacy_user_config_path() -> Option<PathBuf> {
+    Environment::current().legacy_user_config_path()
+}
+
+/// 读取配置时实际使用的文件：新位置不存在而旧位置存在时读取旧文件，
+/// 下次保存时写入新位置，从而完成迁移
+pub fn config_read_path(config_path: &Path, legacy_path: Option<&Path>) -> PathBuf {
+    match legacy_path {
+        Some(legacy) if !config_path.exists() && legacy.exists() => legacy.to_path_buf(),
+        _ => config_path.to_path_buf(),
+    }
+}
+
+/// 把旧位置（~/.proxy-manager）的配置文件迁移到当前配置目录：
+/// 新位置没有配置时复制过去，然后把旧文件改名为 user_config.json.migrated，
+/// 并在旧目录留下记录新位置的说明文件。没有旧配置时返回 false
+pub fn migrate_legacy_config() -> Result<bool, Message> {
+    let Some(legacy) = legacy_user_config_path().filter(|p| p.exists()) else {
+        return Ok(false);
+    };
+    let config_path = user_config_path();
+    let legacy_dir = legacy
+        .parent()
+        .ok_or_else(|| tr!("config_path_unavailable"))?;
+
+    {
+        let _guard = file_lock::lock_file(&config_path)?;
+        // 新位置已有配置时以新位置为准（读取时也优先使用新位置）
+        if !config_path.exists() {
+            if let Some(parent) = config_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| tr!("config_dir_create_failed", error = e))?;
+            }
+            fs::copy(&legacy, &config_path).map_err(|e| tr!("config_migrate_failed", error = e))?;
+        }
+    }
+
+    fs::rename(&legacy, legacy_dir.join(MIGRATED_CONFIG_FILE))
+        .map_err(|e| tr!("config_migrate_failed", error = e))?;
+    let _ = fs::write(
+        legacy_dir.join(MOVED_POINTER_FILE),
+        format!("{}\n", config_path.display()),
+    );
+    Ok(true)
+}
+
+/// 获取当前使用的存储位置
+pub fn storage_paths() -> StoragePaths {
+    StoragePaths {
+        config_path: user_config_path().to_string_lossy().into_owned(),
+        data_dir: data_dir()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        portable_mode: is_portable_mode(),
+    }
+}
+
+/// 开启或关闭便携模式（在可执行文件旁创建或删除标记文件）
+/// 开启时把当前配置复制到可执行文件目录，避免切换后丢失配置
+pub fn set_portable_mode(enabled: bool) -> Result<StoragePaths, Message> {
+    let dir = exe_dir().ok_or_else(|| tr!("exe_dir_unavailable"))?;
+    let marker = dir.join(PORTABLE_MARKER);
+
+    if enabled {
+        let current = config_read_path(&user_config_path(), legacy_user_config_path().as_deref());
+        let portable_config = dir.join(USER_CONFIG_FILE);
+        if current.exists() && !portable_config.exists() {
+            fs::copy(&current, &portable_config).map_err(|e| e.to_string())?;
+        }
+        fs::write(&marker, "").map_err(|e| e.to_string())?;
+    } else if marker.exists() {
+        fs::remove_file(&marker).map_err(|e| e.to_string())?;
+    }
+
+    Ok(storage_paths())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile_manager;
+
+    const LEGACY_CONFIG: &str =
+        "{\n  \"profiles\": [],\n  \"mappings\": [],\n  \"locale\": \"en-US\"\n}";
+
+    #[test]
+    fn config_dir_variable_overrides_platform_dir() {
+        let home = tempfile::tempdir().unwrap();
+        let sandbox = Environment::sandbox(home.path());
+        assert_eq!(
+            sandbox.user_config_path(),
+            sandbox
+                .config_dir
+                .clone()
+                .unwrap()
+                .join("proxy-manager/user_config.json")
+        );
+
+        let custom = home.path().join("custom");
+        let overridden = sandbox.clone().with_var(CONFIG_DIR_ENV, custom.as_os_str());
+        assert_eq!(overridden.user_config_path(), custom.join(USER_CONFIG_FILE));
+        // 空值视为未设置
+        let empty = sandbox.clone().with_var(CONFIG_DIR_ENV, "");
+        assert_eq!(empty.user_config_path(), sandbox.user_config_path());
+    }
+
+    #[test]
+    fn legacy_config_is_read_only_while_new_one_is_missing() {
+        let home = tempfile::tempdir().unwrap();
+        let config = home.path().join("new/user_config.json");
+        let legacy = home.path().join(".proxy-manager/user_config.json");
+        assert_eq!(config_read_path(&config, Some(&legacy)), config);
+
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, LEGACY_CONFIG).unwrap();
+        assert_eq!(config_read_path(&config, Some(&legacy)), legacy);
+
+        fs::create_dir_all(config.parent().unwrap()).unwrap();
+        fs::write(&config, "{}").unwrap();
+        assert_eq!(config_read_path(&config, Some(&legacy)), config);
+    }
+
+    #[test]
+    fn first_save_writes_config_to_new_location() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let legacy = env.legacy_user_config_path().unwrap();
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, LEGACY_CONFIG).unwrap();
+
+        env.run(|| {
+            assert_eq!(profile_manager::load_user_config().locale, "en-US");
+            assert!(!env.user_config_path().exists());
+            profile_manager::update_user_config(|_| Ok(())).unwrap();
+        });
+        let saved = fs::read_to_string(env.user_config_path()).unwrap();
+        assert!(saved.contains("\"en-US\""), "{}", saved);
+        // 旧文件保持不变，之后读取新位置
+        assert_eq!(fs::read_to_string(&legacy).unwrap(), LEGACY_CONFIG);
+    }
+
+    #[test]
+    fn migration_keeps_existing_new_config() {
+        let home = tempfile::tempdir().unwrap();
+        let env = Environment::sandbox(home.path());
+        let legacy = env.legacy_user_config_path().unwrap();
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, LEGACY_CONFIG).unwrap();
+        let config_path = env.user_config_path();
+        fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+        fs::write(&config_path, "{\"profiles\": []}").unwrap();
+
+        assert!(env.run(migrate_legacy_config).unwrap());
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            "{\"profiles\": []}"
+        );
+        assert!(!legacy.exists());
+        assert_eq!(
+            fs::read_to_string(legacy.with_file_name(MIGRATED_CONFIG_FILE)).unwrap(),
+            LEGACY_CONFIG
+        );
+    }
+}
@@ -1,6 +1,14 @@
 use serde::{Deserialize, Serialize};
-#[cfg(any(target_os = "windows", target_os = "macos"))]
-use std::process::Command;
+use std::collections::BTreeMap;
+#[cfg(target_os = "windows")]
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::i18n::Message;
+use crate::profile_manager::{self, PortRange};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VpnConfig {
@@ -8,14 +16,74 @@ pub struct VpnConfig {
     pub process_names: Vec<String>,
     pub default_http_port: u16,
     pub default_socks_port: u16,
+    #[serde(default)]
+    pub default_bypass: Vec<String>, // 建议在本地绕过的地址，新建或刷新关联的配置组时作为其绕过列表
+    #[serde(default)]
+    pub case_sensitive: bool, // 进程名匹配是否区分大小写
+}
+
+impl VpnConfig {
+    /// 预设的默认端口（不受检测端口范围限制）
+    fn default_ports(&self) -> [u16; 2] {
+        [self.default_http_port, self.default_socks_port]
+    }
+}
+
+/// 端口监听的地址范围
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "addr", rename_all = "lowercase")]
+pub enum BindScope {
+    #[default]
+    Loopback, // 回环地址，只有本机可以连接
+    Any,              // 通配地址（0.0.0.0、[::]），允许局域网连接
+    Specific(String), // 具体网卡的地址
+}
+
+impl BindScope {
+    /// 局域网中的其他设备是否可以连接
+    pub fn is_exposed(&self) -> bool {
+        *self != BindScope::Loopback
+    }
+
+    /// 是否可以从本机回环地址连接（回环地址或通配地址）
+    fn is_local(&self) -> bool {
+        !matches!(self, BindScope::Specific(_))
+    }
+}
+
+/// 按监听地址筛选检测到的端口
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BindFilter {
+    #[default]
+    All,
+    Loopback, // 只保留仅本机可以连接的端口
+    Exposed,  // 只保留局域网可以连接的端口
+}
+
+impl BindFilter {
+    fn keeps(self, port: &DetectedPort) -> bool {
+        match self {
+            BindFilter::All => true,
+            BindFilter::Loopback => !port.bind_scope.is_exposed(),
+            BindFilter::Exposed => port.bind_scope.is_exposed(),
+        }
+    }
+
+    /// 筛选端口列表
+    pub fn apply(self, ports: &mut Vec<DetectedPort>) {
+        ports.retain(|p| self.keeps(p));
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DetectedPort {
     pub port: u16,
-    pub port_type: String, // "http" or "socks"
+    pub port_type: String, // "http"、"socks"、"mixed"（同一端口同时支持 HTTP 和 SOCKS）或 "unknown"
     pub process_name: String,
     pub pid: u32,
+    #[serde(default)]
+    pub bind_scope: BindScope, // 未运行时返回的默认端口为 Loopback
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,6 +91,8 @@ pub struct DetectionResult {
     pub success: bool,
     pub message: String,
     pub ports: Vec<DetectedPort>,
+    #[serde(default)]
+    pub config_file: Option<String>, // 从进程命令行中解析出的配置文件路径
 }
 
 // 预设的 VPN 配置
@@ -36,10 +106,17 @@ pub fn get_vpn_configs() -> Vec<VpnConfig> {
                 "Clash for Windows".to_string(),
                 "cfw".to_string(),
                 "clash-verge".to_string(),
+                "clash-meta".to_string(),
+                "mihomo".to_string(),
+                "verge-mihomo".to_string(),
                 "ClashX".to_string(),
+                "ClashX Pro".to_string(),
+                "ClashX Meta".to_string(),
             ],
             default_http_port: 7890,
             default_socks_port: 7891,
+            default_bypass: vec![],
+            case_sensitive: false,
         },
         VpnConfig {
             name: "V2Ray".to_string(),
@@ -47,38 +124,284 @@ pub fn get_vpn_configs() -> Vec<VpnConfig> {
                 "v2ray".to_string(),
                 "v2rayN".to_string(),
                 "v2ray-core".to_string(),
+                "xray".to_string(),
             ],
             default_http_port: 10808,
             default_socks_port: 10809,
+            default_bypass: vec![],
+            case_sensitive: false,
         },
         VpnConfig {
             name: "Veee".to_string(),
-            process_names: vec!["veee".to_string(), "Veee".to_string()],
+            process_names: vec!["Veee".to_string()],
             default_http_port: 15236,
             default_socks_port: 15235,
+            default_bypass: vec![
+                "*.local".to_string(),
+                "*.lan".to_string(),
+                "*.internal".to_string(),
+                "*.corp".to_string(),
+            ],
+            case_sensitive: false,
         },
         VpnConfig {
             name: "Shadowsocks".to_string(),
             process_names: vec![
                 "ss-local".to_string(),
-                "shadowsocks".to_string(),
                 "Shadowsocks".to_string(),
                 "sslocal".to_string(),
             ],
             default_http_port: 1080,
             default_socks_port: 1080,
+            default_bypass: vec![],
+            case_sensitive: false,
         },
         VpnConfig {
             name: "Surge".to_string(),
             process_names: vec!["Surge".to_string(), "surge-cli".to_string()],
             default_http_port: 6152,
             default_socks_port: 6153,
+            default_bypass: vec![],
+            case_sensitive: false,
         },
     ]
 }
 
+/// VPN 及其当前的运行状态
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VpnStatus {
+    pub config: VpnConfig,
+    pub running: bool,
+    pub pids: Vec<u32>,
+    pub detected_ports: Vec<u16>, // 这些进程监听的端口（未运行时为空）
+}
+
+/// 预设的 VPN 及其运行状态，运行中的排在前面
+/// 先只读取进程列表，有 VPN 在运行时才采集一次监听端口
+pub fn get_vpn_list_with_status() -> Vec<VpnStatus> {
+    vpn_statuses(get_vpn_configs(), &list_processes(), capture_listeners)
+}
+
+/// 按给定的进程列表（PID, 进程名）计算 VPN 的运行状态，
+/// 有 VPN 在运行时才调用 capture_listeners 采集监听端口
+fn vpn_statuses(
+    configs: Vec<VpnConfig>,
+    processes: &[(u32, String)],
+    capture_listeners: impl FnOnce() -> Vec<Listener>,
+) -> Vec<VpnStatus> {
+    let mut statuses: Vec<VpnStatus> = configs
+        .into_iter()
+        .map(|config| {
+            let (matched, _) = match_processes(
+                processes,
+                |(_, name)| name,
+                &config.process_names,
+                config.case_sensitive,
+            );
+            let mut pids: Vec<u32> = matched.into_iter().map(|(pid, _)| *pid).collect();
+            pids.sort_unstable();
+            pids.dedup();
+            VpnStatus {
+                config,
+                running: !pids.is_empty(),
+                pids,
+                detected_ports: vec![],
+            }
+        })
+        .collect();
+
+    if statuses.iter().any(|status| status.running) {
+        let listeners = capture_listeners();
+        for status in statuses.iter_mut().filter(|status| status.running) {
+            let mut ports: Vec<u16> = listeners
+                .iter()
+                .filter(|l| status.pids.contains(&l.pid))
+                .map(|l| l.port)
+                .collect();
+            ports.sort_unstable();
+            ports.dedup();
+            status.detected_ports = ports;
+        }
+    }
+
+    statuses.sort_by_key(|status| !status.running);
+    statuses
+}
+
+/// 子串匹配兜底所需的最短名称，更短的名称（如 "go"）会匹配到大量无关进程
+const MIN_SUBSTRING_MATCH_LEN: usize = 4;
+
+/// 通配符匹配：* 匹配任意个字符，? 匹配单个字符
+pub fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            glob_match(&pattern[1..], text) || (!text.is_empty() && glob_match(pattern, &text[1..]))
+        }
+        (Some('?'), Some(_)) => glob_match(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p == t => glob_match(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+/// 按大小写设置规范化名称，并去掉 Windows 可执行文件的 .exe 后缀
+fn normalize_process_name(name: &str, case_sensitive: bool) -> String {
+    let name = match name.len().checked_sub(4) {
+        Some(stem) if name.is_char_boundary(stem) && name[stem..].eq_ignore_ascii_case(".exe") => {
+            &name[..stem]
+        }
+        _ => name,
+    };
+    match case_sensitive {
+        true => name.to_string(),
+        false => name.to_lowercase(),
+    }
+}
+
+/// 进程名是否与模式匹配：默认精确比较可执行文件名（不含 .exe），模式含 * 或 ? 时按通配符匹配
+fn process_matches(process_name: &str, pattern: &str, case_sensitive: bool) -> bool {
+    let name = normalize_process_name(process_name, case_sensitive);
+    let pattern = normalize_process_name(pattern, case_sensitive);
+    if pattern.contains(['*', '?']) {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        glob_match(&pattern, &name)
+    } else {
+        name == pattern
+    }
+}
+
+/// 按进程名匹配：先精确（或通配符）匹配，一个都找不到时对不短于 4 个字符的名称退回旧版本的子串匹配
+/// 返回匹配的项，以及是否使用了子串匹配
+fn match_processes<'a, T>(
+    items: &'a [T],
+    name_of: impl Fn(&T) -> &str,
+    patterns: &[String],
+    case_sensitive: bool,
+) -> (Vec<&'a T>, bool) {
+    let exact: Vec<&T> = items
+        .iter()
+        .filter(|item| {
+            patterns
+                .iter()
+                .any(|pattern| process_matches(name_of(item), pattern, case_sensitive))
+        })
+        .collect();
+    if !exact.is_empty() {
+        return (exact, false);
+    }
+
+    let needles: Vec<String> = patterns
+        .iter()
+        .filter(|p| p.chars().count() >= MIN_SUBSTRING_MATCH_LEN && !p.contains(['*', '?']))
+        .map(|p| normalize_process_name(p, case_sensitive))
+        .collect();
+    let fallback: Vec<&T> = items
+        .iter()
+        .filter(|item| {
+            let name = normalize_process_name(name_of(item), case_sensitive);
+            needles.iter().any(|needle| name.contains(needle.as_str()))
+        })
+        .collect();
+    let used = !fallback.is_empty();
+    (fallback, used)
+}
+
 /// 根据 VPN 名称检测端口
 pub fn detect_port_by_vpn_name(vpn_name: &str) -> DetectionResult {
+    // 空名称会被当作自定义进程名，匹配结果不可预期
+    if vpn_name.trim().is_empty() {
+        return DetectionResult {
+            success: false,
+            message: tr!("vpn_name_required").into(),
+            ports: vec![],
+            config_file: None,
+        };
+    }
+    detect_in_snapshot(&ListenerSnapshot::capture(), vpn_name)
+}
+
+/// 诊断信息中的一条系统命令及其原始输出
+#[derive(Debug, Serialize)]
+pub struct CommandOutput {
+    pub command: String,
+    pub output: String,
+}
+
+/// 附带原始数据的检测结果，供用户附在问题报告中
+#[derive(Debug, Serialize)]
+pub struct DetectionDiagnostics {
+    pub result: DetectionResult,
+    pub matched_pids: Vec<u32>, // 检测结果中实际监听端口的进程
+    pub commands: Vec<CommandOutput>,
+}
+
+/// 采集监听端口和进程时运行的系统命令（与 capture_listeners、list_processes 一致）
+#[cfg(target_os = "windows")]
+const LISTENER_COMMANDS: &[(&str, &[&str])] = &[
+    ("tasklist", &["/FO", "CSV", "/NH"]),
+    ("netstat", &["-ano", "-p", "TCP"]),
+    ("netstat", &["-ano", "-p", "TCPv6"]),
+];
+#[cfg(target_os = "macos")]
+const LISTENER_COMMANDS: &[(&str, &[&str])] = &[
+    ("lsof", &["-nP", "-iTCP", "-sTCP:LISTEN"]),
+    ("ps", &["-axo", "pid=,comm="]),
+];
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const LISTENER_COMMANDS: &[(&str, &[&str])] = &[("ss", &["-ltnpH"])];
+
+/// 运行命令并保留完整输出
+fn command_output(program: &str, args: &[&str]) -> CommandOutput {
+    let mut output = String::new();
+    for_each_output_line(program, args, |line| {
+        output.push_str(line.trim_end_matches('\r'));
+        output.push('\n');
+    });
+    CommandOutput {
+        command: format!("{} {}", program, args.join(" ")),
+        output,
+    }
+}
+
+/// 检测端口并附上检测所用系统命令的原始输出。命令在检测完成后重新运行，
+/// 输出与检测时可能略有差异
+pub fn detect_port_verbose(vpn_name: &str) -> DetectionDiagnostics {
+    let result = detect_port_by_vpn_name(vpn_name);
+    let mut matched_pids: Vec<u32> = result
+        .ports
+        .iter()
+        .map(|p| p.pid)
+        .filter(|pid| *pid != 0)
+        .collect();
+    matched_pids.sort_unstable();
+    matched_pids.dedup();
+
+    let commands = LISTENER_COMMANDS
+        .iter()
+        .map(|(program, args)| command_output(program, args))
+        .collect();
+    DetectionDiagnostics {
+        result,
+        matched_pids,
+        commands,
+    }
+}
+
+/// 检测所有预设的 VPN（只采集一次监听端口），返回 VPN 名称 -> 检测结果
+pub fn detect_all_vpns() -> BTreeMap<String, DetectionResult> {
+    let snapshot = ListenerSnapshot::capture();
+    get_vpn_configs()
+        .into_iter()
+        .map(|vpn| {
+            let detection = detect_in_snapshot(&snapshot, &vpn.name);
+            (vpn.name, detection)
+        })
+        .collect()
+}
+
+/// 在已采集的监听端口中检测指定 VPN
+fn detect_in_snapshot(snapshot: &ListenerSnapshot, vpn_name: &str) -> DetectionResult {
     let configs = get_vpn_configs();
 
     // 查找匹配的 VPN 配置
@@ -87,222 +410,1328 @@ pub fn detect_port_by_vpn_name(vpn_name: &str) -> DetectionResult {
         .find(|c| c.name.to_lowercase() == vpn_name.to_lowercase());
 
     match config {
-        Some(cfg) => detect_port_by_process_names(&cfg.process_names, cfg),
+        Some(cfg) => detect_port_by_process_names(snapshot, cfg),
         None => {
             // 如果不在预设列表中，尝试直接用名字作为进程名搜索
-            detect_port_by_custom_name(vpn_name)
+            detect_port_by_custom_name(snapshot, vpn_name)
         }
     }
 }
 
-/// 根据进程名列表检测端口
-fn detect_port_by_process_names(process_names: &[String], config: &VpnConfig) -> DetectionResult {
-    let mut all_ports = Vec::new();
+/// 从检测结果中选择指定类型（"http" / "socks"）的端口，"mixed" 端口同时满足两种类型
+/// 同类型有多个时，优先选择真实监听的端口（pid 非 0），而不是默认端口猜测
+pub fn select_detected_port<'a>(
+    ports: &'a [DetectedPort],
+    port_type: &str,
+) -> Result<&'a DetectedPort, Message> {
+    let mut candidates = ports
+        .iter()
+        .filter(|p| p.port_type == port_type || p.port_type == "mixed");
+    let first = candidates
+        .next()
+        .ok_or_else(|| tr!("port_type_not_detected", port_type = port_type))?;
+
+    Ok(std::iter::once(first)
+        .chain(candidates)
+        .find(|p| p.pid != 0)
+        .unwrap_or(first))
+}
+
+/// Clash 外部控制器的请求超时
+const CLASH_API_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Clash /configs 接口返回的端口（为 0 表示未开启）
+#[derive(Debug, Default, Deserialize)]
+struct ClashConfigs {
+    #[serde(default)]
+    port: u16,
+    #[serde(default, rename = "socks-port")]
+    socks_port: u16,
+    #[serde(default, rename = "mixed-port")]
+    mixed_port: u16,
+    #[serde(default, rename = "external-controller")]
+    external_controller: String, // 只在配置文件中出现，/configs 接口不返回
+}
 
-    for process_name in process_names {
-        if let Some(ports) = find_ports_by_process_name(process_name) {
-            all_ports.extend(ports);
+impl ClashConfigs {
+    /// 开启的端口及其类型
+    fn typed_ports(&self) -> Vec<(u16, &'static str)> {
+        [
+            (self.mixed_port, "mixed"),
+            (self.port, "http"),
+            (self.socks_port, "socks"),
+        ]
+        .into_iter()
+        .filter(|(port, _)| *port != 0)
+        .collect()
+    }
+}
+
+/// 通过 Clash 的外部控制器（RESTful API，默认 127.0.0.1:9090）读取实际使用的端口，
+/// 比按进程名扫描监听端口更可靠。mixed-port 同时支持 HTTP 和 SOCKS，返回类型为 "mixed"
+pub fn detect_via_clash_api(controller: &str, secret: Option<&str>) -> DetectionResult {
+    match fetch_clash_configs(controller, secret) {
+        Ok(configs) => {
+            // API 只返回端口，进程信息从本机监听列表中补充（找不到时 pid 为 0）
+            let listeners = list_local_listeners();
+            let ports: Vec<DetectedPort> = configs
+                .typed_ports()
+                .into_iter()
+                .map(|(port, port_type)| {
+                    let listener = listeners.iter().find(|l| l.port == port);
+                    DetectedPort {
+                        port,
+                        port_type: port_type.to_string(),
+                        process_name: listener
+                            .map(|l| l.process_name.clone())
+                            .unwrap_or_else(|| "Clash".to_string()),
+                        pid: listener.map(|l| l.pid).unwrap_or(0),
+                        bind_scope: listener.map(|l| l.bind_scope.clone()).unwrap_or_default(),
+                    }
+                })
+                .collect();
+
+            DetectionResult {
+                success: !ports.is_empty(),
+                message: with_lan_note(tr!("vpn_running", name = "Clash").into(), &ports),
+                ports,
+                config_file: None,
+            }
+        }
+        Err(e) => DetectionResult {
+            success: false,
+            message: e.into(),
+            ports: vec![],
+            config_file: None,
+        },
+    }
+}
+
+/// 请求 Clash 的 /configs 接口（HTTP/1.0，避免处理分块传输）
+fn fetch_clash_configs(controller: &str, secret: Option<&str>) -> Result<ClashConfigs, Message> {
+    let authority = controller
+        .trim()
+        .trim_start_matches("http://")
+        .trim_end_matches('/');
+    let addr = authority
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| tr!("clash_api_invalid_controller", controller = controller))?;
+
+    let request_failed = |e: std::io::Error| tr!("clash_api_request_failed", error = e);
+    let mut stream =
+        TcpStream::connect_timeout(&addr, CLASH_API_TIMEOUT).map_err(request_failed)?;
+    stream
+        .set_read_timeout(Some(CLASH_API_TIMEOUT))
+        .map_err(request_failed)?;
+    stream
+        .set_write_timeout(Some(CLASH_API_TIMEOUT))
+        .map_err(request_failed)?;
+
+    let mut request = format!("GET /configs HTTP/1.0\r\nHost: {}\r\n", authority);
+    if let Some(secret) = secret.filter(|s| !s.is_empty()) {
+        request.push_str(&format!("Authorization: Bearer {}\r\n", secret));
+    }
+    request.push_str("\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .map_err(request_failed)?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(request_failed)?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| tr!("clash_api_bad_response"))?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| tr!("clash_api_bad_response"))?;
+    match status {
+        200 => serde_json::from_str(body).map_err(|_| tr!("clash_api_bad_response")),
+        401 | 403 => Err(tr!("clash_api_unauthorized")),
+        _ => Err(tr!("clash_api_status", status = status)),
+    }
+}
+
+/// 端口冲突：同一端口被多个进程监听，或配置组使用的端口没有任何进程监听（owners 为空）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PortConflict {
+    pub port: u16,
+    pub owners: Vec<String>,   // 监听该端口的进程，如 "Clash (PID 1234)"
+    pub profiles: Vec<String>, // 使用该端口的配置组
+}
+
+/// 检查端口冲突：遍历所有预设的 VPN 进程，找出被多个进程同时监听的端口，
+/// 以及配置组中没有任何进程监听的端口
+pub fn check_port_conflicts(profile_ports: &[(String, u16)]) -> Vec<PortConflict> {
+    // 端口 -> 监听该端口的 (预设名称, PID)，同一进程可能被多个进程名匹配到，按 PID 去重
+    let mut listeners: BTreeMap<u16, Vec<(String, u32)>> = BTreeMap::new();
+    let snapshot = ListenerSnapshot::capture();
+    for config in get_vpn_configs() {
+        let (ports, _) = snapshot.ports_of(
+            &config.process_names,
+            config.case_sensitive,
+            &config.default_ports(),
+        );
+        for detected in ports {
+            let owners = listeners.entry(detected.port).or_default();
+            if !owners.iter().any(|(_, pid)| *pid == detected.pid) {
+                owners.push((config.name.clone(), detected.pid));
+            }
         }
     }
 
+    let profiles_on = |port: u16| -> Vec<String> {
+        let mut names: Vec<String> = profile_ports
+            .iter()
+            .filter(|(_, p)| *p == port)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.dedup();
+        names
+    };
+
+    let mut conflicts: Vec<PortConflict> = listeners
+        .iter()
+        .filter(|(_, owners)| owners.len() > 1)
+        .map(|(port, owners)| PortConflict {
+            port: *port,
+            owners: owners
+                .iter()
+                .map(|(name, pid)| format!("{} (PID {})", name, pid))
+                .collect(),
+            profiles: profiles_on(*port),
+        })
+        .collect();
+
+    let mut unmatched: Vec<u16> = profile_ports
+        .iter()
+        .map(|(_, port)| *port)
+        .filter(|port| !listeners.contains_key(port))
+        .collect();
+    unmatched.sort_unstable();
+    unmatched.dedup();
+    conflicts.extend(unmatched.into_iter().map(|port| PortConflict {
+        port,
+        owners: vec![],
+        profiles: profiles_on(port),
+    }));
+
+    conflicts
+}
+
+/// 根据进程名列表检测端口
+fn detect_port_by_process_names(
+    snapshot: &ListenerSnapshot,
+    config: &VpnConfig,
+) -> DetectionResult {
+    let (all_ports, substring) = snapshot.ports_of(
+        &config.process_names,
+        config.case_sensitive,
+        &config.default_ports(),
+    );
+
     if all_ports.is_empty() {
         // 进程未运行，返回默认端口
         DetectionResult {
             success: true,
-            message: format!("未检测到 {} 运行，使用默认端口", config.name),
+            message: tr!("vpn_not_running_default_ports", name = config.name).into(),
             ports: vec![
                 DetectedPort {
                     port: config.default_http_port,
                     port_type: "http".to_string(),
                     process_name: config.name.clone(),
                     pid: 0,
+                    bind_scope: BindScope::Loopback,
                 },
                 DetectedPort {
                     port: config.default_socks_port,
                     port_type: "socks".to_string(),
                     process_name: config.name.clone(),
                     pid: 0,
+                    bind_scope: BindScope::Loopback,
                 },
             ],
+            config_file: None,
         }
     } else {
         // 对端口进行分类
-        let classified_ports = classify_ports(all_ports, config);
+        let flags = LaunchFlags::of_ports(&all_ports);
+        let classified_ports = classify_ports(all_ports, config, &flags);
+        let message = with_substring_note(tr!("vpn_running", name = config.name).into(), substring);
         DetectionResult {
             success: true,
-            message: format!("检测到 {} 正在运行", config.name),
+            message: with_lan_note(message, &classified_ports),
             ports: classified_ports,
+            config_file: flags.config_file,
         }
     }
 }
 
 /// 根据自定义名称检测端口
-fn detect_port_by_custom_name(name: &str) -> DetectionResult {
-    if let Some(ports) = find_ports_by_process_name(name) {
-        if !ports.is_empty() {
-            return DetectionResult {
-                success: true,
-                message: format!("检测到 {} 正在运行", name),
-                ports,
-            };
-        }
+fn detect_port_by_custom_name(snapshot: &ListenerSnapshot, name: &str) -> DetectionResult {
+    let (mut ports, substring) = snapshot.ports_of(&[name.to_string()], false, &[]);
+    if !ports.is_empty() {
+        let flags = LaunchFlags::of_ports(&ports);
+        flags.apply(&mut ports);
+        let message = with_substring_note(tr!("vpn_running", name = name).into(), substring);
+        return DetectionResult {
+            success: true,
+            message: with_lan_note(message, &ports),
+            ports,
+            config_file: flags.config_file,
+        };
     }
 
     DetectionResult {
         success: false,
-        message: format!("未找到名为 {} 的进程", name),
+        message: tr!("process_not_found", name = name).into(),
         ports: vec![],
+        config_file: None,
     }
 }
 
-/// 根据进程名查找监听的端口
-#[cfg(target_os = "windows")]
-fn find_ports_by_process_name(process_name: &str) -> Option<Vec<DetectedPort>> {
-    // Windows: 使用 tasklist 和 netstat
-    let tasklist_output = Command::new("tasklist")
-        .args(["/FO", "CSV", "/NH"])
-        .output()
-        .ok()?;
-
-    let tasklist_str = String::from_utf8_lossy(&tasklist_output.stdout);
-    let mut pids: Vec<u32> = Vec::new();
-
-    // 解析 tasklist 输出，查找匹配的进程
-    for line in tasklist_str.lines() {
-        let lower_line = line.to_lowercase();
-        if lower_line.contains(&process_name.to_lowercase()) {
-            // CSV 格式: "进程名","PID","会话名","会话#","内存使用"
-            let parts: Vec<&str> = line.split(',').collect();
-            if parts.len() >= 2 {
-                if let Ok(pid) = parts[1].trim_matches('"').parse::<u32>() {
-                    pids.push(pid);
-                }
-            }
+/// 使用了子串匹配时在消息后附上提示（匹配到的进程可能并不是目标 VPN）
+fn with_substring_note(message: String, substring: bool) -> String {
+    match substring {
+        true => format!("{} {}", message, tr!("process_substring_match")),
+        false => message,
+    }
+}
+
+/// 有端口允许局域网连接时在消息后附上提示
+fn with_lan_note(message: String, ports: &[DetectedPort]) -> String {
+    let mut exposed: Vec<String> = ports
+        .iter()
+        .filter(|p| p.bind_scope.is_exposed())
+        .map(|p| p.port.to_string())
+        .collect();
+    exposed.dedup();
+    match exposed.is_empty() {
+        true => message,
+        false => format!(
+            "{} {}",
+            message,
+            tr!("port_lan_exposed", ports = exposed.join(", "))
+        ),
+    }
+}
+
+/// 某一时刻本机所有的 TCP 监听端口及其进程
+/// 同一次检测中按多个进程名匹配时复用，避免反复运行系统命令
+struct ListenerSnapshot {
+    listeners: Vec<Listener>,
+    port_range: PortRange, // 用户设置的检测端口范围
+}
+
+struct Listener {
+    port: u16,
+    pid: u32,
+    process_name: String,
+    bind_scope: BindScope,
+}
+
+impl ListenerSnapshot {
+    fn capture() -> Self {
+        ListenerSnapshot {
+            listeners: capture_listeners(),
+            port_range: profile_manager::load_user_config().detection_port_range,
         }
     }
 
-    if pids.is_empty() {
+    /// 进程名与任一模式匹配的进程监听的端口，以及是否退回了子串匹配（见 match_processes）
+    /// 只保留检测范围内的端口和 known_ports 中的端口（如 VPN 预设的默认端口）
+    fn ports_of(
+        &self,
+        patterns: &[String],
+        case_sensitive: bool,
+        known_ports: &[u16],
+    ) -> (Vec<DetectedPort>, bool) {
+        let (matched, substring) = match_processes(
+            &self.listeners,
+            |l| &l.process_name,
+            patterns,
+            case_sensitive,
+        );
+        let ports = matched
+            .into_iter()
+            .filter(|l| self.port_range.contains(l.port) || known_ports.contains(&l.port))
+            .map(|l| DetectedPort {
+                port: l.port,
+                port_type: "unknown".to_string(),
+                process_name: l.process_name.clone(),
+                pid: l.pid,
+                bind_scope: l.bind_scope.clone(),
+            })
+            .collect();
+        (ports, substring)
+    }
+}
+
+/// 运行命令并逐行处理输出，不把整个输出读入内存（非 UTF-8 的输出按有损方式转换）
+fn for_each_output_line(program: &str, args: &[&str], mut handle: impl FnMut(&str)) {
+    let Ok(mut child) = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+    else {
+        return;
+    };
+
+    if let Some(stdout) = child.stdout.take() {
+        for_each_line(stdout, &mut handle);
+    }
+    let _ = child.wait();
+}
+
+/// 逐行读取输出，读取出错时停止
+fn for_each_line(reader: impl Read, mut handle: impl FnMut(&str)) {
+    for line in BufReader::new(reader).split(b'\n') {
+        let Ok(line) = line else {
+            break;
+        };
+        handle(&String::from_utf8_lossy(&line));
+    }
+}
+
+/// 解析 netstat -ano 的一行，返回 (本地地址, PID)，只处理 LISTENING 状态
+/// 格式: TCP 127.0.0.1:7890 0.0.0.0:0 LISTENING 1234
+#[cfg(any(target_os = "windows", test))]
+fn parse_netstat_line(line: &str) -> Option<(&str, u32)> {
+    if !line.contains("LISTENING") {
         return None;
     }
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return None;
+    }
+    let pid = parts[parts.len() - 1].parse::<u32>().ok()?;
+    Some((parts[1], pid))
+}
+
+/// Windows: tasklist 输出 CSV 格式: "进程名","PID","会话名","会话#","内存使用"
+#[cfg(target_os = "windows")]
+fn list_processes() -> Vec<(u32, String)> {
+    let mut processes = Vec::new();
+    for_each_output_line("tasklist", &["/FO", "CSV", "/NH"], |line| {
+        let mut parts = line.split(',');
+        let name = parts.next().map(|name| name.trim_matches('"').to_string());
+        let pid = parts
+            .next()
+            .and_then(|pid| pid.trim_matches('"').parse::<u32>().ok());
+        if let (Some(name), Some(pid)) = (name, pid) {
+            processes.push((pid, name));
+        }
+    });
+    processes
+}
 
-    // 使用 netstat 查找这些 PID 监听的端口
-    let netstat_output = Command::new("netstat").args(["-ano"]).output().ok()?;
+/// macOS: ps 输出 PID 和可执行文件路径，只保留文件名
+#[cfg(target_os = "macos")]
+fn list_processes() -> Vec<(u32, String)> {
+    let mut processes = Vec::new();
+    for_each_output_line("ps", &["-axo", "pid=,comm="], |line| {
+        let Some((pid, command)) = line.trim().split_once(char::is_whitespace) else {
+            return;
+        };
+        let Ok(pid) = pid.parse::<u32>() else {
+            return;
+        };
+        let name = command.trim().rsplit('/').next().unwrap_or_default();
+        processes.push((pid, name.to_string()));
+    });
+    processes
+}
 
-    let netstat_str = String::from_utf8_lossy(&netstat_output.stdout);
-    let mut ports = Vec::new();
+/// Linux: /proc/<pid>/comm 中的进程名
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn list_processes() -> Vec<(u32, String)> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![];
+    };
+    entries
+        .filter_map(|entry| {
+            let pid = entry.ok()?.file_name().to_str()?.parse::<u32>().ok()?;
+            let name = std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok()?;
+            Some((pid, name.trim_end().to_string()))
+        })
+        .collect()
+}
 
-    for line in netstat_str.lines() {
-        if !line.contains("LISTENING") {
-            continue;
+/// Windows: tasklist（PID -> 进程名）和 netstat 同时运行，各自逐行解析
+#[cfg(target_os = "windows")]
+fn capture_listeners() -> Vec<Listener> {
+    let (process_names, sockets) = std::thread::scope(|scope| {
+        let tasklist = scope.spawn(|| {
+            list_processes()
+                .into_iter()
+                .collect::<HashMap<u32, String>>()
+        });
+
+        let mut sockets: Vec<(String, u32)> = Vec::new();
+        for protocol in ["TCP", "TCPv6"] {
+            for_each_output_line("netstat", &["-ano", "-p", protocol], |line| {
+                if let Some((addr, pid)) = parse_netstat_line(line) {
+                    sockets.push((addr.to_string(), pid));
+                }
+            });
         }
+        (tasklist.join().unwrap_or_default(), sockets)
+    });
 
+    sockets
+        .into_iter()
+        .filter_map(|(addr, pid)| {
+            Some(Listener {
+                port: parse_listen_port(&addr)?,
+                pid,
+                process_name: process_names.get(&pid).cloned().unwrap_or_default(),
+                bind_scope: parse_bind_scope(&addr),
+            })
+        })
+        .collect()
+}
+
+/// macOS: lsof 输出格式: COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
+#[cfg(target_os = "macos")]
+fn capture_listeners() -> Vec<Listener> {
+    let mut listeners = Vec::new();
+    for_each_output_line("lsof", &["-nP", "-iTCP", "-sTCP:LISTEN"], |line| {
         let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 5 {
-            continue;
+        if parts.len() < 9 {
+            return;
         }
-
-        // 检查 PID 是否匹配
-        if let Ok(pid) = parts[parts.len() - 1].parse::<u32>() {
-            if pids.contains(&pid) {
-                // 解析本地地址和端口
-                let local_addr = parts[1];
-                if let Some(port_str) = local_addr.rsplit(':').next() {
-                    if let Ok(port) = port_str.parse::<u16>() {
-                        // 只关注常见的代理端口范围
-                        if port > 1000 && port < 65535 {
-                            ports.push(DetectedPort {
-                                port,
-                                port_type: "unknown".to_string(),
-                                process_name: process_name.to_string(),
-                                pid,
-                            });
-                        }
-                    }
-                }
-            }
+        // NAME 类似 *:7890、127.0.0.1:7890 或 [::1]:7890（表头不是地址，会被跳过）
+        if let Some(port) = parse_listen_port(parts[8]) {
+            listeners.push(Listener {
+                port,
+                pid: parts[1].parse::<u32>().unwrap_or(0),
+                process_name: parts[0].to_string(),
+                bind_scope: parse_bind_scope(parts[8]),
+            });
         }
+    });
+    listeners
+}
+
+/// Linux: 逐行解析 ss 的输出
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn capture_listeners() -> Vec<Listener> {
+    let mut listeners = Vec::new();
+    for_each_output_line("ss", &["-ltnpH"], |line| {
+        listeners.extend(parse_ss_line(line));
+    });
+    listeners
+}
+
+/// ss 输出格式: State Recv-Q Send-Q Local Peer Process，例如
+/// LISTEN 0 4096 127.0.0.1:7890 0.0.0.0:* users:(("clash",pid=1234,fd=7))
+#[cfg(any(not(any(target_os = "windows", target_os = "macos")), test))]
+fn parse_ss_line(line: &str) -> Option<Listener> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 5 {
+        return None;
     }
+    let port = parse_listen_port(parts[3])?;
+    // 没有权限查看其他用户的进程时，Process 列为空
+    let process = parts.get(5).copied().unwrap_or_default();
+    let process_name = process
+        .split_once("((\"")
+        .and_then(|(_, rest)| rest.split_once('"'))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_default();
+    let pid = process
+        .split_once("pid=")
+        .and_then(|(_, rest)| rest.split(|c: char| !c.is_ascii_digit()).next())
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .unwrap_or(0);
 
-    Some(ports)
+    Some(Listener {
+        port,
+        pid,
+        process_name,
+        bind_scope: parse_bind_scope(parts[3]),
+    })
 }
 
-#[cfg(target_os = "macos")]
-fn find_ports_by_process_name(process_name: &str) -> Option<Vec<DetectedPort>> {
-    // macOS: 使用 lsof
-    let output = Command::new("lsof")
-        .args(["-i", "-P", "-n"])
-        .output()
-        .ok()?;
-
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let mut ports = Vec::new();
-
-    for line in output_str.lines() {
-        let lower_line = line.to_lowercase();
-        if !lower_line.contains(&process_name.to_lowercase()) {
+/// 从监听地址中提取端口，支持以下形式：
+/// `127.0.0.1:7890`、`0.0.0.0:7890`、`*:7890`、`[::1]:7890`、`[::]:7890`、`localhost:7890`
+fn parse_listen_port(addr: &str) -> Option<u16> {
+    let port_str = if let Some(inner) = addr.strip_prefix('[') {
+        // [IPv6]:port
+        inner.split_once(']')?.1.strip_prefix(':')?
+    } else {
+        addr.rsplit_once(':')?.1
+    };
+
+    port_str.parse::<u16>().ok()
+}
+
+/// 常见代理软件使用的端口范围（闭区间）
+const PROXY_PORT_RANGES: [(u16, u16); 9] = [
+    (1080, 1080),
+    (1086, 1089),
+    (3128, 3128),
+    (6152, 6153),
+    (7890, 7900),
+    (8080, 8080),
+    (8118, 8118),
+    (10808, 10810),
+    (15235, 15236),
+];
+
+/// 常见的 HTTP 代理端口
+const COMMON_HTTP_PORTS: [u16; 7] = [7890, 8080, 8118, 3128, 10808, 15236, 6152];
+
+/// 常见的 SOCKS 代理端口
+const COMMON_SOCKS_PORTS: [u16; 5] = [7891, 1080, 10809, 15235, 6153];
+
+/// 根据常见端口猜测类型（"http" / "socks" / "unknown"）
+fn guess_port_type(port: u16) -> &'static str {
+    if COMMON_HTTP_PORTS.contains(&port) {
+        "http"
+    } else if COMMON_SOCKS_PORTS.contains(&port) {
+        "socks"
+    } else {
+        "unknown"
+    }
+}
+
+/// 监听地址的范围，地址形式同 parse_listen_port
+fn parse_bind_scope(addr: &str) -> BindScope {
+    let host = match addr.strip_prefix('[') {
+        Some(inner) => inner.split_once(']').map(|(host, _)| host).unwrap_or(inner),
+        None => addr.rsplit_once(':').map(|(host, _)| host).unwrap_or(addr),
+    };
+    let host = host.split('%').next().unwrap_or(host); // 去掉 IPv6 的网卡后缀
+
+    match host {
+        "*" | "0.0.0.0" | "::" => BindScope::Any,
+        "::1" | "localhost" => BindScope::Loopback,
+        _ if host.starts_with("127.") || host.starts_with("::ffff:127.") => BindScope::Loopback,
+        _ => BindScope::Specific(host.to_string()),
+    }
+}
+
+/// 扫描本机所有像代理的监听端口（不依赖预设的进程名），用于发现未知的代理核心
+pub fn scan_proxy_ports() -> Vec<DetectedPort> {
+    let mut ports: Vec<DetectedPort> = list_local_listeners()
+        .into_iter()
+        .filter(|p| {
+            PROXY_PORT_RANGES
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&p.port))
+        })
+        .map(|mut p| {
+            p.port_type = guess_port_type(p.port).to_string();
+            p
+        })
+        .collect();
+
+    // 同一进程同时监听 IPv4 和 IPv6 时只保留一条，优先保留允许局域网连接的那条
+    ports.sort_by_key(|p| (p.port, p.pid, !p.bind_scope.is_exposed()));
+    ports.dedup_by_key(|p| (p.port, p.pid));
+    ports
+}
+
+/// 列出本机回环/通配地址上的所有 TCP 监听端口及其进程
+fn list_local_listeners() -> Vec<DetectedPort> {
+    ListenerSnapshot::capture()
+        .listeners
+        .into_iter()
+        .filter(|l| l.bind_scope.is_local())
+        .map(|l| DetectedPort {
+            port: l.port,
+            port_type: "unknown".to_string(),
+            process_name: l.process_name,
+            pid: l.pid,
+            bind_scope: l.bind_scope,
+        })
+        .collect()
+}
+
+/// 探测端口协议时的连接和读写超时
+const PROBE_TIMEOUT: Duration = Duration::from_millis(300);
+
+/// 地址上是否有程序在监听（能在探测超时内建立连接）
+pub fn is_listening(addr: SocketAddr) -> bool {
+    TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok()
+}
+
+/// 向本机端口发送一段数据，返回响应的前几个字节（连接失败或超时返回 None）
+fn probe_port(port: u16, request: &[u8], expected_len: usize) -> Option<Vec<u8>> {
+    let addr = ([127, 0, 0, 1], port).into();
+    let mut stream = TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).ok()?;
+    stream.set_read_timeout(Some(PROBE_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(PROBE_TIMEOUT)).ok()?;
+    stream.write_all(request).ok()?;
+
+    let mut response = vec![0; expected_len];
+    stream.read_exact(&mut response).ok()?;
+    Some(response)
+}
+
+/// 是否响应 HTTP CONNECT（目标为本机不可用的端口，代理会很快返回错误状态）
+fn speaks_http_proxy(port: u16) -> bool {
+    let request = b"CONNECT 127.0.0.1:1 HTTP/1.1\r\nHost: 127.0.0.1:1\r\n\r\n";
+    probe_port(port, request, 5).is_some_and(|response| response == b"HTTP/")
+}
+
+/// 是否响应 SOCKS5 握手（无认证方式）
+fn speaks_socks5(port: u16) -> bool {
+    probe_port(port, &[0x05, 0x01, 0x00], 2).is_some_and(|response| response[0] == 0x05)
+}
+
+/// 实际探测端口支持的协议："http"、"socks"、"mixed"，都不响应时返回 None
+fn probe_port_type(port: u16) -> Option<&'static str> {
+    match (speaks_http_proxy(port), speaks_socks5(port)) {
+        (true, true) => Some("mixed"),
+        (true, false) => Some("http"),
+        (false, true) => Some("socks"),
+        (false, false) => None,
+    }
+}
+
+/// 对检测到的端口进行分类（HTTP/SOCKS/混合）
+/// 命令行参数或配置文件中指明的端口直接使用其类型，其余端口优先实际探测协议，
+/// 探测不到时再根据默认端口和常见端口推断
+fn classify_ports(
+    mut ports: Vec<DetectedPort>,
+    config: &VpnConfig,
+    flags: &LaunchFlags,
+) -> Vec<DetectedPort> {
+    // 去重（同一端口同时监听多个地址时，优先保留允许局域网连接的那条）
+    ports.sort_by_key(|p| (p.port, !p.bind_scope.is_exposed()));
+    ports.dedup_by_key(|p| p.port);
+    flags.apply(&mut ports);
+
+    for port in &mut ports {
+        if port.port_type != "unknown" {
             continue;
         }
-        if !line.contains("LISTEN") {
-            continue;
+        if let Some(port_type) = probe_port_type(port.port) {
+            port.port_type = port_type.to_string();
+        } else if port.port == config.default_http_port {
+            port.port_type = "http".to_string();
+        } else if port.port == config.default_socks_port {
+            port.port_type = "socks".to_string();
+        } else if guess_port_type(port.port) != "unknown" {
+            port.port_type = guess_port_type(port.port).to_string();
         }
+    }
 
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 9 {
-            continue;
+    ports
+}
+
+/// 从代理核心的启动参数中解析出的信息，例如
+/// `clash -f config.yaml -ext-ctl 127.0.0.1:9090`、`v2ray run -c config.json`
+#[derive(Debug, Default)]
+struct LaunchFlags {
+    config_file: Option<String>,
+    controller_port: Option<u16>,    // 外部控制器端口，不是代理端口
+    ports: Vec<(u16, &'static str)>, // 参数或配置文件中指明的代理端口及其类型
+}
+
+impl LaunchFlags {
+    /// 解析检测到的端口所属进程的命令行，多个进程时合并（先出现的优先）
+    fn of_ports(ports: &[DetectedPort]) -> Self {
+        let mut pids: Vec<u32> = ports
+            .iter()
+            .map(|p| p.pid)
+            .filter(|pid| *pid != 0)
+            .collect();
+        pids.sort_unstable();
+        pids.dedup();
+
+        let mut merged = LaunchFlags::default();
+        for pid in pids {
+            let Some(args) = process_args(pid) else {
+                continue;
+            };
+            let flags = LaunchFlags::parse(&args);
+            merged.config_file = merged.config_file.or(flags.config_file);
+            merged.controller_port = merged.controller_port.or(flags.controller_port);
+            merged.ports.extend(flags.ports);
         }
+        merged
+    }
 
-        // lsof 输出格式: COMMAND PID USER FD TYPE DEVICE SIZE/OFF NODE NAME
-        let pid = parts[1].parse::<u32>().unwrap_or(0);
-        let name_part = parts[8]; // 类似 *:7890 或 127.0.0.1:7890
+    /// 解析命令行参数（支持 -flag value、--flag value 和 -flag=value 形式），
+    /// 再从 YAML 配置文件中补充参数没有指明的端口和外部控制器
+    fn parse(args: &[String]) -> Self {
+        let mut flags = LaunchFlags::default();
+        let mut home_dir = None;
+        let mut iter = args.iter().skip(1);
+        while let Some(arg) = iter.next() {
+            let Some(flag) = arg.strip_prefix('-') else {
+                continue;
+            };
+            let flag = flag.strip_prefix('-').unwrap_or(flag);
+            let (name, inline_value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (flag, None),
+            };
+            let known = matches!(
+                name,
+                "f" | "c"
+                    | "config"
+                    | "d"
+                    | "ext-ctl"
+                    | "external-controller"
+                    | "port"
+                    | "http-port"
+                    | "socks-port"
+                    | "mixed-port"
+            );
+            if !known {
+                continue;
+            }
+            let Some(value) = inline_value.or_else(|| iter.next().cloned()) else {
+                break;
+            };
 
-        if let Some(port_str) = name_part.rsplit(':').next() {
-            if let Ok(port) = port_str.parse::<u16>() {
-                if port > 1000 && port < 65535 {
-                    ports.push(DetectedPort {
-                        port,
-                        port_type: "unknown".to_string(),
-                        process_name: process_name.to_string(),
-                        pid,
-                    });
+            match name {
+                "f" | "c" | "config" => flags.config_file = Some(value),
+                "d" => home_dir = Some(value),
+                "ext-ctl" | "external-controller" => {
+                    flags.controller_port = parse_listen_port(&value)
+                }
+                _ => {
+                    let port_type = match name {
+                        "socks-port" => "socks",
+                        "mixed-port" => "mixed",
+                        _ => "http",
+                    };
+                    if let Ok(port) = value.parse::<u16>() {
+                        flags.ports.push((port, port_type));
+                    }
                 }
             }
         }
+
+        // Clash 只指定了配置目录时，使用目录下的 config.yaml
+        if flags.config_file.is_none() {
+            flags.config_file = home_dir.map(|dir| {
+                std::path::Path::new(&dir)
+                    .join("config.yaml")
+                    .to_string_lossy()
+                    .into_owned()
+            });
+        }
+
+        if let Some(configs) = flags.config_file.as_deref().and_then(read_yaml_configs) {
+            for (port, port_type) in configs.typed_ports() {
+                if !flags.ports.iter().any(|(p, _)| *p == port) {
+                    flags.ports.push((port, port_type));
+                }
+            }
+            if flags.controller_port.is_none() {
+                flags.controller_port = parse_listen_port(&configs.external_controller);
+            }
+        }
+        flags
     }
 
-    Some(ports)
+    /// 去掉外部控制器端口，并为参数中指明的端口设置类型
+    fn apply(&self, ports: &mut Vec<DetectedPort>) {
+        if let Some(controller_port) = self.controller_port {
+            ports.retain(|p| p.port != controller_port);
+        }
+        for port in ports.iter_mut() {
+            if let Some((_, port_type)) = self.ports.iter().find(|(p, _)| *p == port.port) {
+                port.port_type = port_type.to_string();
+            }
+        }
+    }
+}
+
+/// 读取 Clash 的 YAML 配置文件中的端口（不是 YAML 或读取失败时返回 None）
+fn read_yaml_configs(path: &str) -> Option<ClashConfigs> {
+    let lower = path.to_lowercase();
+    if !lower.ends_with(".yaml") && !lower.ends_with(".yml") {
+        return None;
+    }
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_yaml::from_str(&content).ok()
 }
 
+/// Linux: /proc/<pid>/cmdline 中的参数以 NUL 分隔
 #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-fn find_ports_by_process_name(_process_name: &str) -> Option<Vec<DetectedPort>> {
-    // Linux 或其他系统暂不支持
-    None
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let content = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args: Vec<String> = content
+        .split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    (!args.is_empty()).then_some(args)
 }
 
-/// 对检测到的端口进行分类（HTTP/SOCKS）
-fn classify_ports(mut ports: Vec<DetectedPort>, config: &VpnConfig) -> Vec<DetectedPort> {
-    // 去重
-    ports.sort_by_key(|p| p.port);
-    ports.dedup_by_key(|p| p.port);
+/// macOS: ps 只能给出拼接后的命令行，按空白拆分（路径中含空格的参数会被拆开）
+#[cfg(target_os = "macos")]
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let mut command = None;
+    for_each_output_line("ps", &["-o", "command=", "-p", &pid.to_string()], |line| {
+        if command.is_none() && !line.trim().is_empty() {
+            command = Some(line.split_whitespace().map(str::to_string).collect());
+        }
+    });
+    command
+}
 
-    // 根据默认端口和常见规则分类
-    for port in &mut ports {
-        if port.port == config.default_http_port {
-            port.port_type = "http".to_string();
-        } else if port.port == config.default_socks_port {
-            port.port_type = "socks".to_string();
-        } else {
-            // 常见的 HTTP 代理端口
-            let http_ports = [7890, 8080, 8118, 3128, 10808, 15236, 6152];
-            // 常见的 SOCKS 代理端口
-            let socks_ports = [7891, 1080, 10809, 15235, 6153];
-
-            if http_ports.contains(&port.port) {
-                port.port_type = "http".to_string();
-            } else if socks_ports.contains(&port.port) {
-                port.port_type = "socks".to_string();
+/// Windows: wmic 输出 CommandLine=<命令行>，按 Windows 的规则拆分（双引号内的空白不拆分）
+#[cfg(target_os = "windows")]
+fn process_args(pid: u32) -> Option<Vec<String>> {
+    let filter = format!("ProcessId={}", pid);
+    let mut command = None;
+    for_each_output_line(
+        "wmic",
+        &["process", "where", &filter, "get", "CommandLine", "/value"],
+        |line| {
+            if let Some(value) = line.trim().strip_prefix("CommandLine=") {
+                command = Some(split_windows_command_line(value));
+            }
+        },
+    );
+    command.filter(|args: &Vec<String>| !args.is_empty())
+}
+
+#[cfg(target_os = "windows")]
+fn split_windows_command_line(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
             }
         }
     }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
 
-    ports
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::time::Instant;
+
+    /// 合成的 netstat -ano 输出：表头加 50000 行连接，每 100 行有一行监听
+    fn synthetic_netstat_dump(lines: usize) -> Vec<u8> {
+        let mut dump = b"\r\nActive Connections\r\n\r\n  Proto  Local Address          Foreign Address        State           PID\r\n".to_vec();
+        for i in 0..lines {
+            let port = 10000 + (i % 50000) as u16;
+            let row = if i % 100 == 0 {
+                format!(
+                    "  TCP    127.0.0.1:{}        0.0.0.0:0              LISTENING       {}\r\n",
+                    port, i
+                )
+            } else {
+                format!(
+                    "  TCP    192.168.1.2:{}      93.184.216.34:443      ESTABLISHED     {}\r\n",
+                    port, i
+                )
+            };
+            dump.extend_from_slice(row.as_bytes());
+        }
+        // 非 UTF-8 的本地化输出不应中断解析
+        dump.extend_from_slice(b"  TCP    0.0.0.0:7890  0.0.0.0:0  \xc0\xfa LISTENING  42\r\n");
+        dump
+    }
+
+    #[test]
+    fn streaming_parser_handles_large_netstat_dump_quickly() {
+        let dump = synthetic_netstat_dump(50_000);
+        let started = Instant::now();
+        let mut listeners = Vec::new();
+        for_each_line(Cursor::new(dump), |line| {
+            if let Some((addr, pid)) = parse_netstat_line(line) {
+                listeners.push((parse_listen_port(addr), pid));
+            }
+        });
+        let elapsed = started.elapsed();
+
+        assert_eq!(listeners.len(), 501);
+        assert_eq!(listeners[0], (Some(10000), 0));
+        assert_eq!(listeners[500], (Some(7890), 42));
+        assert!(
+            elapsed < Duration::from_secs(2),
+            "parsing 50k lines took {:?}",
+            elapsed
+        );
+    }
+
+    fn processes(list: &[(u32, &str)]) -> Vec<(u32, String)> {
+        list.iter()
+            .map(|(pid, name)| (*pid, name.to_string()))
+            .collect()
+    }
+
+    fn listener(port: u16, pid: u32) -> Listener {
+        Listener {
+            port,
+            pid,
+            process_name: String::new(),
+            bind_scope: BindScope::Loopback,
+        }
+    }
+
+    #[test]
+    fn running_vpn_reports_pids_and_ports_first() {
+        let processes = processes(&[
+            (1, "sshd"),
+            (43, "verge-mihomo"),
+            (42, "Clash-Verge.exe"),
+            (43, "verge-mihomo"),
+        ]);
+        let statuses = vpn_statuses(get_vpn_configs(), &processes, || {
+            vec![listener(9090, 43), listener(22, 1), listener(7890, 43)]
+        });
+
+        assert_eq!(statuses[0].config.name, "Clash");
+        assert!(statuses[0].running);
+        assert_eq!(statuses[0].pids, [42, 43]);
+        assert_eq!(statuses[0].detected_ports, [7890, 9090]);
+        assert!(statuses[1..]
+            .iter()
+            .all(|s| !s.running && s.pids.is_empty() && s.detected_ports.is_empty()));
+    }
+
+    #[test]
+    fn listeners_are_not_captured_when_no_vpn_runs() {
+        let processes = processes(&[(1, "sshd"), (2, "cfwhelper")]);
+        let statuses = vpn_statuses(get_vpn_configs(), &processes, || {
+            panic!("listeners captured without a running VPN")
+        });
+        let names: Vec<String> = statuses.into_iter().map(|s| s.config.name).collect();
+        let expected: Vec<String> = get_vpn_configs().into_iter().map(|c| c.name).collect();
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn process_matching_prefers_exact_names() {
+        let processes = processes(&[(1, "mihomo"), (2, "mihomo-helper"), (3, "cfwhelper")]);
+        fn name(process: &(u32, String)) -> &str {
+            &process.1
+        }
+        let patterns = ["mihomo".to_string(), "cfw".to_string()];
+
+        let (matched, substring) = match_processes(&processes, name, &patterns, false);
+        assert_eq!(matched, [&processes[0]]);
+        assert!(!substring);
+
+        // 精确匹配不到时退回子串匹配，短于 4 个字符的名称不参与
+        let (matched, substring) = match_processes(&processes[1..], name, &patterns, false);
+        assert_eq!(matched, [&processes[1]]);
+        assert!(substring);
+
+        let case_sensitive = ["Mihomo".to_string()];
+        assert!(match_processes(&processes, name, &case_sensitive, true)
+            .0
+            .is_empty());
+        let glob = ["mihomo-*".to_string()];
+        assert_eq!(
+            match_processes(&processes, name, &glob, false).0,
+            [&processes[1]]
+        );
+    }
+
+    #[test]
+    fn glob_patterns_and_exe_suffix() {
+        let cases = [
+            ("clash*", "clash-verge", true),
+            ("*verge*", "Clash-Verge.exe", true),
+            ("*verge*", "clash", false),
+            ("ss-?ocal", "ss-local", true),
+            ("clash", "Clash.EXE", true),
+            ("clash", "clash-verge", false),
+        ];
+        for (pattern, name, expected) in cases {
+            assert_eq!(
+                process_matches(name, pattern, false),
+                expected,
+                "{} ~ {}",
+                pattern,
+                name
+            );
+        }
+        assert!(!process_matches("Clash.exe", "clash", true));
+        assert!(process_matches("Clash.exe", "Clash", true));
+    }
+
+    /// 合成的监听表：每个进程监听一个端口
+    fn snapshot_of(list: &[(u32, &str, u16)]) -> ListenerSnapshot {
+        ListenerSnapshot {
+            listeners: list
+                .iter()
+                .map(|(pid, name, port)| Listener {
+                    process_name: name.to_string(),
+                    ..listener(*port, *pid)
+                })
+                .collect(),
+            port_range: PortRange::default(),
+        }
+    }
+
+    #[test]
+    fn short_custom_names_do_not_fall_back_to_substrings() {
+        let snapshot = snapshot_of(&[(1, "gopls", 7001), (2, "cargo", 7002)]);
+        let result = detect_in_snapshot(&snapshot, "go");
+        assert!(!result.success);
+        assert_eq!(
+            result.message,
+            tr!("process_not_found", name = "go").as_str()
+        );
+    }
+
+    #[test]
+    fn substring_fallback_is_noted_in_message() {
+        let snapshot = snapshot_of(&[(1, "sshd", 2222), (5, "my-tunnel-core", 8118)]);
+        let result = detect_in_snapshot(&snapshot, "tunnel");
+        assert!(result.success);
+        assert_eq!(ports(&result.ports), [8118]);
+        assert!(result
+            .message
+            .ends_with(tr!("process_substring_match").as_str()));
+
+        let result = detect_in_snapshot(&snapshot, "my-tunnel-*");
+        assert_eq!(ports(&result.ports), [8118]);
+        assert!(!result
+            .message
+            .contains(tr!("process_substring_match").as_str()));
+    }
+
+    fn snapshot_with_port_80(range: PortRange) -> ListenerSnapshot {
+        ListenerSnapshot {
+            listeners: vec![
+                Listener {
+                    process_name: "tinyproxy".to_string(),
+                    ..listener(80, 7)
+                },
+                Listener {
+                    process_name: "tinyproxy".to_string(),
+                    ..listener(8888, 7)
+                },
+            ],
+            port_range: range,
+        }
+    }
+
+    fn ports(detected: &[DetectedPort]) -> Vec<u16> {
+        detected.iter().map(|p| p.port).collect()
+    }
+
+    #[test]
+    fn port_80_is_excluded_by_default_range() {
+        let snapshot = snapshot_with_port_80(PortRange::default());
+        let (detected, _) = snapshot.ports_of(&["tinyproxy".to_string()], false, &[]);
+        assert_eq!(ports(&detected), [8888]);
+    }
+
+    #[test]
+    fn port_80_is_included_when_range_allows_it() {
+        let snapshot = snapshot_with_port_80(PortRange { min: 1, max: 65535 });
+        let (detected, _) = snapshot.ports_of(&["tinyproxy".to_string()], false, &[]);
+        assert_eq!(ports(&detected), [80, 8888]);
+        assert!(detected.iter().all(|p| p.pid == 7));
+    }
+
+    #[test]
+    fn known_port_80_is_included_outside_range() {
+        let snapshot = snapshot_with_port_80(PortRange::default());
+        let (detected, _) = snapshot.ports_of(&["tinyproxy".to_string()], false, &[80]);
+        assert_eq!(ports(&detected), [80, 8888]);
+    }
+
+    #[test]
+    fn listener_addresses_from_netstat_lines() {
+        let cases = [
+            (
+                "  TCP    127.0.0.1:7890     0.0.0.0:0    LISTENING    100",
+                7890,
+                BindScope::Loopback,
+            ),
+            (
+                "  TCP    0.0.0.0:7890       0.0.0.0:0    LISTENING    100",
+                7890,
+                BindScope::Any,
+            ),
+            (
+                "  TCP    [::]:7890          [::]:0       LISTENING    100",
+                7890,
+                BindScope::Any,
+            ),
+            (
+                "  TCP    [::1]:7891         [::]:0       LISTENING    100",
+                7891,
+                BindScope::Loopback,
+            ),
+            (
+                "  TCP    192.168.1.5:1080   0.0.0.0:0    LISTENING    100",
+                1080,
+                BindScope::Specific("192.168.1.5".to_string()),
+            ),
+            (
+                "  TCP    [fe80::1%12]:1080  [::]:0       LISTENING    100",
+                1080,
+                BindScope::Specific("fe80::1".to_string()),
+            ),
+        ];
+        for (line, port, scope) in cases {
+            let (addr, pid) = parse_netstat_line(line).unwrap();
+            assert_eq!(pid, 100);
+            assert_eq!(parse_listen_port(addr), Some(port), "{}", line);
+            assert_eq!(parse_bind_scope(addr), scope, "{}", line);
+        }
+    }
+
+    #[test]
+    fn listener_addresses_from_lsof_names() {
+        // lsof -nP -iTCP -sTCP:LISTEN 输出的 NAME 列
+        let cases = [
+            ("*:7890", Some(7890), BindScope::Any),
+            ("127.0.0.1:7890", Some(7890), BindScope::Loopback),
+            ("[::1]:7890", Some(7890), BindScope::Loopback),
+            ("[::]:7891", Some(7891), BindScope::Any),
+            ("localhost:6152", Some(6152), BindScope::Loopback),
+            ("[::ffff:127.0.0.1]:1080", Some(1080), BindScope::Loopback),
+            ("NAME", None, BindScope::Specific("NAME".to_string())),
+            ("*:*", None, BindScope::Any),
+        ];
+        for (name, port, scope) in cases {
+            assert_eq!(parse_listen_port(name), port, "{}", name);
+            assert_eq!(parse_bind_scope(name), scope, "{}", name);
+        }
+    }
+
+    #[test]
+    fn listener_addresses_from_ss_lines() {
+        let cases = [
+            (
+                "LISTEN 0 4096 127.0.0.1:7890 0.0.0.0:* users:((\"clash\",pid=1234,fd=7))",
+                7890,
+                BindScope::Loopback,
+            ),
+            (
+                "LISTEN 0 4096 0.0.0.0:7890 0.0.0.0:* users:((\"clash\",pid=1234,fd=8))",
+                7890,
+                BindScope::Any,
+            ),
+            (
+                "LISTEN 0 4096 [::]:7890 [::]:* users:((\"clash\",pid=1234,fd=9))",
+                7890,
+                BindScope::Any,
+            ),
+            (
+                "LISTEN 0 4096 *:7891 *:* users:((\"clash\",pid=1234,fd=10))",
+                7891,
+                BindScope::Any,
+            ),
+            (
+                "LISTEN 0 128 192.168.1.5:1080 0.0.0.0:* users:((\"clash\",pid=1234,fd=11))",
+                1080,
+                BindScope::Specific("192.168.1.5".to_string()),
+            ),
+            (
+                "LISTEN 0 128 [fe80::1%eth0]:1080 [::]:* users:((\"clash\",pid=1234,fd=12))",
+                1080,
+                BindScope::Specific("fe80::1".to_string()),
+            ),
+        ];
+        for (line, port, scope) in cases {
+            let listener = parse_ss_line(line).unwrap();
+            assert_eq!(listener.port, port, "{}", line);
+            assert_eq!(listener.bind_scope, scope, "{}", line);
+            assert_eq!(
+                (listener.pid, listener.process_name.as_str()),
+                (1234, "clash")
+            );
+        }
+
+        // 没有权限查看其他用户的进程时只有地址
+        let listener = parse_ss_line("LISTEN 0 4096 127.0.0.53%lo:53 0.0.0.0:*").unwrap();
+        assert_eq!(listener.bind_scope, BindScope::Loopback);
+        assert_eq!((listener.pid, listener.process_name.as_str()), (0, ""));
+        assert!(parse_ss_line("LISTEN 0 4096").is_none());
+    }
+
+    fn detected(port: u16, bind_scope: BindScope) -> DetectedPort {
+        DetectedPort {
+            port,
+            port_type: "http".to_string(),
+            process_name: "clash".to_string(),
+            pid: 1,
+            bind_scope,
+        }
+    }
+
+    #[test]
+    fn bind_filter_and_lan_note() {
+        let all = || {
+            vec![
+                detected(7890, BindScope::Loopback),
+                detected(7891, BindScope::Any),
+                detected(7892, BindScope::Specific("192.168.1.5".to_string())),
+            ]
+        };
+        let filtered = |filter: BindFilter| {
+            let mut list = all();
+            filter.apply(&mut list);
+            ports(&list)
+        };
+        assert_eq!(filtered(BindFilter::All), [7890, 7891, 7892]);
+        assert_eq!(filtered(BindFilter::Loopback), [7890]);
+        assert_eq!(filtered(BindFilter::Exposed), [7891, 7892]);
+
+        assert_eq!(
+            with_lan_note("ok".to_string(), &all()),
+            format!("ok {}", tr!("port_lan_exposed", ports = "7891, 7892"))
+        );
+        assert_eq!(with_lan_note("ok".to_string(), &all()[..1]), "ok");
+    }
+
+    #[test]
+    fn netstat_line_requires_listening_state_and_pid() {
+        assert_eq!(
+            parse_netstat_line("  TCP    [::]:7890    [::]:0    LISTENING    1234\r"),
+            Some(("[::]:7890", 1234))
+        );
+        assert_eq!(
+            parse_netstat_line("  TCP    127.0.0.1:7890  1.2.3.4:80  ESTABLISHED  1234"),
+            None
+        );
+        assert_eq!(parse_netstat_line("  TCP  LISTENING  abc"), None);
+    }
+
+    #[test]
+    fn empty_vpn_name_is_rejected() {
+        for name in ["", "   ", "\t\n"] {
+            let result = detect_port_by_vpn_name(name);
+            assert!(!result.success, "{:?}", name);
+            assert_eq!(result.message, tr!("vpn_name_required").as_str());
+            assert!(result.ports.is_empty());
+        }
+    }
 }
@@ -1,13 +1,255 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::net::{Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::time::Duration;
+
+use crate::applied_state;
+use crate::config_manager::{self, DisableStrategy, EnvVarStyle, GitScope, ProxyCapabilities};
+use crate::file_lock;
+use crate::i18n::{CommandError, Message};
+use crate::paths::{self, Environment};
+use crate::port_detector;
+use crate::shims;
 
 /// 代理配置组
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyProfile {
     pub name: String,
     pub host: String,
-    pub port: u16,
+    pub port: u16, // HTTP 端口
+    #[serde(default)]
+    pub socks_port: Option<u16>, // SOCKS 端口（可选，旧配置没有该字段）
+    #[serde(default)]
+    pub vpn: Option<String>, // 关联的 VPN 名称，用于检测端口是否已变化
+    #[serde(default)]
+    pub bypass: Option<Vec<String>>, // 使用该配置组的软件都不走代理的地址，None 表示未自定义
+    #[serde(default)]
+    pub proxy_scheme: ProxyScheme, // 主端口的代理协议
+}
+
+/// 代理地址使用的协议（旧配置没有该字段，默认为 http）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyScheme {
+    #[default]
+    Http,
+    Https, // 与代理之间使用 TLS 连接
+    Socks5,
+    Socks5h, // 由代理解析域名
+}
+
+impl ProxyScheme {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProxyScheme::Http => "http",
+            ProxyScheme::Https => "https",
+            ProxyScheme::Socks5 => "socks5",
+            ProxyScheme::Socks5h => "socks5h",
+        }
+    }
+}
+
+impl ProxyProfile {
+    /// 生成代理 URL，IPv6 地址会加上方括号，如 http://[::1]:7890
+    pub fn proxy_url(&self) -> String {
+        format!(
+            "{}://{}:{}",
+            self.proxy_scheme.as_str(),
+            format_host(&self.host),
+            self.port
+        )
+    }
+
+    /// 生成 SOCKS 代理 URL（未配置 SOCKS 端口时返回 None）
+    pub fn socks_url(&self) -> Option<String> {
+        self.socks_port
+            .map(|port| format!("socks5://{}:{}", format_host(&self.host), port))
+    }
+}
+
+/// 裸 IPv6 地址加上方括号，其他主机名原样返回
+pub fn format_host(host: &str) -> String {
+    if host.parse::<Ipv6Addr>().is_ok() {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// 规范化主机地址：去除协议前缀和末尾斜杠，IPv6 地址统一加方括号保存
+/// 拒绝空值、空白/换行字符（会破坏 .gitconfig 等文件）以及内嵌的端口号
+pub fn normalize_host(host: &str) -> Result<String, Message> {
+    let mut host = host.trim();
+    if let Some((_, rest)) = host.split_once("://") {
+        host = rest;
+    }
+    let host = host.trim_end_matches('/');
+
+    if host.is_empty() {
+        return Err(tr!("host_empty"));
+    }
+    if host.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(tr!("host_invalid_chars"));
+    }
+
+    // 带方括号的 IPv6 地址
+    if let Some(inner) = host.strip_prefix('[') {
+        return match inner.split_once(']') {
+            Some((addr, "")) if addr.parse::<Ipv6Addr>().is_ok() => Ok(format!("[{}]", addr)),
+            Some((addr, _)) if addr.parse::<Ipv6Addr>().is_ok() => Err(tr!("host_has_port")),
+            _ => Err(tr!("host_invalid", host = host)),
+        };
+    }
+
+    // 裸 IPv6 地址
+    if host.parse::<Ipv6Addr>().is_ok() {
+        return Ok(format!("[{}]", host));
+    }
+
+    if host.contains(':') {
+        return Err(tr!("host_has_port"));
+    }
+    if host.contains('/') || host.contains('@') {
+        return Err(tr!("host_invalid", host = host));
+    }
+
+    Ok(host.to_string())
+}
+
+/// 保留的配置组名称：映射到它的软件明确不使用代理，应用时关闭该软件的代理
+pub const DIRECT_PROFILE: &str = "direct";
+
+/// 配置组和自定义软件名称的最大长度（字符数）
+const MAX_NAME_LEN: usize = 64;
+
+/// 规范化用户输入的名称：去除首尾空白，拒绝空名称和过长的名称
+pub fn normalize_name(name: &str) -> Result<String, Message> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(tr!("name_empty"));
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(tr!("name_too_long", max = MAX_NAME_LEN));
+    }
+    Ok(name.to_string())
+}
+
+/// 名称是否相同（忽略大小写和首尾空白），配置组、软件和场景的查找统一使用
+pub fn names_eq(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// 按名称查找配置组（忽略大小写和首尾空白）
+pub fn find_profile<'a>(config: &'a UserConfig, name: &str) -> Option<&'a ProxyProfile> {
+    config.profiles.iter().find(|p| names_eq(&p.name, name))
+}
+
+/// 映射引用的配置组是否可用（存在的配置组或 direct）
+pub fn profile_exists(config: &UserConfig, profile_name: &str) -> bool {
+    names_eq(profile_name, DIRECT_PROFILE) || find_profile(config, profile_name).is_some()
+}
+
+/// 规范化后重名的配置组和自定义软件（旧版本允许只有大小写或空白不同的名称），
+/// 保留全部并返回提示
+pub fn name_collisions(config: &UserConfig) -> Vec<String> {
+    let collisions = |names: Vec<&str>| {
+        let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for name in names {
+            groups
+                .entry(name.trim().to_lowercase())
+                .or_default()
+                .push(name);
+        }
+        groups
+            .into_values()
+            .filter(|group| group.len() > 1)
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|name| format!("\"{}\"", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let profiles = collisions(config.profiles.iter().map(|p| p.name.as_str()).collect());
+    let software = collisions(
+        config
+            .custom_software
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect(),
+    );
+    profiles
+        .into_iter()
+        .map(|names| tr!("profile_name_collision", names = names).to_string())
+        .chain(
+            software
+                .into_iter()
+                .map(|names| tr!("software_name_collision", names = names).to_string()),
+        )
+        .collect()
+}
+
+/// 校验并规范化代理配置组
+pub fn validate_profile(mut profile: ProxyProfile) -> Result<ProxyProfile, Message> {
+    profile.name = normalize_name(&profile.name)?;
+    // 控制字符在任何配置格式中都无法安全表示
+    if profile.name.chars().any(char::is_control) {
+        return Err(tr!("profile_name_invalid_chars"));
+    }
+    if profile.name.eq_ignore_ascii_case(DIRECT_PROFILE) {
+        return Err(tr!("profile_name_reserved", name = profile.name));
+    }
+    profile.host = normalize_host(&profile.host)?;
+    if profile.port == 0 || profile.socks_port == Some(0) {
+        return Err(tr!("port_out_of_range"));
+    }
+    if let Some(bypass) = &profile.bypass {
+        let bypass: Vec<String> = bypass.iter().map(|host| host.trim().to_string()).collect();
+        if let Some(host) = bypass.iter().find(|host| {
+            host.contains(',') || host.chars().any(|c| c.is_whitespace() || c.is_control())
+        }) {
+            return Err(tr!("invalid_bypass_host", host = host));
+        }
+        profile.bypass = Some(merge_bypass(&[&bypass]));
+    }
+    Ok(profile)
+}
+
+/// 合并多个绕过列表：按顺序保留首次出现的地址（不区分大小写去重），跳过空项
+/// 靠前的列表优先，用户自己的列表应放在 VPN 默认列表之前
+pub fn merge_bypass(lists: &[&[String]]) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for host in lists.iter().flat_map(|list| list.iter()) {
+        let host = host.trim();
+        if !host.is_empty() && !merged.iter().any(|h| h.eq_ignore_ascii_case(host)) {
+            merged.push(host.to_string());
+        }
+    }
+    merged
+}
+
+/// 配置组还没有自定义绕过列表时，使用关联 VPN 的默认绕过列表填充（已自定义的列表保持不变）
+pub fn seed_profile_bypass(profile: &mut ProxyProfile) {
+    if profile.bypass.is_some() {
+        return;
+    }
+    let Some(vpn) = &profile.vpn else {
+        return;
+    };
+    let defaults = port_detector::get_vpn_configs()
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(vpn))
+        .map(|c| c.default_bypass)
+        .unwrap_or_default();
+    if !defaults.is_empty() {
+        profile.bypass = Some(merge_bypass(&[&defaults]));
+    }
 }
 
 /// 软件与代理配置的映射
@@ -15,14 +257,90 @@ pub struct ProxyProfile {
 pub struct SoftwareProxyMapping {
     pub software_name: String,
     pub profile_name: String,
+    #[serde(default)]
+    pub proxy_localhost: bool, // 为 true 时本地地址也走代理（不加入 no_proxy）
+    #[serde(default)]
+    pub no_proxy_override: Option<String>, // 设置后替代默认的 no_proxy 列表（逗号分隔）
+    #[serde(default)]
+    pub options: MappingOptions,
+}
+
+/// 软件级别的选项，覆盖配置组和全局设置（旧配置没有该字段时使用默认值）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MappingOptions {
+    #[serde(default)]
+    pub strict_ssl: Option<bool>, // 是否校验证书，None 表示不修改软件的设置
+    #[serde(default)]
+    pub url_scopes: Vec<String>, // 只对这些地址使用代理，如 https://github.com
+    #[serde(default)]
+    pub env_var_style: Option<EnvVarStyle>, // 覆盖全局的环境变量写入风格
+    #[serde(default)]
+    pub extra_bypass: Vec<String>, // 额外不走代理的地址，追加到 no_proxy
+    #[serde(default)]
+    pub auto_failover: bool, // 配置组不可用时是否自动切换
+    #[serde(default)]
+    pub use_shims: bool, // 环境变量类软件：通过包装脚本只为指定命令设置代理
+    #[serde(default)]
+    pub shim_commands: Vec<String>, // 需要包装的命令，如 terraform
+    #[serde(default)]
+    pub disable_strategy: DisableStrategy, // 关闭代理时还原备份（默认）还是直接删除代理设置
+}
+
+/// 校验并规范化软件选项：去除空白项，地址范围必须是 http(s) URL，绕过地址不能包含空白或逗号
+pub fn validate_mapping_options(mut options: MappingOptions) -> Result<MappingOptions, Message> {
+    options.url_scopes = options
+        .url_scopes
+        .iter()
+        .map(|scope| scope.trim().trim_end_matches('/').to_string())
+        .filter(|scope| !scope.is_empty())
+        .collect();
+    if let Some(scope) = options.url_scopes.iter().find(|scope| {
+        !(scope.starts_with("http://") || scope.starts_with("https://"))
+            || scope.chars().any(char::is_whitespace)
+    }) {
+        return Err(tr!("invalid_url_scope", scope = scope));
+    }
+
+    options.extra_bypass = options
+        .extra_bypass
+        .iter()
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .collect();
+    if let Some(host) = options
+        .extra_bypass
+        .iter()
+        .find(|host| host.contains(',') || host.chars().any(char::is_whitespace))
+    {
+        return Err(tr!("invalid_bypass_host", host = host));
+    }
+
+    options.shim_commands = options
+        .shim_commands
+        .iter()
+        .map(|command| command.trim().to_string())
+        .filter(|command| !command.is_empty())
+        .collect();
+    for command in &options.shim_commands {
+        shims::validate_command(command)?;
+    }
+    if options.use_shims && options.shim_commands.is_empty() {
+        return Err(tr!("shim_commands_required"));
+    }
+
+    Ok(options)
 }
 
 /// 自定义软件配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomSoftware {
     pub name: String,
-    pub config_type: String, // "json", "ini", "env"
+    pub config_type: String, // "json", "ini", "env", "electron-json"
     pub config_path: String,
+    #[serde(default)]
+    pub json_keys: BTreeMap<String, String>, // electron-json 类型：键路径 -> 值模板
+    #[serde(default)]
+    pub capabilities: Option<ProxyCapabilities>, // 未指定时只支持 HTTP 代理
 }
 
 /// 关闭行为偏好
@@ -32,6 +350,9 @@ pub struct ClosePreference {
     pub action: String, // "minimize" 或 "exit"
 }
 
+/// 关闭窗口时可选的行为
+pub const CLOSE_ACTIONS: [&str; 2] = ["minimize", "exit"];
+
 impl Default for ClosePreference {
     fn default() -> Self {
         ClosePreference {
@@ -41,6 +362,56 @@ impl Default for ClosePreference {
     }
 }
 
+/// 场景：一整套软件映射（如 办公室 / 家 / 出差）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub name: String,
+    pub mappings: Vec<SoftwareProxyMapping>,
+    #[serde(default)]
+    pub disabled_software: Vec<String>, // 该场景下不使用代理的软件
+}
+
+/// 按进程名检测端口时接受的端口范围（闭区间）
+/// 与 VPN 预设默认端口相同的端口不受此限制
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PortRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl Default for PortRange {
+    fn default() -> Self {
+        PortRange {
+            min: 1001,
+            max: 65534,
+        }
+    }
+}
+
+impl PortRange {
+    pub fn contains(&self, port: u16) -> bool {
+        (self.min..=self.max).contains(&port)
+    }
+}
+
+/// 后台健康检查的设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HealthCheckSettings {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    pub target: String, // 通过代理连接的目标地址（主机:端口），只建立隧道不发送数据
+}
+
+impl Default for HealthCheckSettings {
+    fn default() -> Self {
+        HealthCheckSettings {
+            enabled: false,
+            interval_minutes: 10,
+            target: "www.gstatic.com:443".to_string(),
+        }
+    }
+}
+
 /// 用户配置（包含所有代理配置组、软件映射和自定义软件）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserConfig {
@@ -50,6 +421,46 @@ pub struct UserConfig {
     pub custom_software: Vec<CustomSoftware>,
     #[serde(default)]
     pub close_preference: ClosePreference,
+    #[serde(default = "default_locale")]
+    pub locale: String, // "zh-CN" 或 "en-US"
+    #[serde(default)]
+    pub scenes: Vec<Scene>,
+    #[serde(default)]
+    pub env_var_style: EnvVarStyle, // 环境变量写入风格
+    #[serde(default)]
+    pub idea_install: Option<String>, // 手动指定的 IDEA 配置目录名，为空时自动选择
+    #[serde(default = "default_true")]
+    pub config_watch_enabled: bool, // 是否监听配置文件的外部修改
+    #[serde(default = "default_true")]
+    pub skip_unchanged_writes: bool, // 配置已是目标值时不写入（避免编辑器提示文件已更改）
+    #[serde(default)]
+    pub verify_after_apply: bool, // 写入后重新读取配置，确认代理设置没有被其他程序还原
+    #[serde(default)]
+    pub start_hidden: bool, // 启动时不显示窗口，只显示托盘图标
+    #[serde(default)]
+    pub check_profile_endpoints: bool, // 保存配置组时检查主机能否解析、本机端口是否有程序监听
+    #[serde(default)]
+    pub git_scope: GitScope, // Git 代理写入的配置层级
+    #[serde(default)]
+    pub git_local_repo: Option<String>, // git_scope 为 local 时写入的仓库目录
+    #[serde(default)]
+    pub detection_port_range: PortRange, // 按进程名检测端口时接受的端口范围
+    #[serde(default)]
+    pub health_check: HealthCheckSettings, // 后台定期检查正在使用的配置组
+    #[serde(default)]
+    pub auto_check_updates: bool, // 启动时自动检查新版本
+    #[serde(default)]
+    pub path_overrides: BTreeMap<String, String>, // 软件名 -> 手动指定的配置文件路径
+    #[serde(default)]
+    pub protected_software: Vec<String>, // 重置和彻底清除时跳过的软件
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_locale() -> String {
+    crate::i18n::DEFAULT_LOCALE.to_string()
 }
 
 impl Default for UserConfig {
@@ -61,37 +472,82 @@ impl Default for UserConfig {
                     name: "Clash".to_string(),
                     host: "127.0.0.1".to_string(),
                     port: 7890,
+                    socks_port: Some(7891),
+                    vpn: Some("Clash".to_string()),
+                    bypass: None,
+                    proxy_scheme: ProxyScheme::Http,
                 },
                 ProxyProfile {
                     name: "V2Ray".to_string(),
                     host: "127.0.0.1".to_string(),
                     port: 10808,
+                    socks_port: Some(10809),
+                    vpn: Some("V2Ray".to_string()),
+                    bypass: None,
+                    proxy_scheme: ProxyScheme::Http,
                 },
                 ProxyProfile {
                     name: "Veee".to_string(),
                     host: "127.0.0.1".to_string(),
                     port: 15236,
+                    socks_port: Some(15235),
+                    vpn: Some("Veee".to_string()),
+                    bypass: None,
+                    proxy_scheme: ProxyScheme::Http,
                 },
             ],
             mappings: vec![],
             custom_software: vec![],
             close_preference: ClosePreference::default(),
+            locale: default_locale(),
+            scenes: vec![],
+            env_var_style: EnvVarStyle::default(),
+            idea_install: None,
+            config_watch_enabled: true,
+            skip_unchanged_writes: true,
+            verify_after_apply: false,
+            start_hidden: false,
+            check_profile_endpoints: false,
+            git_scope: GitScope::default(),
+            git_local_repo: None,
+            detection_port_range: PortRange::default(),
+            health_check: HealthCheckSettings::default(),
+            auto_check_updates: false,
+            path_overrides: BTreeMap::new(),
+            protected_software: vec![],
         }
     }
 }
 
-/// 获取配置文件路径
-fn get_config_path() -> PathBuf {
-    let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
-    home.join(".proxy-manager").join("user_config.json")
+/// 本程序最近一次写入（或已处理过）的配置内容哈希，用于区分外部修改
+static KNOWN_CONFIG_HASH: Mutex<Option<String>> = Mutex::new(None);
+
+/// 记录配置内容为本程序已知的内容
+pub fn remember_config_content(content: &str) {
+    if let Ok(mut known) = KNOWN_CONFIG_HASH.lock() {
+        *known = Some(applied_state::content_hash(content));
+    }
 }
 
-/// 加载用户配置
-pub fn load_user_config() -> UserConfig {
-    let config_path = get_config_path();
+/// 配置内容是否与本程序最近写入的一致
+pub fn is_known_config_content(content: &str) -> bool {
+    KNOWN_CONFIG_HASH
+        .lock()
+        .map(|known| known.as_deref() == Some(applied_state::content_hash(content).as_str()))
+        .unwrap_or(false)
+}
+
+/// 获取配置文件路径
+fn get_config_path(env: &Environment) -> PathBuf {
+    env.user_config_path()
+}
 
+/// 读取配置文件（调用方负责加锁），新位置不存在时读取旧位置的配置
+fn read_user_config(env: &Environment, config_path: &Path) -> UserConfig {
+    let config_path =
+        &paths::config_read_path(config_path, env.legacy_user_config_path().as_deref());
     if config_path.exists() {
-        match fs::read_to_string(&config_path) {
+        match fs::read_to_string(config_path) {
             Ok(content) => match serde_json::from_str(&content) {
                 Ok(config) => return config,
                 Err(e) => {
@@ -108,150 +564,1150 @@ pub fn load_user_config() -> UserConfig {
     UserConfig::default()
 }
 
-/// 保存用户配置
-pub fn save_user_config(config: &UserConfig) -> Result<(), String> {
-    let config_path = get_config_path();
+/// 写入配置文件（调用方负责加锁）
+fn write_user_config(config_path: &Path, config: &UserConfig) -> Result<(), Message> {
+    if paths::is_safe_mode() {
+        return Err(tr!("safe_mode_read_only"));
+    }
 
     // 确保目录存在
     if let Some(parent) = config_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| format!("创建配置目录失败: {}", e))?;
+        fs::create_dir_all(parent).map_err(|e| tr!("config_dir_create_failed", error = e))?;
     }
 
-    let content =
-        serde_json::to_string_pretty(config).map_err(|e| format!("序列化配置失败: {}", e))?;
+    let content = serde_json::to_string_pretty(config)
+        .map_err(|e| tr!("config_serialize_failed", error = e))?;
 
-    fs::write(&config_path, content).map_err(|e| format!("写入配置文件失败: {}", e))?;
+    fs::write(config_path, &content).map_err(|e| tr!("config_write_failed", error = e))?;
+    remember_config_content(&content);
 
     Ok(())
 }
 
-/// 添加代理配置组
-pub fn add_profile(profile: ProxyProfile) -> Result<UserConfig, String> {
-    let mut config = load_user_config();
+/// 所有配置组使用的端口（配置组名称, 端口），包括 SOCKS 端口
+pub fn profile_ports(config: &UserConfig) -> Vec<(String, u16)> {
+    config
+        .profiles
+        .iter()
+        .flat_map(|p| {
+            std::iter::once(p.port)
+                .chain(p.socks_port)
+                .map(move |port| (p.name.clone(), port))
+        })
+        .collect()
+}
 
-    // 检查是否已存在同名配置
-    if config.profiles.iter().any(|p| p.name == profile.name) {
-        return Err(format!("配置组 '{}' 已存在", profile.name));
+/// 加载用户配置
+pub fn load_user_config() -> UserConfig {
+    let env = Environment::current();
+    let config_path = get_config_path(&env);
+    // 获取锁失败时仍然读取，避免界面因为读取配置而卡住
+    let _guard = file_lock::lock_file(&config_path);
+    read_user_config(&env, &config_path)
+}
+
+/// 校验并保存整份用户配置，返回规范化后保存的配置
+/// 与逐项修改使用相同的校验，避免绕过校验写入无效的主机或端口
+pub fn save_user_config(config: UserConfig) -> Result<UserConfig, Message> {
+    let config = validate_user_config(config)?;
+    let config_path = get_config_path(&Environment::current());
+    let _guard = file_lock::lock_file(&config_path)?;
+    write_user_config(&config_path, &config)?;
+    Ok(config)
+}
+
+/// 校验并规范化整份用户配置：配置组、软件选项、检测端口范围和健康检查设置
+fn validate_user_config(mut config: UserConfig) -> Result<UserConfig, Message> {
+    config.profiles = config
+        .profiles
+        .into_iter()
+        .map(validate_profile)
+        .collect::<Result<_, _>>()?;
+    for mapping in &mut config.mappings {
+        mapping.options = validate_mapping_options(std::mem::take(&mut mapping.options))?;
     }
+    validate_port_range(&config.detection_port_range)?;
+    config.health_check = validate_health_check(config.health_check)?;
+    Ok(config)
+}
+
+/// 在同一把锁内完成"读取-修改-保存"，避免并发命令互相覆盖
+pub fn update_user_config<F>(modify: F) -> Result<UserConfig, Message>
+where
+    F: FnOnce(&mut UserConfig) -> Result<(), Message>,
+{
+    let env = Environment::current();
+    let config_path = get_config_path(&env);
+    let _guard = file_lock::lock_file(&config_path)?;
 
-    config.profiles.push(profile);
-    save_user_config(&config)?;
+    let mut config = read_user_config(&env, &config_path);
+    modify(&mut config)?;
+    write_user_config(&config_path, &config)?;
 
     Ok(config)
 }
 
+/// 保存配置组的结果：警告不影响保存（代理可能还没有启动）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileSaveResult {
+    pub config: UserConfig,
+    pub warnings: Vec<String>,
+}
+
+/// 检查配置组地址时解析主机名最多等待的时间（DNS 无响应时不能让保存卡住）
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 在后台线程解析主机名，超过 RESOLVE_TIMEOUT 时返回超时（解析线程继续运行，结果被丢弃）
+fn resolve_with_timeout(host: &str, port: u16) -> Result<Vec<SocketAddr>, Message> {
+    let (sender, receiver) = mpsc::channel();
+    let name = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    std::thread::spawn(move || {
+        let _ = sender.send((name.as_str(), port).to_socket_addrs().map(Vec::from_iter));
+    });
+    match receiver.recv_timeout(RESOLVE_TIMEOUT) {
+        Ok(Ok(addrs)) => Ok(addrs),
+        Ok(Err(e)) => Err(tr!("profile_host_unresolvable", host = host, error = e)),
+        Err(_) => Err(tr!(
+            "profile_host_resolve_timeout",
+            host = host,
+            seconds = RESOLVE_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+/// 检查配置组的地址：主机能否解析；解析为本机地址时，端口上是否有程序监听
+pub fn check_profile_endpoint(profile: &ProxyProfile) -> Vec<String> {
+    let addrs = match resolve_with_timeout(&profile.host, profile.port) {
+        Ok(addrs) => addrs,
+        Err(e) => return vec![e.to_string()],
+    };
+    if addrs.is_empty() || !addrs.iter().all(|addr| addr.ip().is_loopback()) {
+        return vec![];
+    }
+
+    std::iter::once(profile.port)
+        .chain(profile.socks_port)
+        .filter(|&port| {
+            !addrs
+                .iter()
+                .any(|addr| port_detector::is_listening(SocketAddr::new(addr.ip(), port)))
+        })
+        .map(|port| {
+            tr!(
+                "profile_port_not_listening",
+                host = profile.host,
+                port = port
+            )
+            .to_string()
+        })
+        .collect()
+}
+
+/// 开启了保存时检查的情况下检查配置组的地址
+fn profile_warnings(profile: &ProxyProfile) -> Vec<String> {
+    if load_user_config().check_profile_endpoints {
+        check_profile_endpoint(profile)
+    } else {
+        vec![]
+    }
+}
+
+/// 添加代理配置组
+pub fn add_profile(profile: ProxyProfile) -> Result<ProfileSaveResult, Message> {
+    let profile = validate_profile(profile)?;
+    let warnings = profile_warnings(&profile);
+
+    let config = update_user_config(|config| {
+        // 检查是否已存在同名配置（忽略大小写）
+        if find_profile(config, &profile.name).is_some() {
+            return Err(tr!("profile_exists", name = profile.name));
+        }
+
+        config.profiles.push(profile);
+        Ok(())
+    })?;
+    Ok(ProfileSaveResult { config, warnings })
+}
+
+/// 当前应用了该配置组的软件
+pub fn applied_software_of(profile_name: &str) -> Vec<String> {
+    applied_state::load_applied_state()
+        .entries
+        .into_iter()
+        .filter(|e| names_eq(&e.profile, profile_name))
+        .map(|e| e.software)
+        .collect()
+}
+
+/// 删除配置组失败的原因，前端按 kind 区分：in_use 时可以确认后强制删除
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DeleteProfileError {
+    InUse {
+        profile: String,
+        software: Vec<String>, // 仍应用了该配置组的软件
+        message: String,
+    },
+    Failed(CommandError),
+}
+
+impl From<Message> for DeleteProfileError {
+    fn from(message: Message) -> Self {
+        DeleteProfileError::Failed(message)
+    }
+}
+
 /// 删除代理配置组
-pub fn delete_profile(profile_name: &str) -> Result<UserConfig, String> {
-    let mut config = load_user_config();
+/// 配置组仍应用在某些软件上时拒绝删除（删除后无法再干净地关闭这些软件的代理）
+pub fn delete_profile(profile_name: &str) -> Result<UserConfig, DeleteProfileError> {
+    // 在删除所持有的同一把锁内检查占用，避免检查之后、删除之前又有软件应用了该配置组
+    let mut applied = Vec::new();
+    let result = update_user_config(|config| {
+        applied = applied_software_of(profile_name);
+        if !applied.is_empty() {
+            return Err(tr!(
+                "profile_in_use",
+                name = profile_name,
+                software = applied.join(", ")
+            ));
+        }
 
-    let original_len = config.profiles.len();
-    config.profiles.retain(|p| p.name != profile_name);
+        let original_len = config.profiles.len();
+        config.profiles.retain(|p| !names_eq(&p.name, profile_name));
 
-    if config.profiles.len() == original_len {
-        return Err(format!("配置组 '{}' 不存在", profile_name));
+        if config.profiles.len() == original_len {
+            return Err(tr!("profile_not_found", name = profile_name));
+        }
+
+        // 同时删除使用该配置组的映射
+        config
+            .mappings
+            .retain(|m| !names_eq(&m.profile_name, profile_name));
+        Ok(())
+    });
+    match result {
+        Err(message) if !applied.is_empty() => Err(DeleteProfileError::InUse {
+            profile: profile_name.to_string(),
+            software: applied,
+            message: message.to_string(),
+        }),
+        result => result.map_err(DeleteProfileError::from),
     }
+}
 
-    // 同时删除使用该配置组的映射
-    config.mappings.retain(|m| m.profile_name != profile_name);
+/// 清理孤立映射的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrunedMappings {
+    pub pruned: Vec<SoftwareProxyMapping>,
+    pub config: UserConfig,
+}
 
-    save_user_config(&config)?;
+/// 映射是否孤立：软件既不是预设也不是自定义软件，或配置组不存在
+fn is_orphaned_mapping(
+    mapping: &SoftwareProxyMapping,
+    config: &UserConfig,
+    builtin: &[String],
+) -> bool {
+    let known_software = builtin
+        .iter()
+        .any(|name| names_eq(name, &mapping.software_name))
+        || config
+            .custom_software
+            .iter()
+            .any(|s| names_eq(&s.name, &mapping.software_name));
+    !known_software || !profile_exists(config, &mapping.profile_name)
+}
 
-    Ok(config)
+fn builtin_software_names(config: &UserConfig) -> Vec<String> {
+    config_manager::get_software_list(config)
+        .into_iter()
+        .map(|s| s.name)
+        .collect()
+}
+
+/// 列出孤立的映射（导入配置或手动编辑后可能出现）
+pub fn list_orphaned_mappings() -> Vec<SoftwareProxyMapping> {
+    let config = load_user_config();
+    let builtin = builtin_software_names(&config);
+    config
+        .mappings
+        .iter()
+        .filter(|m| is_orphaned_mapping(m, &config, &builtin))
+        .cloned()
+        .collect()
+}
+
+/// 删除孤立的映射，返回被删除的映射和清理后的配置
+pub fn prune_orphaned_mappings() -> Result<PrunedMappings, Message> {
+    let builtin = builtin_software_names(&load_user_config());
+    let mut pruned = Vec::new();
+
+    let config = update_user_config(|config| {
+        let (orphaned, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut config.mappings)
+            .into_iter()
+            .partition(|m| is_orphaned_mapping(m, config, &builtin));
+        config.mappings = kept;
+        pruned = orphaned;
+        Ok(())
+    })?;
+
+    Ok(PrunedMappings { pruned, config })
 }
 
 /// 更新软件的代理配置映射
 pub fn update_software_mapping(
     software_name: &str,
     profile_name: &str,
-) -> Result<UserConfig, String> {
-    let mut config = load_user_config();
+) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        // 验证配置组是否存在
+        if !profile_exists(config, profile_name) {
+            return Err(tr!("profile_not_found", name = profile_name));
+        }
 
-    // 验证配置组是否存在
-    if !config.profiles.iter().any(|p| p.name == profile_name) {
-        return Err(format!("配置组 '{}' 不存在", profile_name));
-    }
+        // 查找并更新现有映射，或添加新映射
+        if let Some(mapping) = config
+            .mappings
+            .iter_mut()
+            .find(|m| names_eq(&m.software_name, software_name))
+        {
+            mapping.profile_name = profile_name.to_string();
+        } else {
+            config.mappings.push(SoftwareProxyMapping {
+                software_name: software_name.to_string(),
+                profile_name: profile_name.to_string(),
+                proxy_localhost: false,
+                no_proxy_override: None,
+                options: MappingOptions::default(),
+            });
+        }
+        Ok(())
+    })
+}
 
-    // 查找并更新现有映射，或添加新映射
-    if let Some(mapping) = config
-        .mappings
-        .iter_mut()
-        .find(|m| m.software_name == software_name)
-    {
-        mapping.profile_name = profile_name.to_string();
-    } else {
-        config.mappings.push(SoftwareProxyMapping {
-            software_name: software_name.to_string(),
-            profile_name: profile_name.to_string(),
-        });
-    }
+/// 把所有映射切换到同一个配置组（托盘快速切换使用）
+pub fn set_all_mappings_profile(profile_name: &str) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        if !profile_exists(config, profile_name) {
+            return Err(tr!("profile_not_found", name = profile_name));
+        }
+        if config.mappings.is_empty() {
+            return Err(tr!("no_mappings_to_switch"));
+        }
 
-    save_user_config(&config)?;
+        for mapping in &mut config.mappings {
+            mapping.profile_name = profile_name.to_string();
+        }
+        Ok(())
+    })
+}
 
-    Ok(config)
+/// 更新软件的选项
+pub fn update_mapping_options(
+    software_name: &str,
+    options: MappingOptions,
+) -> Result<UserConfig, Message> {
+    let options = validate_mapping_options(options)?;
+
+    update_user_config(|config| {
+        let mapping = config
+            .mappings
+            .iter_mut()
+            .find(|m| names_eq(&m.software_name, software_name))
+            .ok_or_else(|| tr!("mapping_not_found", name = software_name))?;
+        mapping.options = options;
+        Ok(())
+    })
+}
+
+/// 设置软件的本地地址是否走代理
+pub fn set_mapping_proxy_localhost(
+    software_name: &str,
+    proxy_localhost: bool,
+) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        let mapping = config
+            .mappings
+            .iter_mut()
+            .find(|m| names_eq(&m.software_name, software_name))
+            .ok_or_else(|| tr!("mapping_not_found", name = software_name))?;
+        mapping.proxy_localhost = proxy_localhost;
+        Ok(())
+    })
+}
+
+/// 设置软件的 no_proxy 覆盖列表，None 表示使用默认列表
+/// 列表会规范化为逗号分隔、无空白的形式；空字符串表示所有地址都走代理
+pub fn set_mapping_no_proxy_override(
+    software_name: &str,
+    no_proxy: Option<String>,
+) -> Result<UserConfig, Message> {
+    let no_proxy = match no_proxy {
+        Some(list) => {
+            let hosts: Vec<&str> = list
+                .split(',')
+                .map(str::trim)
+                .filter(|host| !host.is_empty())
+                .collect();
+            if let Some(host) = hosts
+                .iter()
+                .find(|host| host.chars().any(char::is_whitespace))
+            {
+                return Err(tr!("invalid_bypass_host", host = host));
+            }
+            Some(hosts.join(","))
+        }
+        None => None,
+    };
+
+    update_user_config(|config| {
+        let mapping = config
+            .mappings
+            .iter_mut()
+            .find(|m| names_eq(&m.software_name, software_name))
+            .ok_or_else(|| tr!("mapping_not_found", name = software_name))?;
+        mapping.no_proxy_override = no_proxy;
+        Ok(())
+    })
 }
 
 /// 更新代理配置组
-#[allow(dead_code)]
-pub fn update_profile(old_name: &str, profile: ProxyProfile) -> Result<UserConfig, String> {
-    let mut config = load_user_config();
-
-    // 查找并更新配置组
-    if let Some(existing) = config.profiles.iter_mut().find(|p| p.name == old_name) {
-        // 如果名称改变了，需要更新所有映射
-        if old_name != profile.name {
-            for mapping in &mut config.mappings {
-                if mapping.profile_name == old_name {
-                    mapping.profile_name = profile.name.clone();
+pub fn update_profile(old_name: &str, profile: ProxyProfile) -> Result<ProfileSaveResult, Message> {
+    let profile = validate_profile(profile)?;
+    let warnings = profile_warnings(&profile);
+
+    let config = update_user_config(|config| {
+        // 改名时不能与其他配置组重名（忽略大小写）
+        if config
+            .profiles
+            .iter()
+            .any(|p| names_eq(&p.name, &profile.name) && !names_eq(&p.name, old_name))
+        {
+            return Err(tr!("profile_exists", name = profile.name));
+        }
+
+        // 查找并更新配置组
+        if let Some(existing) = config
+            .profiles
+            .iter_mut()
+            .find(|p| names_eq(&p.name, old_name))
+        {
+            // 如果名称改变了，需要更新所有映射（包括场景中保存的映射）
+            if existing.name != profile.name {
+                let scene_mappings = config.scenes.iter_mut().flat_map(|s| &mut s.mappings);
+                for mapping in config.mappings.iter_mut().chain(scene_mappings) {
+                    if names_eq(&mapping.profile_name, old_name) {
+                        mapping.profile_name = profile.name.clone();
+                    }
                 }
+                // 已应用记录按配置组名称查找，不改名会使删除时的占用检查失效
+                applied_state::rename_profile(old_name, &profile.name)?;
             }
+
+            *existing = profile;
+        } else {
+            return Err(tr!("profile_not_found", name = old_name));
         }
+        Ok(())
+    })?;
+    Ok(ProfileSaveResult { config, warnings })
+}
 
-        existing.name = profile.name;
-        existing.host = profile.host;
-        existing.port = profile.port;
-    } else {
-        return Err(format!("配置组 '{}' 不存在", old_name));
-    }
+/// 两个配置组中取值不同的字段（未设置的字段为 None）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfileFieldDiff {
+    pub field: String,
+    pub a_value: Option<String>,
+    pub b_value: Option<String>,
+}
 
-    save_user_config(&config)?;
+/// 比较两个配置组，返回取值不同的字段（用于清理相近的重复配置组）
+pub fn diff_profiles(a: &str, b: &str) -> Result<Vec<ProfileFieldDiff>, Message> {
+    let config = load_user_config();
+    let find = |name: &str| {
+        find_profile(&config, name).ok_or_else(|| tr!("profile_not_found", name = name))
+    };
+    let (a, b) = (find(a)?, find(b)?);
 
-    Ok(config)
+    let fields = |p: &ProxyProfile| {
+        [
+            ("host", Some(p.host.clone())),
+            ("port", Some(p.port.to_string())),
+            ("socks_port", p.socks_port.map(|port| port.to_string())),
+            ("vpn", p.vpn.clone()),
+            ("bypass", p.bypass.as_ref().map(|hosts| hosts.join(", "))),
+            ("proxy_scheme", Some(p.proxy_scheme.as_str().to_string())),
+        ]
+    };
+    Ok(fields(a)
+        .into_iter()
+        .zip(fields(b))
+        .filter(|((_, a_value), (_, b_value))| a_value != b_value)
+        .map(|((field, a_value), (_, b_value))| ProfileFieldDiff {
+            field: field.to_string(),
+            a_value,
+            b_value,
+        })
+        .collect())
 }
 
 /// 添加自定义软件
-pub fn add_custom_software(software: CustomSoftware) -> Result<UserConfig, String> {
-    let mut config = load_user_config();
+pub fn add_custom_software(mut software: CustomSoftware) -> Result<UserConfig, Message> {
+    software.name = normalize_name(&software.name)?;
+    validate_custom_software(&software)?;
 
-    // 检查是否已存在同名软件
-    if config
-        .custom_software
-        .iter()
-        .any(|s| s.name == software.name)
-    {
-        return Err(format!("软件 '{}' 已存在", software.name));
+    update_user_config(|config| {
+        // 检查是否已存在同名软件（忽略大小写）
+        if config
+            .custom_software
+            .iter()
+            .any(|s| names_eq(&s.name, &software.name))
+        {
+            return Err(tr!("software_exists", name = software.name));
+        }
+
+        config.custom_software.push(software);
+        Ok(())
+    })
+}
+
+/// 校验自定义软件的配置路径和 JSON 键
+pub fn validate_custom_software(software: &CustomSoftware) -> Result<(), Message> {
+    config_manager::check_config_target(Path::new(&software.config_path))?;
+    if software.config_type == "electron-json" {
+        config_manager::validate_json_keys(&software.json_keys)?;
     }
+    Ok(())
+}
 
-    config.custom_software.push(software);
-    save_user_config(&config)?;
+/// 批量导入中单个软件的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomSoftwareImportItem {
+    pub name: String,
+    pub status: String, // "added" | "skipped" | "failed"
+    pub message: Option<String>,
+}
 
-    Ok(config)
+/// 批量导入自定义软件的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomSoftwareImport {
+    pub config: UserConfig,
+    pub items: Vec<CustomSoftwareImportItem>,
+}
+
+/// 批量导入自定义软件（如团队共享的软件列表），每项的校验与单个添加相同
+/// skip_existing: 同名软件已存在时跳过，否则该项导入失败；其余各项不受影响
+pub fn import_custom_software(
+    list: Vec<CustomSoftware>,
+    skip_existing: bool,
+) -> Result<CustomSoftwareImport, Message> {
+    let mut items = Vec::new();
+
+    let config = update_user_config(|config| {
+        for mut software in list {
+            let normalized = normalize_name(&software.name);
+            let item = |status: &str, message: Option<Message>| CustomSoftwareImportItem {
+                name: software.name.clone(),
+                status: status.to_string(),
+                message: message.map(String::from),
+            };
+            let exists = config
+                .custom_software
+                .iter()
+                .any(|s| names_eq(&s.name, &software.name));
+            if let Err(e) = normalized {
+                items.push(item("failed", Some(e)));
+            } else if exists && skip_existing {
+                items.push(item("skipped", None));
+            } else if exists {
+                items.push(item(
+                    "failed",
+                    Some(tr!("software_exists", name = software.name)),
+                ));
+            } else if let Err(e) = validate_custom_software(&software) {
+                items.push(item("failed", Some(e)));
+            } else {
+                items.push(item("added", None));
+                software.name = normalized?;
+                config.custom_software.push(software);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(CustomSoftwareImport { config, items })
 }
 
 /// 删除自定义软件
-pub fn delete_custom_software(software_name: &str) -> Result<UserConfig, String> {
-    let mut config = load_user_config();
+pub fn delete_custom_software(software_name: &str) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        let original_len = config.custom_software.len();
+        config
+            .custom_software
+            .retain(|s| !names_eq(&s.name, software_name));
 
-    let original_len = config.custom_software.len();
-    config.custom_software.retain(|s| s.name != software_name);
+        if config.custom_software.len() == original_len {
+            return Err(tr!("software_not_found", name = software_name));
+        }
+
+        // 同时删除该软件的映射
+        config
+            .mappings
+            .retain(|m| !names_eq(&m.software_name, software_name));
+        Ok(())
+    })
+}
+
+/// 找出场景中引用了但已不存在的配置组
+pub fn missing_scene_profiles(config: &UserConfig, scene: &Scene) -> Vec<String> {
+    let mut missing: Vec<String> = scene
+        .mappings
+        .iter()
+        .filter(|m| !profile_exists(config, &m.profile_name))
+        .map(|m| m.profile_name.clone())
+        .collect();
+    missing.dedup();
+    missing
+}
+
+/// 保存场景
+pub fn save_scene(scene: Scene) -> Result<UserConfig, Message> {
+    let name = normalize_name(&scene.name)?;
+
+    update_user_config(|config| {
+        if config.scenes.iter().any(|s| names_eq(&s.name, &name)) {
+            return Err(tr!("scene_exists", name = name));
+        }
+
+        let missing = missing_scene_profiles(config, &scene);
+        if !missing.is_empty() {
+            return Err(tr!("scene_profile_missing", profiles = missing.join(", ")));
+        }
+
+        config.scenes.push(Scene { name, ..scene });
+        Ok(())
+    })
+}
 
-    if config.custom_software.len() == original_len {
-        return Err(format!("软件 '{}' 不存在", software_name));
+/// 删除场景
+pub fn delete_scene(scene_name: &str) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        let original_len = config.scenes.len();
+        config.scenes.retain(|s| !names_eq(&s.name, scene_name));
+
+        if config.scenes.len() == original_len {
+            return Err(tr!("scene_not_found", name = scene_name));
+        }
+        Ok(())
+    })
+}
+
+/// 设置关闭行为（只接受 "minimize" / "exit"）
+pub fn set_close_action(action: &str) -> Result<UserConfig, Message> {
+    if !CLOSE_ACTIONS.contains(&action) {
+        return Err(tr!("invalid_close_action", action = action));
     }
 
-    // 同时删除该软件的映射
-    config.mappings.retain(|m| m.software_name != software_name);
+    update_user_config(|config| {
+        config.close_preference.action = action.to_string();
+        Ok(())
+    })
+}
 
-    save_user_config(&config)?;
+/// 设置是否记住关闭行为
+pub fn set_remember_close(remember: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.close_preference.remember = remember;
+        Ok(())
+    })
+}
 
-    Ok(config)
+/// 设置环境变量写入风格
+pub fn set_env_var_style(style: EnvVarStyle) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.env_var_style = style;
+        Ok(())
+    })
+}
+
+/// 指定写入代理设置的 IDEA 版本（None 表示自动选择）
+pub fn set_idea_install(name: Option<String>) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.idea_install = name;
+        Ok(())
+    })
+}
+
+/// 设置 Git 代理写入的配置层级（repo 为 local 层级使用的仓库目录）
+pub fn set_git_scope(scope: GitScope, repo: Option<String>) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.git_scope = scope;
+        config.git_local_repo = repo;
+        Ok(())
+    })
+}
+
+/// 设置配置已是目标值时是否跳过写入
+pub fn set_skip_unchanged_writes(enabled: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.skip_unchanged_writes = enabled;
+        Ok(())
+    })
+}
+
+/// 设置保存配置组时是否检查地址能否解析、端口是否有程序监听
+pub fn set_check_profile_endpoints(enabled: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.check_profile_endpoints = enabled;
+        Ok(())
+    })
+}
+
+/// 设置写入后是否验证代理设置仍然存在
+pub fn set_verify_after_apply(enabled: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.verify_after_apply = enabled;
+        Ok(())
+    })
+}
+
+/// 设置启动时是否隐藏窗口
+pub fn set_start_hidden(enabled: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.start_hidden = enabled;
+        Ok(())
+    })
+}
+
+/// 设置按进程名检测端口时接受的端口范围
+pub fn set_detection_port_range(range: PortRange) -> Result<UserConfig, Message> {
+    validate_port_range(&range)?;
+    update_user_config(|config| {
+        config.detection_port_range = range;
+        Ok(())
+    })
+}
+
+/// 最小值至少为 1，且不大于最大值
+fn validate_port_range(range: &PortRange) -> Result<(), Message> {
+    if range.min == 0 || range.min > range.max {
+        return Err(tr!("port_range_invalid", min = range.min, max = range.max));
+    }
+    Ok(())
+}
+
+/// 设置后台健康检查（间隔至少 1 分钟，目标为 主机:端口）
+pub fn set_health_check(settings: HealthCheckSettings) -> Result<UserConfig, Message> {
+    let settings = validate_health_check(settings)?;
+    update_user_config(|config| {
+        config.health_check = settings;
+        Ok(())
+    })
+}
+
+/// 校验健康检查设置，去除目标地址的首尾空白
+fn validate_health_check(settings: HealthCheckSettings) -> Result<HealthCheckSettings, Message> {
+    if settings.interval_minutes == 0 {
+        return Err(tr!("health_interval_invalid"));
+    }
+    let target = settings.target.trim().to_string();
+    let valid_target = target
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok_and(|p| p != 0));
+    if !valid_target {
+        return Err(tr!("health_target_invalid", target = target));
+    }
+    Ok(HealthCheckSettings { target, ..settings })
+}
+
+/// 设置或清除（path 为 None）软件的配置文件路径，路径必须是绝对路径
+pub fn set_config_path_override(
+    software_name: &str,
+    path: Option<String>,
+) -> Result<UserConfig, Message> {
+    let path = path.map(|p| p.trim().to_string());
+    if let Some(path) = &path {
+        if !Path::new(path).is_absolute() {
+            return Err(tr!("config_path_override_invalid", path = path));
+        }
+    }
+    update_user_config(|config| {
+        config
+            .path_overrides
+            .retain(|name, _| !names_eq(name, software_name));
+        if let Some(path) = path {
+            config
+                .path_overrides
+                .insert(software_name.trim().to_string(), path);
+        }
+        Ok(())
+    })
+}
+
+/// 软件是否受保护（批量重置和彻底清除时跳过）
+pub fn is_software_protected(config: &UserConfig, software_name: &str) -> bool {
+    config
+        .protected_software
+        .iter()
+        .any(|name| names_eq(name, software_name))
+}
+
+/// 设置软件是否受保护
+pub fn set_software_protected(software_name: &str, protected: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config
+            .protected_software
+            .retain(|name| !names_eq(name, software_name));
+        if protected {
+            config
+                .protected_software
+                .push(software_name.trim().to_string());
+        }
+        Ok(())
+    })
+}
+
+/// 设置启动时是否自动检查新版本
+pub fn set_auto_check_updates(enabled: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.auto_check_updates = enabled;
+        Ok(())
+    })
+}
+
+/// 设置是否监听配置文件的外部修改
+pub fn set_config_watch_enabled(enabled: bool) -> Result<UserConfig, Message> {
+    update_user_config(|config| {
+        config.config_watch_enabled = enabled;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(host: &str, port: u16) -> ProxyProfile {
+        ProxyProfile {
+            name: "Clash".to_string(),
+            host: host.to_string(),
+            port,
+            socks_port: None,
+            vpn: None,
+            bypass: None,
+            proxy_scheme: ProxyScheme::Http,
+        }
+    }
+
+    #[test]
+    fn typed_hosts_are_normalized() {
+        let cases = [
+            ("127.0.0.1", "127.0.0.1"),
+            ("  proxy.corp.local  ", "proxy.corp.local"),
+            ("http://127.0.0.1", "127.0.0.1"),
+            ("socks5://127.0.0.1/", "127.0.0.1"),
+            ("::1", "[::1]"),
+            ("[::1]", "[::1]"),
+            ("http://[fe80::1]/", "[fe80::1]"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(
+                normalize_host(input).as_deref(),
+                Ok(expected),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_hosts_are_rejected_per_field() {
+        let cases = [
+            ("", tr!("host_empty")),
+            ("http://", tr!("host_empty")),
+            ("127.0.0.1:7890", tr!("host_has_port")),
+            ("http://127.0.0.1:7890", tr!("host_has_port")),
+            ("[::1]:7890", tr!("host_has_port")),
+            ("proxy host", tr!("host_invalid_chars")),
+            ("proxy\n[core]", tr!("host_invalid_chars")),
+            ("proxy\u{7}", tr!("host_invalid_chars")),
+            ("user@proxy", tr!("host_invalid", host = "user@proxy")),
+            ("proxy/path", tr!("host_invalid", host = "proxy/path")),
+            ("[not-ipv6]", tr!("host_invalid", host = "[not-ipv6]")),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize_host(input), Err(expected), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn ipv6_profile_formats_bracketed_urls() {
+        let mut profile = validate_profile(profile("::1", 7890)).unwrap();
+        assert_eq!(profile.host, "[::1]");
+        assert_eq!(profile.proxy_url(), "http://[::1]:7890");
+        profile.socks_port = Some(7891);
+        assert_eq!(profile.socks_url().as_deref(), Some("socks5://[::1]:7891"));
+    }
+
+    #[test]
+    fn saving_whole_config_validates_and_normalizes() {
+        let home = tempfile::tempdir().unwrap();
+        Environment::sandbox(home.path()).run(|| {
+            let mut config = load_user_config();
+            config.profiles = vec![profile("http://[::1]/", 7890)];
+            let saved = save_user_config(config.clone()).unwrap();
+            assert_eq!(saved.profiles[0].host, "[::1]");
+            assert_eq!(load_user_config().profiles[0].host, "[::1]");
+
+            config.profiles = vec![profile("127.0.0.1\n[core]", 7890)];
+            assert_eq!(
+                save_user_config(config.clone()).unwrap_err(),
+                tr!("host_invalid_chars")
+            );
+            config.profiles = vec![profile("127.0.0.1", 7890)];
+            config.detection_port_range = PortRange { min: 9000, max: 80 };
+            assert!(save_user_config(config).is_err());
+            assert_eq!(load_user_config().profiles[0].host, "[::1]");
+        });
+    }
+
+    #[test]
+    fn profile_validation_rejects_bad_ports_and_names() {
+        assert_eq!(
+            validate_profile(profile("127.0.0.1", 0)).unwrap_err(),
+            tr!("port_out_of_range")
+        );
+        let socks_zero = ProxyProfile {
+            socks_port: Some(0),
+            ..profile("127.0.0.1", 7890)
+        };
+        assert_eq!(
+            validate_profile(socks_zero).unwrap_err(),
+            tr!("port_out_of_range")
+        );
+        let reserved = ProxyProfile {
+            name: " Direct ".to_string(),
+            ..profile("127.0.0.1", 7890)
+        };
+        assert_eq!(
+            validate_profile(reserved).unwrap_err(),
+            tr!("profile_name_reserved", name = "Direct")
+        );
+        let trimmed = ProxyProfile {
+            name: "  Clash  ".to_string(),
+            bypass: Some(vec![" localhost ".to_string(), "LOCALHOST".to_string()]),
+            ..profile("127.0.0.1", 7890)
+        };
+        let trimmed = validate_profile(trimmed).unwrap();
+        assert_eq!(trimmed.name, "Clash");
+        assert_eq!(trimmed.bypass, Some(vec!["localhost".to_string()]));
+    }
+
+    fn hosts(list: &[&str]) -> Vec<String> {
+        list.iter().map(|host| host.to_string()).collect()
+    }
+
+    #[test]
+    fn bypass_lists_merge_in_priority_order() {
+        let user = hosts(&["*.corp", " intranet ", ""]);
+        let defaults = hosts(&["*.LOCAL", "*.Corp", "Intranet", "*.lan"]);
+        assert_eq!(
+            merge_bypass(&[&user, &defaults]),
+            hosts(&["*.corp", "intranet", "*.LOCAL", "*.lan"])
+        );
+        assert!(merge_bypass(&[&[], &hosts(&["  "])]).is_empty());
+    }
+
+    #[test]
+    fn vpn_default_bypass_seeds_only_uncustomized_profiles() {
+        let linked = |bypass: Option<Vec<String>>| ProxyProfile {
+            vpn: Some("veee".to_string()),
+            bypass,
+            ..profile("127.0.0.1", 15236)
+        };
+
+        let mut seeded = linked(None);
+        seed_profile_bypass(&mut seeded);
+        assert_eq!(
+            seeded.bypass,
+            Some(hosts(&["*.local", "*.lan", "*.internal", "*.corp"]))
+        );
+
+        // 用户的列表（包括清空后的列表）保持不变
+        for custom in [hosts(&["intranet"]), vec![]] {
+            let mut customized = linked(Some(custom.clone()));
+            seed_profile_bypass(&mut customized);
+            assert_eq!(customized.bypass, Some(custom));
+        }
+
+        // 未关联 VPN 或 VPN 没有默认列表时不填充
+        let mut unlinked = profile("127.0.0.1", 7890);
+        seed_profile_bypass(&mut unlinked);
+        assert_eq!(unlinked.bypass, None);
+        let mut clash = ProxyProfile {
+            vpn: Some("Clash".to_string()),
+            ..profile("127.0.0.1", 7890)
+        };
+        seed_profile_bypass(&mut clash);
+        assert_eq!(clash.bypass, None);
+    }
+
+    #[test]
+    fn names_are_trimmed_and_length_checked() {
+        assert_eq!(normalize_name("  Clash  ").as_deref(), Ok("Clash"));
+        assert_eq!(normalize_name(" \t ").unwrap_err(), tr!("name_empty"));
+        assert_eq!(
+            normalize_name(&"a".repeat(MAX_NAME_LEN + 1)).unwrap_err(),
+            tr!("name_too_long", max = MAX_NAME_LEN)
+        );
+        assert!(normalize_name(&"名".repeat(MAX_NAME_LEN)).is_ok());
+    }
+
+    #[test]
+    fn lookups_ignore_case_and_whitespace() {
+        let mut config = UserConfig {
+            profiles: vec![profile("127.0.0.1", 7890)],
+            ..UserConfig::default()
+        };
+        assert!(names_eq(" clash", "CLASH "));
+        assert!(!names_eq("Clash", "Clash2"));
+        assert_eq!(find_profile(&config, "  cLaSh ").unwrap().port, 7890);
+        assert!(profile_exists(&config, "DIRECT"));
+        assert!(!profile_exists(&config, "Office"));
+        assert!(name_collisions(&config).is_empty());
+
+        // 旧版本保存的重名配置组保留，并提示用户
+        config.profiles.push(ProxyProfile {
+            name: "clash ".to_string(),
+            ..profile("127.0.0.1", 7891)
+        });
+        assert_eq!(
+            name_collisions(&config),
+            [tr!("profile_name_collision", names = "\"Clash\", \"clash \"").to_string()]
+        );
+    }
+
+    #[test]
+    fn mutators_match_names_case_insensitively() {
+        let home = tempfile::tempdir().unwrap();
+        Environment::sandbox(home.path()).run(|| {
+            assert_eq!(
+                add_profile(ProxyProfile {
+                    name: "clash ".to_string(),
+                    ..profile("127.0.0.1", 7891)
+                })
+                .err(),
+                Some(tr!("profile_exists", name = "clash"))
+            );
+
+            add_custom_software(CustomSoftware {
+                name: " myTool ".to_string(),
+                config_type: "json".to_string(),
+                config_path: home.path().join("tool.json").to_string_lossy().into_owned(),
+                json_keys: BTreeMap::new(),
+                capabilities: None,
+            })
+            .unwrap();
+            update_software_mapping("myTool", "CLASH").unwrap();
+            let config = set_mapping_proxy_localhost("MYTOOL", true).unwrap();
+            assert!(config.mappings.iter().any(|m| m.proxy_localhost));
+            assert!(list_orphaned_mappings()
+                .iter()
+                .all(|m| m.software_name != "myTool"));
+
+            let config = delete_custom_software("mytool").unwrap();
+            assert!(config.custom_software.is_empty());
+            assert!(config
+                .mappings
+                .iter()
+                .all(|m| !names_eq(&m.software_name, "myTool")));
+
+            let scene = |name: &str| Scene {
+                name: name.to_string(),
+                mappings: vec![],
+                disabled_software: vec![],
+            };
+            save_scene(scene("Work")).unwrap();
+            assert_eq!(
+                save_scene(scene(" work ")).err(),
+                Some(tr!("scene_exists", name = "work"))
+            );
+            assert!(delete_scene("WORK").unwrap().scenes.is_empty());
+        });
+    }
+
+    #[test]
+    fn renaming_profile_updates_scene_mappings() {
+        let home = tempfile::tempdir().unwrap();
+        Environment::sandbox(home.path()).run(|| {
+            let mapping = SoftwareProxyMapping {
+                software_name: "Git".to_string(),
+                profile_name: "clash".to_string(),
+                proxy_localhost: false,
+                no_proxy_override: None,
+                options: Default::default(),
+            };
+            save_scene(Scene {
+                name: "Work".to_string(),
+                mappings: vec![mapping],
+                disabled_software: vec![],
+            })
+            .unwrap();
+
+            let renamed = ProxyProfile {
+                name: "Office".to_string(),
+                ..profile("127.0.0.1", 7890)
+            };
+            let config = update_profile("Clash", renamed).unwrap().config;
+            assert_eq!(config.scenes[0].mappings[0].profile_name, "Office");
+            assert!(missing_scene_profiles(&config, &config.scenes[0]).is_empty());
+        });
+    }
+
+    #[test]
+    fn deleting_applied_profile_returns_structured_error() {
+        let home = tempfile::tempdir().unwrap();
+        fs::write(home.path().join(".gitconfig"), "[user]\n\tname = Tester\n").unwrap();
+        Environment::sandbox(home.path()).run(|| {
+            let proxy = config_manager::ProxySettings {
+                http_proxy: "http://127.0.0.1:7890".to_string(),
+                ..Default::default()
+            };
+            config_manager::enable_proxy(&["Git".to_string()], &proxy, "Clash").unwrap();
+
+            let error = delete_profile("clash").unwrap_err();
+            assert_eq!(
+                error,
+                DeleteProfileError::InUse {
+                    profile: "clash".to_string(),
+                    software: vec!["Git".to_string()],
+                    message: tr!("profile_in_use", name = "clash", software = "Git").to_string(),
+                }
+            );
+            let json = serde_json::to_value(&error).unwrap();
+            assert_eq!(json["kind"], "in_use");
+            assert_eq!(json["software"], serde_json::json!(["Git"]));
+            assert!(find_profile(&load_user_config(), "Clash").is_some());
+
+            let error = serde_json::to_value(delete_profile("Office").unwrap_err()).unwrap();
+            assert_eq!(error["kind"], "failed");
+            assert_eq!(error["message_key"], "profile_not_found");
+            assert_eq!(error["params"]["name"], "Office");
+
+            // 改名后已应用记录跟随新名称，仍然不能删除
+            let renamed = ProxyProfile {
+                name: "Office".to_string(),
+                ..profile("127.0.0.1", 7890)
+            };
+            update_profile("Clash", renamed).unwrap();
+            assert_eq!(applied_software_of("office"), ["Git"]);
+            assert!(matches!(
+                delete_profile("Office"),
+                Err(DeleteProfileError::InUse { .. })
+            ));
+        });
+    }
 }
@@ -0,0 +1,133 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+use crate::applied_state;
+use crate::config_manager::ApplyResult;
+use crate::i18n::Message;
+
+/// 批量操作中每个软件开始和完成时发送的事件，载荷为 ApplyProgress
+pub const APPLY_PROGRESS_EVENT: &str = "apply-progress";
+
+/// 后台批量操作完成时发送的事件，载荷为 BatchComplete
+pub const BATCH_COMPLETE_EVENT: &str = "apply-batch-complete";
+
+/// 单个软件的处理状态
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressStatus {
+    Started,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApplyProgress {
+    pub batch_id: String,
+    pub software: String,
+    pub index: usize, // 从 0 开始
+    pub total: usize,
+    pub status: ProgressStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchComplete {
+    pub batch_id: String,
+    pub results: Vec<ApplyResult>,
+    pub error: Option<Message>, // 整个批次失败时的原因（此时 results 为空）
+}
+
+/// 当前线程正在执行的后台批次，同步命令没有批次，不发送进度事件
+struct Reporter {
+    app: AppHandle,
+    batch_id: String,
+}
+
+thread_local! {
+    static REPORTER: RefCell<Option<Reporter>> = const { RefCell::new(None) };
+}
+
+/// 批次的执行结果
+type BatchOutcome = Result<Vec<ApplyResult>, Message>;
+
+/// 批次 ID -> 结果（None 表示仍在执行），结果被读取后移除
+static BATCH_RESULTS: Mutex<BTreeMap<String, Option<BatchOutcome>>> = Mutex::new(BTreeMap::new());
+
+static NEXT_BATCH: AtomicU64 = AtomicU64::new(1);
+
+/// 在后台线程执行批量操作并立即返回批次 ID：执行期间发送进度事件，完成后发送完成事件并保存结果
+pub fn start_batch(app: AppHandle, run: impl FnOnce() -> BatchOutcome + Send + 'static) -> String {
+    let batch_id = format!(
+        "{}-{}",
+        applied_state::now_secs(),
+        NEXT_BATCH.fetch_add(1, Ordering::SeqCst)
+    );
+    BATCH_RESULTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(batch_id.clone(), None);
+
+    let id = batch_id.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        REPORTER.with(|reporter| {
+            *reporter.borrow_mut() = Some(Reporter {
+                app: app.clone(),
+                batch_id: id.clone(),
+            })
+        });
+        let outcome = run();
+        REPORTER.with(|reporter| reporter.borrow_mut().take());
+
+        let (results, error) = match &outcome {
+            Ok(results) => (results.clone(), None),
+            Err(e) => (vec![], Some(e.clone())),
+        };
+        BATCH_RESULTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id.clone(), Some(outcome));
+        let _ = app.emit(
+            BATCH_COMPLETE_EVENT,
+            BatchComplete {
+                batch_id: id,
+                results,
+                error,
+            },
+        );
+    });
+    batch_id
+}
+
+/// 读取批次结果：仍在执行时返回 None，读取后结果被移除
+pub fn get_batch_result(batch_id: &str) -> Result<Option<Vec<ApplyResult>>, Message> {
+    let mut batches = BATCH_RESULTS.lock().unwrap_or_else(|e| e.into_inner());
+    match batches.remove(batch_id) {
+        None => Err(tr!("batch_not_found", id = batch_id)),
+        Some(None) => {
+            batches.insert(batch_id.to_string(), None);
+            Ok(None)
+        }
+        Some(Some(outcome)) => outcome.map(Some),
+    }
+}
+
+/// 报告当前批次中一个软件的状态（不在后台批次中时不做任何事）
+pub fn report(software: &str, index: usize, total: usize, status: ProgressStatus) {
+    REPORTER.with(|reporter| {
+        if let Some(reporter) = reporter.borrow().as_ref() {
+            let _ = reporter.app.emit(
+                APPLY_PROGRESS_EVENT,
+                ApplyProgress {
+                    batch_id: reporter.batch_id.clone(),
+                    software: software.to_string(),
+                    index,
+                    total,
+                    status,
+                },
+            );
+        }
+    });
+}
@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::applied_state;
+use crate::i18n::Message;
+use crate::paths;
+use crate::profile_manager::{
+    self, CustomSoftware, ProxyProfile, Scene, SoftwareProxyMapping, UserConfig,
+};
+
+/// 迁移包中保存设置的文件
+const SETTINGS_ENTRY: &str = "settings.json";
+
+/// 迁移包格式版本
+const SETTINGS_VERSION: u32 = 1;
+
+/// 导出时可选包含的内容（配置组和映射总是包含）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    #[serde(default)]
+    pub include_custom_software: bool,
+    #[serde(default)]
+    pub include_scenes: bool,
+}
+
+/// 迁移包中的设置：不包含已应用状态和备份，这些只对原来的机器有意义
+#[derive(Debug, Serialize, Deserialize)]
+struct TransferSettings {
+    version: u32,
+    profiles: Vec<ProxyProfile>,
+    mappings: Vec<SoftwareProxyMapping>,
+    #[serde(default)]
+    custom_software: Vec<CustomSoftware>, // 配置路径在用户目录下时写成 ~/...
+    #[serde(default)]
+    scenes: Vec<Scene>,
+}
+
+/// 导入迁移包的结果
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsImport {
+    pub config: UserConfig,
+    pub skipped: Vec<String>,       // 已存在或校验失败而跳过的项（附原因）
+    pub missing_paths: Vec<String>, // 本机上配置文件不存在的自定义软件（仍会导入）
+}
+
+/// 用户目录下的路径改写为 ~/...（使用 / 分隔），其他路径原样保留
+fn to_home_relative(path: &str) -> String {
+    let Some(home) = paths::home_dir() else {
+        return path.to_string();
+    };
+    match Path::new(path).strip_prefix(&home) {
+        Ok(rest) => {
+            let parts: Vec<String> = rest
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            format!("~/{}", parts.join("/"))
+        }
+        Err(_) => path.to_string(),
+    }
+}
+
+/// 把 ~/... 重新定位到本机的用户目录
+fn from_home_relative(path: &str) -> String {
+    let Some(rest) = path.strip_prefix("~/") else {
+        return path.to_string();
+    };
+    let Some(home) = paths::home_dir() else {
+        return path.to_string();
+    };
+    let resolved: PathBuf = rest.split('/').fold(home, |dir, part| dir.join(part));
+    resolved.to_string_lossy().into_owned()
+}
+
+/// 导出迁移包（zip），返回文件路径；未指定路径时保存到系统临时目录
+/// 只导出设置，导入时不会自动应用
+pub fn export_settings(
+    options: ExportOptions,
+    destination: Option<String>,
+) -> Result<String, Message> {
+    let config = profile_manager::load_user_config();
+    let settings = TransferSettings {
+        version: SETTINGS_VERSION,
+        profiles: config.profiles,
+        mappings: config.mappings,
+        custom_software: match options.include_custom_software {
+            true => config
+                .custom_software
+                .into_iter()
+                .map(|software| CustomSoftware {
+                    config_path: to_home_relative(&software.config_path),
+                    ..software
+                })
+                .collect(),
+            false => vec![],
+        },
+        scenes: match options.include_scenes {
+            true => config.scenes,
+            false => vec![],
+        },
+    };
+    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+
+    let bundle_path = match destination {
+        Some(path) => PathBuf::from(path),
+        None => std::env::temp_dir().join(format!(
+            "proxy-manager-settings-{}.zip",
+            applied_state::now_secs()
+        )),
+    };
+    let file = File::create(&bundle_path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(SETTINGS_ENTRY, options)
+        .map_err(|e| e.to_string())?;
+    zip.write_all(content.as_bytes())
+        .map_err(|e| e.to_string())?;
+    zip.finish().map_err(|e| e.to_string())?;
+
+    Ok(bundle_path.to_string_lossy().into_owned())
+}
+
+/// 读取迁移包中的设置
+fn read_bundle(path: &Path) -> Result<TransferSettings, Message> {
+    let file = File::open(path).map_err(|e| tr!("settings_bundle_invalid", error = e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| tr!("settings_bundle_invalid", error = e))?;
+    let mut entry = archive
+        .by_name(SETTINGS_ENTRY)
+        .map_err(|e| tr!("settings_bundle_invalid", error = e))?;
+    let mut content = String::new();
+    entry
+        .read_to_string(&mut content)
+        .map_err(|e| tr!("settings_bundle_invalid", error = e))?;
+    let settings: TransferSettings =
+        serde_json::from_str(&content).map_err(|e| tr!("settings_bundle_invalid", error = e))?;
+    if settings.version > SETTINGS_VERSION {
+        return Err(tr!("settings_bundle_version", version = settings.version));
+    }
+    Ok(settings)
+}
+
+/// 导入迁移包：合并到当前配置，同名的配置组、自定义软件和场景保留本机的版本，
+/// 自定义软件的 ~/ 路径重新定位到本机用户目录。只保存设置，不会开启任何代理
+pub fn import_settings(path: &str) -> Result<SettingsImport, Message> {
+    let settings = read_bundle(Path::new(path))?;
+    let mut skipped = Vec::new();
+    let mut missing_paths = Vec::new();
+
+    let config = profile_manager::update_user_config(|config| {
+        for profile in settings.profiles {
+            let name = profile.name.clone();
+            match profile_manager::validate_profile(profile) {
+                Ok(_) if profile_manager::find_profile(config, &name).is_some() => {
+                    skipped.push(tr!("import_item_exists", name = name).to_string())
+                }
+                Ok(profile) => config.profiles.push(profile),
+                Err(e) => skipped.push(format!("{}: {}", name, e)),
+            }
+        }
+
+        for mut software in settings.custom_software {
+            software.config_path = from_home_relative(&software.config_path);
+            let exists = config
+                .custom_software
+                .iter()
+                .any(|s| profile_manager::names_eq(&s.name, &software.name));
+            if exists {
+                skipped.push(tr!("import_item_exists", name = software.name).to_string());
+                continue;
+            }
+            if let Err(e) = profile_manager::validate_custom_software(&software) {
+                skipped.push(format!("{}: {}", software.name, e));
+                continue;
+            }
+            if !Path::new(&software.config_path).exists() {
+                missing_paths.push(format!("{}: {}", software.name, software.config_path));
+            }
+            config.custom_software.push(software);
+        }
+
+        // 映射只导入引用的配置组存在的，覆盖本机同一软件的映射
+        for mapping in settings.mappings {
+            if !profile_manager::profile_exists(config, &mapping.profile_name) {
+                skipped.push(
+                    tr!("mapping_profile_not_found", name = mapping.profile_name).to_string(),
+                );
+                continue;
+            }
+            config
+                .mappings
+                .retain(|m| m.software_name != mapping.software_name);
+            config.mappings.push(mapping);
+        }
+
+        for scene in settings.scenes {
+            if config
+                .scenes
+                .iter()
+                .any(|s| profile_manager::names_eq(&s.name, &scene.name))
+            {
+                skipped.push(tr!("import_item_exists", name = scene.name).to_string());
+            } else {
+                config.scenes.push(scene);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(SettingsImport {
+        config,
+        skipped,
+        missing_paths,
+    })
+}
@@ -0,0 +1,177 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config_manager::{self, ProxySettings};
+use crate::i18n::Message;
+use crate::paths;
+
+/// 包装脚本中的标记注释，后接所属软件名称，用于识别和清理本程序生成的脚本
+const SHIM_MARKER: &str = "proxy-manager shim for";
+
+/// 包装脚本目录（需要由用户加入 PATH，且位于真实命令所在目录之前）
+/// 位置: %LOCALAPPDATA%\proxy-manager\shims
+pub fn shim_dir() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("shims"))
+}
+
+/// 命令对应的包装脚本路径：Windows 为 <命令>.cmd，其他系统为同名的 sh 脚本
+fn shim_path(dir: &Path, command: &str) -> PathBuf {
+    if cfg!(target_os = "windows") {
+        dir.join(format!("{}.cmd", command))
+    } else {
+        dir.join(command)
+    }
+}
+
+/// 校验包装的命令名：只能是命令名，不能包含路径或空白
+pub fn validate_command(command: &str) -> Result<(), Message> {
+    if command.is_empty()
+        || command
+            .chars()
+            .any(|c| c == '/' || c == '\\' || c == '"' || c.is_whitespace())
+    {
+        return Err(tr!("invalid_shim_command", command = command));
+    }
+    Ok(())
+}
+
+/// 在 PATH 中查找真正的可执行文件（跳过包装脚本目录，避免包装脚本调用自身）
+fn find_real_executable(command: &str, shim_dir: &Path) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let extensions = if cfg!(target_os = "windows") {
+        std::env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT".to_string())
+    } else {
+        ";".to_string() // 其他系统只有不带扩展名的形式
+    };
+
+    std::env::split_paths(&path)
+        .filter(|dir| dir != shim_dir)
+        .find_map(|dir| {
+            extensions
+                .split(';')
+                .filter(|ext| !ext.is_empty() || !cfg!(target_os = "windows"))
+                .map(|ext| dir.join(format!("{}{}", command, ext)))
+                .find(|candidate| candidate.is_file())
+        })
+}
+
+/// 生成包装脚本：设置代理变量后执行真正的命令，并原样传递参数
+#[cfg(target_os = "windows")]
+fn render_shim(software_name: &str, target: &Path, vars: &[(String, String)]) -> String {
+    let mut script = format!("@echo off\r\nREM {} {}\r\n", SHIM_MARKER, software_name);
+    for (name, value) in vars {
+        // 批处理中 % 需要写成 %%
+        script.push_str(&format!(
+            "set \"{}={}\"\r\n",
+            name,
+            value.replace('%', "%%")
+        ));
+    }
+    script.push_str(&format!("\"{}\" %*\r\n", target.display()));
+    script
+}
+
+/// 生成包装脚本：设置代理变量后执行真正的命令，并原样传递参数
+#[cfg(not(target_os = "windows"))]
+fn render_shim(software_name: &str, target: &Path, vars: &[(String, String)]) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "'\\''"));
+    let mut script = format!("#!/bin/sh\n# {} {}\n", SHIM_MARKER, software_name);
+    for (name, value) in vars {
+        script.push_str(&format!("export {}={}\n", name, quote(value)));
+    }
+    script.push_str(&format!(
+        "exec {} \"$@\"\n",
+        quote(&target.to_string_lossy())
+    ));
+    script
+}
+
+/// 包装脚本所属的软件（不是本程序生成的脚本时返回 None）
+fn shim_owner(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .take(3)
+        .find_map(|line| line.split_once(SHIM_MARKER))
+        .map(|(_, owner)| owner.trim())
+}
+
+/// 为软件的每个命令生成包装脚本，使这些命令使用该软件映射的配置组，而不修改用户环境变量
+/// 同一软件不再包装的命令的旧脚本会被删除
+pub fn write_shims(
+    software_name: &str,
+    commands: &[String],
+    proxy_settings: &ProxySettings,
+) -> Result<Message, Message> {
+    let dir = shim_dir().ok_or_else(|| tr!("shim_dir_unavailable"))?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    // 先找到所有真实命令，避免写入一半后失败
+    let targets = commands
+        .iter()
+        .map(|command| {
+            validate_command(command)?;
+            find_real_executable(command, &dir)
+                .map(|target| (command, target))
+                .ok_or_else(|| tr!("shim_target_not_found", command = command))
+        })
+        .collect::<Result<Vec<_>, Message>>()?;
+
+    remove_shims(software_name, commands)?;
+
+    let vars = config_manager::software_env_vars(software_name, proxy_settings);
+    for (command, target) in &targets {
+        let path = shim_path(&dir, command);
+        fs::write(&path, render_shim(software_name, target, &vars)).map_err(|e| e.to_string())?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut message = tr!(
+        "shims_written",
+        commands = commands.join(", "),
+        dir = dir.display()
+    );
+    let on_path = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).any(|p| p == dir))
+        .unwrap_or(false);
+    if !on_path {
+        message = format!(
+            "{} {}",
+            message,
+            tr!("shim_dir_not_on_path", dir = dir.display())
+        )
+        .into();
+    }
+    Ok(message)
+}
+
+/// 删除软件的包装脚本（keep 中的命令除外），返回删除的数量
+pub fn remove_shims(software_name: &str, keep: &[String]) -> Result<usize, Message> {
+    let Some(dir) = shim_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(0);
+    };
+
+    let mut removed = 0;
+    for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if shim_owner(&content) != Some(software_name) {
+            continue;
+        }
+        let kept = path
+            .file_stem()
+            .map(|stem| keep.iter().any(|c| stem.to_string_lossy() == c.as_str()))
+            .unwrap_or(false);
+        if !kept {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
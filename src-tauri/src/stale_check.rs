@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::applied_state;
+use crate::config_manager;
+use crate::i18n::Message;
+use crate::port_detector::{self, DetectionResult};
+use crate::profile_manager::{self, ProxyProfile};
+
+/// 已应用的代理端口与关联 VPN 实际监听端口不一致的软件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaleReport {
+    pub software: String,
+    pub profile: String,
+    pub vpn: String,
+    pub port_type: String, // "http" 或 "socks"
+    pub old_port: u16,
+    pub new_port: Option<u16>, // 检测到的新端口，无法确定时为空
+    pub config_path: Option<String>,
+}
+
+/// 软件实际写入的端口类型和端口（与应用时的选择规则一致）
+fn written_port(software: &str, profile: &ProxyProfile) -> (&'static str, u16) {
+    match profile.socks_port {
+        Some(port) if config_manager::prefers_socks(software) => ("socks", port),
+        _ => ("http", profile.port),
+    }
+}
+
+/// 检查已应用的代理是否过期：对关联了 VPN 的配置组重新检测端口，
+/// 写入的端口不在 VPN 当前监听的端口中时视为过期（VPN 未运行时无法判断，跳过）
+pub fn check_stale_proxies() -> Vec<StaleReport> {
+    let config = profile_manager::load_user_config();
+    let applied = applied_state::load_applied_state();
+    let mut detections: HashMap<String, DetectionResult> = HashMap::new();
+
+    applied
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let profile = profile_manager::find_profile(&config, &entry.profile)?;
+            let vpn = profile.vpn.as_ref()?;
+            let detection = detections
+                .entry(vpn.clone())
+                .or_insert_with(|| port_detector::detect_port_by_vpn_name(vpn));
+
+            // 只看真实监听的端口（pid 为 0 的是未运行时给出的默认端口）
+            let listening: Vec<u16> = detection
+                .ports
+                .iter()
+                .filter(|p| p.pid != 0)
+                .map(|p| p.port)
+                .collect();
+            let (port_type, old_port) = written_port(&entry.software, profile);
+            if listening.is_empty() || listening.contains(&old_port) {
+                return None;
+            }
+
+            let new_port = port_detector::select_detected_port(&detection.ports, port_type)
+                .ok()
+                .filter(|p| p.pid != 0)
+                .map(|p| p.port);
+
+            Some(StaleReport {
+                software: entry.software.clone(),
+                profile: profile.name.clone(),
+                vpn: vpn.clone(),
+                port_type: port_type.to_string(),
+                old_port,
+                new_port,
+                config_path: config_manager::software_config_path(&config, &entry.software),
+            })
+        })
+        .collect()
+}
+
+/// 用检测到的新端口更新配置组，返回需要重新应用的软件
+pub fn update_stale_profiles(reports: &[StaleReport]) -> Result<Vec<String>, Message> {
+    let mut software = Vec::new();
+
+    profile_manager::update_user_config(|config| {
+        for report in reports {
+            let Some(new_port) = report.new_port else {
+                continue;
+            };
+            if let Some(profile) = config
+                .profiles
+                .iter_mut()
+                .find(|p| profile_manager::names_eq(&p.name, &report.profile))
+            {
+                if report.port_type == "socks" {
+                    profile.socks_port = Some(new_port);
+                } else {
+                    profile.port = new_port;
+                }
+                profile_manager::seed_profile_bypass(profile);
+                software.push(report.software.clone());
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(software)
+}
@@ -0,0 +1,77 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::i18n::Message;
+use crate::{applied_state, config_manager, error_report, paths};
+
+type Bundle = ZipWriter<File>;
+
+/// 导出用于求助的诊断包：脱敏后的用户配置、备份目录和错误日志，返回 zip 文件路径
+/// 位置: 系统临时目录下的 proxy-manager-support-<时间戳>.zip
+pub fn export_support_bundle() -> Result<String, Message> {
+    let bundle_path = std::env::temp_dir().join(format!(
+        "proxy-manager-support-{}.zip",
+        applied_state::now_secs()
+    ));
+    let file = File::create(&bundle_path).map_err(|e| e.to_string())?;
+    let mut zip = ZipWriter::new(file);
+
+    add_file(&mut zip, &paths::user_config_path(), "user_config.json")?;
+
+    if let Some(backup_dir) = config_manager::get_backup_dir().filter(|dir| dir.is_dir()) {
+        add_dir(&mut zip, &backup_dir, "backups")?;
+    }
+
+    if let Some(log_path) = error_report::get_log_path() {
+        add_file(&mut zip, &log_path, "error.log")?;
+        add_file(
+            &mut zip,
+            &log_path.with_extension("log.old"),
+            "error.log.old",
+        )?;
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(bundle_path.to_string_lossy().into_owned())
+}
+
+/// 写入单个文件（不存在时跳过），文本内容先隐藏令牌和密码
+fn add_file(zip: &mut Bundle, path: &Path, name: &str) -> Result<(), Message> {
+    let Ok(bytes) = fs::read(path) else {
+        return Ok(());
+    };
+    let content = match String::from_utf8(bytes) {
+        Ok(text) => config_manager::redact_secrets(&text).into_bytes(),
+        Err(e) => e.into_bytes(),
+    };
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    zip.start_file(name, options).map_err(|e| e.to_string())?;
+    zip.write_all(&content).map_err(|e| e.to_string().into())
+}
+
+/// 递归写入目录下的所有文件，压缩包内使用 / 分隔的相对路径
+fn add_dir(zip: &mut Bundle, dir: &Path, prefix: &str) -> Result<(), Message> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let name = format!("{}/{}", prefix, file_name.to_string_lossy());
+        if path.is_dir() {
+            add_dir(zip, &path, &name)?;
+        } else {
+            add_file(zip, &path, &name)?;
+        }
+    }
+    Ok(())
+}
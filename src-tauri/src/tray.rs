@@ -0,0 +1,166 @@
+use tauri::{
+    menu::{CheckMenuItem, IsMenuItem, Menu, MenuItem, Submenu},
+    AppHandle, Emitter, Wry,
+};
+
+use crate::auto_revert;
+use crate::config_watcher;
+use crate::error_report;
+use crate::history::{self, OperationKind};
+use crate::profile_manager::{self, UserConfig};
+
+/// 托盘图标 ID，重建菜单时用于查找托盘
+pub const TRAY_ID: &str = "main";
+
+/// 配置组菜单项的 ID 前缀，后接配置组名称
+const PROFILE_ITEM_PREFIX: &str = "profile:";
+
+/// 场景菜单项的 ID 前缀，后接场景名称
+const SCENE_ITEM_PREFIX: &str = "scene:";
+
+/// 通过托盘切换配置组后发送的事件，载荷为各软件的 ApplyResult
+const PROFILE_SWITCHED_EVENT: &str = "tray-profile-switched";
+
+/// 通过托盘应用场景后发送的事件，载荷为各软件的 ApplyResult
+const SCENE_APPLIED_EVENT: &str = "tray-scene-applied";
+
+/// 所有映射使用的同一个配置组（映射为空或使用了不同配置组时返回 None）
+fn active_profile(config: &UserConfig) -> Option<&str> {
+    let first = config.mappings.first()?;
+    config
+        .mappings
+        .iter()
+        .all(|m| m.profile_name == first.profile_name)
+        .then_some(first.profile_name.as_str())
+}
+
+/// 创建托盘菜单：显示窗口、切换配置组（勾选当前使用的配置组）、应用场景、退出
+pub fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let config = profile_manager::load_user_config();
+    let active = active_profile(&config);
+
+    let mut profile_items = config
+        .profiles
+        .iter()
+        .map(|profile| {
+            CheckMenuItem::with_id(
+                app,
+                format!("{}{}", PROFILE_ITEM_PREFIX, profile.name),
+                &profile.name,
+                true,
+                active == Some(profile.name.as_str()),
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    // 直连放在最后，以单独的名称显示
+    if !config.mappings.is_empty() {
+        profile_items.push(CheckMenuItem::with_id(
+            app,
+            format!("{}{}", PROFILE_ITEM_PREFIX, profile_manager::DIRECT_PROFILE),
+            tr!("tray_direct"),
+            true,
+            active == Some(profile_manager::DIRECT_PROFILE),
+            None::<&str>,
+        )?);
+    }
+    let profile_refs: Vec<&dyn IsMenuItem<Wry>> = profile_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    let profiles_menu = Submenu::with_items(
+        app,
+        tr!("tray_switch_profile"),
+        !profile_refs.is_empty(),
+        &profile_refs,
+    )?;
+
+    let scene_items = config
+        .scenes
+        .iter()
+        .map(|scene| {
+            MenuItem::with_id(
+                app,
+                format!("{}{}", SCENE_ITEM_PREFIX, scene.name),
+                &scene.name,
+                true,
+                None::<&str>,
+            )
+        })
+        .collect::<tauri::Result<Vec<_>>>()?;
+    let scene_refs: Vec<&dyn IsMenuItem<Wry>> = scene_items
+        .iter()
+        .map(|item| item as &dyn IsMenuItem<Wry>)
+        .collect();
+    let scenes_menu = Submenu::with_items(
+        app,
+        tr!("tray_apply_scene"),
+        !scene_refs.is_empty(),
+        &scene_refs,
+    )?;
+
+    let show_item = MenuItem::with_id(app, "show", tr!("tray_show"), true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", tr!("tray_quit"), true, None::<&str>)?;
+    Menu::with_items(app, &[&show_item, &profiles_menu, &scenes_menu, &quit_item])
+}
+
+/// 重新生成托盘菜单（配置组或映射变化后调用）
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            let _ = tray.set_menu(Some(menu));
+        }
+        Err(e) => error_report::log_error(&format!("更新托盘菜单失败: {}", e)),
+    }
+}
+
+/// 处理配置组和场景菜单项（其他 ID 忽略）
+pub fn handle_menu_event(app: &AppHandle, id: &str) {
+    // 写入配置文件可能较慢，不阻塞托盘事件循环
+    let app = app.clone();
+    if let Some(profile_name) = id.strip_prefix(PROFILE_ITEM_PREFIX) {
+        let profile_name = profile_name.to_string();
+        std::thread::spawn(move || switch_profile(&app, &profile_name));
+    } else if let Some(scene_name) = id.strip_prefix(SCENE_ITEM_PREFIX) {
+        let scene_name = scene_name.to_string();
+        std::thread::spawn(move || apply_scene(&app, &scene_name));
+    }
+}
+
+/// 把所有映射切换到指定配置组并重新应用，完成后通知前端并更新菜单的勾选状态
+fn switch_profile(app: &AppHandle, profile_name: &str) {
+    match profile_manager::set_all_mappings_profile(profile_name) {
+        Ok(config) => {
+            let software: Vec<String> = config
+                .mappings
+                .iter()
+                .map(|m| m.software_name.clone())
+                .collect();
+            auto_revert::cancel(&software);
+            let results = crate::apply_mappings(&config.mappings);
+            history::record(OperationKind::Enable, None, &results);
+            let _ = app.emit(config_watcher::CONFIG_CHANGED_EVENT, config);
+            let _ = app.emit(PROFILE_SWITCHED_EVENT, results);
+        }
+        Err(e) => error_report::report_error(e.as_str()),
+    }
+    refresh(app);
+}
+
+/// 应用场景，完成后通知前端并更新菜单的勾选状态
+fn apply_scene(app: &AppHandle, scene_name: &str) {
+    match crate::activate_scene(scene_name) {
+        Ok(results) => {
+            let _ = app.emit(
+                config_watcher::CONFIG_CHANGED_EVENT,
+                profile_manager::load_user_config(),
+            );
+            let _ = app.emit(SCENE_APPLIED_EVENT, results);
+        }
+        Err(e) => error_report::report_error(e.as_str()),
+    }
+    refresh(app);
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::http_fetch;
+use crate::i18n::Message;
+
+/// 发现新版本时发送的事件（只在启动时自动检查），载荷为 UpdateInfo
+pub const UPDATE_AVAILABLE_EVENT: &str = "update-available";
+
+/// 最新发布版本的查询接口
+const LATEST_RELEASE_API: &str =
+    "https://api.github.com/repos/youjizi/proxy-manager/releases/latest";
+
+/// 请求超时（秒）
+const REQUEST_TIMEOUT_SECS: u32 = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateInfo {
+    pub current: String,
+    pub latest: String,
+    pub available: bool,
+    pub notes_url: String, // 发布说明页面
+}
+
+/// 接口返回的发布信息中需要的字段
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    html_url: String,
+}
+
+/// 解析版本号，如 "v1.2.3" -> [1, 2, 3]（预发布后缀如 "-beta" 忽略）
+fn parse_version(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches(['v', 'V'])
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse::<u64>().unwrap_or(0))
+        .collect()
+}
+
+/// latest 是否比 current 新（缺少的部分按 0 比较，1.2 与 1.2.0 相同）
+fn is_newer(latest: &str, current: &str) -> bool {
+    let (mut latest, mut current) = (parse_version(latest), parse_version(current));
+    let len = latest.len().max(current.len());
+    latest.resize(len, 0);
+    current.resize(len, 0);
+    latest > current
+}
+
+/// 查询最新的发布版本
+fn fetch_latest_release() -> Result<Release, Message> {
+    let content = http_fetch::get(
+        LATEST_RELEASE_API,
+        REQUEST_TIMEOUT_SECS,
+        &["Accept: application/vnd.github+json"],
+    )
+    .map_err(|e| tr!("update_check_failed", error = e))?;
+    serde_json::from_slice(&content).map_err(|e| tr!("update_check_failed", error = e))
+}
+
+/// 检查是否有新版本
+pub fn check_for_update() -> Result<UpdateInfo, Message> {
+    let current = env!("CARGO_PKG_VERSION").to_string();
+    let release = fetch_latest_release()?;
+    let latest = release.tag_name.trim_start_matches(['v', 'V']).to_string();
+    Ok(UpdateInfo {
+        available: is_newer(&latest, &current),
+        current,
+        latest,
+        notes_url: release.html_url,
+    })
+}
+
+/// 启动时在后台检查一次，有新版本时通知前端（检查失败时不打扰用户）
+pub fn check_in_background(app: AppHandle) {
+    std::thread::spawn(move || {
+        if let Ok(info) = check_for_update() {
+            if info.available {
+                let _ = app.emit(UPDATE_AVAILABLE_EVENT, info);
+            }
+        }
+    });
+}
@@ -0,0 +1,395 @@
+//! 在临时用户目录中运行的端到端流程：开启、关闭、重置代理，以及配置和备份的迁移
+//! 每个测试使用独立的沙盒（Environment::sandbox），不接触真实的用户配置
+
+use proxy_manager_lib::applied_state::AppliedState;
+use proxy_manager_lib::config_manager::{self, ApplyAction, ProxySettings};
+use proxy_manager_lib::paths::{self, Environment};
+use proxy_manager_lib::profile_manager;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+const GITCONFIG: &str = "[user]\n\tname = Tester\n";
+const NPMRC: &str = "registry=https://registry.npmjs.org/\n";
+const VSCODE_SETTINGS: &str = "{\n  \"editor.fontSize\": 14\n}\n";
+
+/// VSCode 用户设置相对于用户目录的位置
+const VSCODE_SETTINGS_PATH: &str = if cfg!(target_os = "windows") {
+    "AppData/Roaming/Code/User/settings.json"
+} else if cfg!(target_os = "macos") {
+    "Library/Application Support/Code/User/settings.json"
+} else {
+    ".config/Code/User/settings.json"
+};
+
+/// 临时用户目录及其中的沙盒环境
+struct Sandbox {
+    home: TempDir,
+    env: Environment,
+}
+
+impl Sandbox {
+    fn new() -> Self {
+        let home = tempfile::tempdir().expect("create temp home");
+        let env = Environment::sandbox(home.path());
+        Sandbox { home, env }
+    }
+
+    fn path(&self, relative: &str) -> PathBuf {
+        self.home.path().join(relative)
+    }
+
+    fn write(&self, relative: &str, content: &str) -> PathBuf {
+        let path = self.path(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn backup(&self, name: &str) -> PathBuf {
+        self.env.app_data_dir().unwrap().join("backups").join(name)
+    }
+
+    fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.env.run(f)
+    }
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| panic!("read {}: {}", path.display(), e))
+}
+
+fn software(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn proxy(address: &str) -> ProxySettings {
+    ProxySettings {
+        http_proxy: address.to_string(),
+        https_proxy: address.to_string(),
+        ..ProxySettings::default()
+    }
+}
+
+fn applied_status() -> AppliedState {
+    config_manager::load_applied_status(&profile_manager::load_user_config())
+}
+
+fn assert_all_succeeded(results: &[config_manager::ApplyResult]) {
+    for result in results {
+        assert!(result.success, "{}: {}", result.software, result.message);
+    }
+}
+
+#[test]
+fn enable_then_disable_restores_original_bytes() {
+    let sandbox = Sandbox::new();
+    let gitconfig = sandbox.write(".gitconfig", GITCONFIG);
+    let npmrc = sandbox.write(".npmrc", NPMRC);
+    let vscode = sandbox.write(VSCODE_SETTINGS_PATH, VSCODE_SETTINGS);
+    let list = software(&["Git", "npm", "VSCode"]);
+
+    sandbox.run(|| {
+        let results =
+            config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(
+        read(&gitconfig),
+        "[user]\n\tname = Tester\n[http]\n\tproxy = http://127.0.0.1:7890\n\
+         [https]\n\tproxy = http://127.0.0.1:7890\n"
+    );
+    assert_eq!(
+        read(&npmrc),
+        "registry=https://registry.npmjs.org/\nproxy=http://127.0.0.1:7890\n\
+         https-proxy=http://127.0.0.1:7890"
+    );
+    assert_eq!(
+        read(&vscode),
+        "{\n  \"editor.fontSize\": 14,\n  \"http.proxy\": \"http://127.0.0.1:7890\",\n  \
+         \"http.noProxy\": [\n    \"localhost\",\n    \"127.0.0.1\",\n    \"::1\"\n  ]\n}\n"
+    );
+    let applied = sandbox.run(applied_status);
+    let mut recorded: Vec<&str> = applied
+        .entries
+        .iter()
+        .map(|e| e.software.as_str())
+        .collect();
+    recorded.sort();
+    assert_eq!(recorded, ["Git", "VSCode", "npm"]);
+
+    sandbox.run(|| {
+        let results = config_manager::disable_proxy(&list, false, None).unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(read(&gitconfig), GITCONFIG);
+    assert_eq!(read(&npmrc), NPMRC);
+    assert_eq!(read(&vscode), VSCODE_SETTINGS);
+    assert!(sandbox.run(applied_status).entries.is_empty());
+}
+
+#[test]
+fn reset_returns_to_first_backup_after_switching_profiles() {
+    let sandbox = Sandbox::new();
+    let gitconfig = sandbox.write(".gitconfig", GITCONFIG);
+    let list = software(&["Git"]);
+
+    sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        config_manager::enable_proxy(&list, &proxy("http://10.0.0.2:3128"), "Office").unwrap();
+    });
+    assert_eq!(
+        read(&gitconfig),
+        "[user]\n\tname = Tester\n[http]\n\tproxy = http://10.0.0.2:3128\n\
+         [https]\n\tproxy = http://10.0.0.2:3128\n"
+    );
+    // 初始备份只在第一次开启时创建，当前备份是切换前的配置
+    assert_eq!(read(&sandbox.backup("Git.original.backup")), GITCONFIG);
+    assert!(read(&sandbox.backup("Git.current.backup")).contains("127.0.0.1:7890"));
+
+    sandbox.run(|| {
+        let results = config_manager::reset_to_original(&list, false).unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(read(&gitconfig), GITCONFIG);
+}
+
+/// 其他程序（如 IT 部门的脚本）写入的 Git 代理
+const GITCONFIG_IT_PROXY: &str =
+    "[user]\n\tname = Tester\n[http]\n\tproxy = http://it.example.com:8080\n";
+
+#[test]
+fn disable_restores_third_party_git_proxy() {
+    let sandbox = Sandbox::new();
+    let gitconfig = sandbox.write(".gitconfig", GITCONFIG_IT_PROXY);
+    let list = software(&["Git"]);
+
+    sandbox.run(|| {
+        let results =
+            config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert!(!read(&gitconfig).contains("it.example.com"));
+    assert!(sandbox.backup("Git.third_party.json").exists());
+
+    sandbox.run(|| {
+        let results = config_manager::disable_proxy(&list, false, None).unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(read(&gitconfig), GITCONFIG_IT_PROXY);
+    assert!(!sandbox.backup("Git.third_party.json").exists());
+}
+
+#[test]
+fn reset_restores_third_party_git_proxy_set_after_first_backup() {
+    let sandbox = Sandbox::new();
+    let gitconfig = sandbox.write(".gitconfig", GITCONFIG);
+    let list = software(&["Git"]);
+
+    sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        config_manager::disable_proxy(&list, false, None).unwrap();
+    });
+    // 初始备份早于其他程序写入代理的时间
+    fs::write(&gitconfig, GITCONFIG_IT_PROXY).unwrap();
+    sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+    });
+    assert_eq!(read(&sandbox.backup("Git.original.backup")), GITCONFIG);
+
+    sandbox.run(|| {
+        let results = config_manager::reset_to_original(&list, false).unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(read(&gitconfig), GITCONFIG_IT_PROXY);
+}
+
+#[test]
+fn repeated_enable_leaves_mtime_untouched() {
+    let sandbox = Sandbox::new();
+    let gitconfig = sandbox.write(".gitconfig", GITCONFIG);
+    let list = software(&["Git"]);
+    sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+    });
+    // 修改时间设为过去，重写文件一定会改变它
+    let earlier = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options()
+        .write(true)
+        .open(&gitconfig)
+        .unwrap()
+        .set_modified(earlier)
+        .unwrap();
+    let current_backup = read(&sandbox.backup("Git.current.backup"));
+
+    let results = sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap()
+    });
+    assert_all_succeeded(&results);
+    assert_eq!(results[0].action, ApplyAction::NoChange);
+    assert_eq!(
+        fs::metadata(&gitconfig).unwrap().modified().unwrap(),
+        earlier
+    );
+    assert_eq!(read(&sandbox.backup("Git.current.backup")), current_backup);
+}
+
+#[test]
+fn disable_removes_config_created_by_enable() {
+    let sandbox = Sandbox::new();
+    let npmrc = sandbox.path(".npmrc");
+    let list = software(&["npm"]);
+
+    sandbox.run(|| {
+        let results =
+            config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(
+        read(&npmrc),
+        "proxy=http://127.0.0.1:7890\nhttps-proxy=http://127.0.0.1:7890"
+    );
+
+    sandbox.run(|| {
+        let results = config_manager::disable_proxy(&list, false, None).unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert!(!npmrc.exists());
+}
+
+#[test]
+fn sandbox_variables_select_config_location() {
+    let sandbox = Sandbox::new();
+    let custom = sandbox.write("custom/gitconfig", GITCONFIG);
+    let env = sandbox
+        .env
+        .clone()
+        .with_var("GIT_CONFIG_GLOBAL", custom.as_os_str());
+    let list = software(&["Git"]);
+
+    env.run(|| {
+        let results =
+            config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        assert_all_succeeded(&results);
+        assert_eq!(
+            results[0].changed_path.as_deref(),
+            Some(custom.to_string_lossy().as_ref())
+        );
+    });
+    assert!(read(&custom).contains("proxy = http://127.0.0.1:7890"));
+    assert!(!sandbox.path(".gitconfig").exists());
+}
+
+#[test]
+fn legacy_user_config_is_migrated() {
+    let sandbox = Sandbox::new();
+    let legacy_content = "{\n  \"profiles\": []\n}";
+    let legacy = sandbox.write(".proxy-manager/user_config.json", legacy_content);
+
+    let migrated = sandbox.run(paths::migrate_legacy_config).unwrap();
+    assert!(migrated);
+    let config_path = sandbox.env.user_config_path();
+    assert_eq!(read(&config_path), legacy_content);
+    assert!(!legacy.exists());
+    assert_eq!(
+        read(&sandbox.path(".proxy-manager/user_config.json.migrated")),
+        legacy_content
+    );
+    assert_eq!(
+        read(&sandbox.path(".proxy-manager/MOVED.txt")),
+        format!("{}\n", config_path.display())
+    );
+
+    // 迁移只进行一次
+    assert!(!sandbox.run(paths::migrate_legacy_config).unwrap());
+}
+
+#[test]
+fn repair_reseeds_missing_original_backup() {
+    let sandbox = Sandbox::new();
+    sandbox.write(".gitconfig", GITCONFIG);
+    sandbox.write(".npmrc", "proxy=http://127.0.0.1:7890\n");
+    // 只检查已有的备份目录
+    fs::create_dir_all(sandbox.backup("")).unwrap();
+
+    let report = sandbox.run(config_manager::repair_backups);
+    // 不含代理设置的配置补建初始备份；含有代理设置的只报告，避免把代理当作初始状态
+    assert_eq!(read(&sandbox.backup("Git.original.backup")), GITCONFIG);
+    assert!(!sandbox.backup("npm.original.backup").exists());
+    assert_eq!(report.len(), 2, "{:?}", report);
+}
+
+#[test]
+fn zero_byte_original_backup_is_not_restored() {
+    let sandbox = Sandbox::new();
+    let gitconfig = sandbox.write(".gitconfig", GITCONFIG);
+    let list = software(&["Git"]);
+    sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+    });
+    let enabled = read(&gitconfig);
+    fs::write(sandbox.backup("Git.original.backup"), "").unwrap();
+
+    let results = sandbox.run(|| config_manager::reset_to_original(&list, false).unwrap());
+    assert!(!results[0].success, "{}", results[0].message);
+    assert_eq!(read(&gitconfig), enabled);
+
+    let report = sandbox.run(config_manager::repair_backups);
+    assert!(
+        report
+            .iter()
+            .any(|line| line.contains("Git.original.backup")),
+        "{:?}",
+        report
+    );
+}
+
+#[test]
+fn truncated_current_backup_is_not_restored() {
+    let sandbox = Sandbox::new();
+    let npmrc = sandbox.write(".npmrc", NPMRC);
+    let list = software(&["npm"]);
+    sandbox.run(|| {
+        config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+    });
+    let enabled = read(&npmrc);
+    fs::write(sandbox.backup("npm.current.backup"), &NPMRC[..10]).unwrap();
+
+    let results = sandbox.run(|| config_manager::disable_proxy(&list, false, None).unwrap());
+    assert!(!results[0].success, "{}", results[0].message);
+    assert_eq!(read(&npmrc), enabled);
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+#[test]
+fn idea_config_is_created_and_removed() {
+    let sandbox = Sandbox::new();
+    let root = if cfg!(target_os = "windows") {
+        "AppData/Roaming/JetBrains"
+    } else {
+        "Library/Application Support/JetBrains"
+    };
+    let options = sandbox.path(&format!("{}/IntelliJIdea2024.3/options", root));
+    fs::create_dir_all(&options).unwrap();
+    let list = software(&["IDEA"]);
+
+    sandbox.run(|| {
+        let results =
+            config_manager::enable_proxy(&list, &proxy("http://127.0.0.1:7890"), "Clash").unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert_eq!(
+        read(&options.join("proxy.settings.xml")),
+        "<application>\n  <component name=\"HttpConfigurable\">\n    \
+         <option name=\"USE_HTTP_PROXY\" value=\"true\"/>\n    \
+         <option name=\"PROXY_HOST\" value=\"127.0.0.1\"/>\n    \
+         <option name=\"PROXY_PORT\" value=\"7890\"/>\n  </component>\n</application>"
+    );
+
+    sandbox.run(|| {
+        let results = config_manager::disable_proxy(&list, false, None).unwrap();
+        assert_all_succeeded(&results);
+    });
+    assert!(!options.join("proxy.settings.xml").exists());
+}
@@ -8,19 +8,30 @@ interface VpnConfig {
   process_names: string[];
   default_http_port: number;
   default_socks_port: number;
+  default_bypass?: string[]; // 建议在本地绕过的地址
+  case_sensitive?: boolean; // 进程名匹配是否区分大小写
+}
+
+interface VpnStatus {
+  config: VpnConfig;
+  running: boolean;
+  pids: number[];
+  detected_ports: number[];
 }
 
 interface DetectedPort {
   port: number;
-  port_type: string;
+  port_type: "http" | "socks" | "mixed" | "unknown"; // mixed: 同一端口同时支持 HTTP 和 SOCKS
   process_name: string;
   pid: number;
+  bind_scope: { kind: "loopback" | "any" } | { kind: "specific"; addr: string };
 }
 
 interface DetectionResult {
   success: boolean;
   message: string;
   ports: DetectedPort[];
+  config_file?: string | null;
 }
 
 interface SoftwareConfig {
@@ -28,25 +39,88 @@ interface SoftwareConfig {
   config_type: string;
   enabled: boolean;
   installed: boolean;
+  config_exists?: boolean;
   config_path: string | null;
   is_custom?: boolean;
+  capabilities?: ProxyCapabilities;
+  warning?: string | null;
+}
+
+interface ProxyCapabilities {
+  supports_http: boolean;
+  supports_socks: boolean;
+  supports_pac: boolean;
+  supports_auth: boolean;
+  supports_no_proxy: boolean;
+  supports_https_proxy?: boolean; // https:// 代理地址（与代理之间使用 TLS）
 }
 
 interface ProxyProfile {
   name: string;
   host: string;
   port: number;
+  socks_port?: number | null;
+  vpn?: string | null;
+  bypass?: string[] | null; // 不走代理的地址，null 表示未自定义（使用关联 VPN 的默认列表）
+  proxy_scheme?: ProxyScheme; // 未设置时为 http
+}
+
+type ProxyScheme = "http" | "https" | "socks5" | "socks5h";
+
+interface AppliedEntry {
+  software: string;
+  profile: string;
+  applied_at: number;
+  content_hash: string;
+  config_path?: string | null;
+  relocated?: boolean;
+}
+
+interface AppliedState {
+  entries: AppliedEntry[];
+}
+
+// 删除配置组失败的原因：in_use 时列出仍应用了该配置组的软件
+type DeleteProfileError =
+  | { kind: "in_use"; profile: string; software: string[]; message: string }
+  | ({ kind: "failed" } & CommandError);
+
+interface ProfileSaveResult {
+  config: UserConfig;
+  warnings: string[];
 }
 
 interface SoftwareProxyMapping {
   software_name: string;
   profile_name: string;
+  proxy_localhost?: boolean;
+  no_proxy_override?: string | null;
+  options?: MappingOptions;
+}
+
+interface MappingOptions {
+  strict_ssl?: boolean | null;
+  url_scopes?: string[];
+  env_var_style?: "UppercaseOnly" | "Both" | "All" | null;
+  extra_bypass?: string[];
+  auto_failover?: boolean;
+  use_shims?: boolean; // 环境变量类软件：通过包装脚本只为指定命令设置代理
+  shim_commands?: string[];
+  disable_strategy?: "restore" | "strip"; // 关闭代理时还原备份（默认）还是直接删除代理设置
+}
+
+interface AppError {
+  kind: string;
+  message: string;
+  log_path: string | null;
 }
 
 interface CustomSoftware {
   name: string;
   config_type: string;
   config_path: string;
+  json_keys?: Record<string, string>; // electron-json 类型：键路径 -> 值模板
+  capabilities?: ProxyCapabilities | null; // 未指定时只支持 HTTP 代理
 }
 
 interface UserConfig {
@@ -55,13 +129,39 @@ interface UserConfig {
   custom_software: CustomSoftware[];
 }
 
+interface ApplyResult {
+  software: string;
+  success: boolean;
+  message: string;
+  changed_path: string | null;
+  profile?: string | null;
+  requires_restart?: boolean;
+  action?: "Changed" | "NoChange";
+  verified?: boolean | null;
+  detail?: string | null;
+  message_key?: string | null; // message 的键名，无法识别（如多条消息拼接）时为空
+  params?: Record<string, string>;
+}
+
+// 命令返回的错误：message 为当前语言的文本，message_key 和 params 可用于按键名显示
+interface CommandError {
+  message: string;
+  message_key: string | null;
+  params: Record<string, string>;
+}
+
 interface ClosePreference {
   remember: boolean;
   action: string;
 }
 
+// 命令错误的显示文本（CommandError 等带有 message 的对象，或字符串）
+function errorText(e: unknown): string {
+  return typeof e === "object" && e !== null && "message" in e ? (e as CommandError).message : String(e);
+}
+
 function App() {
-  const [vpnList, setVpnList] = useState<VpnConfig[]>([]);
+  const [vpnList, setVpnList] = useState<VpnStatus[]>([]);
   const [selectedVpn, setSelectedVpn] = useState<string>("");
   const [customVpn, setCustomVpn] = useState<string>("");
   const [detectionResult, setDetectionResult] = useState<DetectionResult | null>(null);
@@ -70,31 +170,39 @@ function App() {
   const [softwareList, setSoftwareList] = useState<SoftwareConfig[]>([]);
   const [selectedSoftware, setSelectedSoftware] = useState<Set<string>>(new Set());
   const [expandedSoftware, setExpandedSoftware] = useState<string | null>(null);
+  const [relocatedSoftware, setRelocatedSoftware] = useState<Set<string>>(new Set());
 
   const [isProxyEnabled, setIsProxyEnabled] = useState(false);
-  const [operationResults, setOperationResults] = useState<string[]>([]);
+  const [operationResults, setOperationResults] = useState<(string | ApplyResult)[]>([]);
   const [isOperating, setIsOperating] = useState(false);
+  const [atomicApply, setAtomicApply] = useState(false); // 有软件失败时回滚整批操作
 
   // 代理配置组相关状态
   const [userConfig, setUserConfig] = useState<UserConfig>({ profiles: [], mappings: [], custom_software: [] });
   const [softwareMappings, setSoftwareMappings] = useState<Map<string, string>>(new Map());
   const [showProfileModal, setShowProfileModal] = useState(false);
   const [editingProfile, setEditingProfile] = useState<ProxyProfile | null>(null);
+  const [editingProfileName, setEditingProfileName] = useState<string>(""); // 编辑前的名称
   const [newProfile, setNewProfile] = useState<ProxyProfile>({ name: "", host: "127.0.0.1", port: 7890 });
 
   // 自定义软件相关状态
   const [showSoftwareModal, setShowSoftwareModal] = useState(false);
   const [newSoftware, setNewSoftware] = useState<CustomSoftware>({ name: "", config_type: "json", config_path: "" });
+  const [jsonKeysText, setJsonKeysText] = useState("");
 
   // 关闭确认对话框相关状态
   const [showCloseModal, setShowCloseModal] = useState(false);
   const [closeAction, setCloseAction] = useState<string>("minimize");
   const [rememberClose, setRememberClose] = useState(false);
 
+  // 安全模式：启动时未执行任何自动操作，配置只读
+  const [safeMode, setSafeMode] = useState(false);
+
   useEffect(() => {
     loadVpnList();
     loadSoftwareList();
     loadUserConfig();
+    invoke<boolean>("is_safe_mode").then(setSafeMode).catch(() => {});
 
     // 监听窗口关闭事件
     const unlisten = listen("close-requested", async () => {
@@ -118,15 +226,63 @@ function App() {
       }
     });
 
+    // 配置文件被外部修改时重新加载
+    const unlistenConfig = listen<UserConfig>("user-config-changed", (event) => {
+      applyUserConfig(event.payload);
+    });
+    const unlistenInvalid = listen<string>("user-config-invalid", (event) => {
+      setOperationResults([event.payload]);
+    });
+
+    // 后台发生未处理的错误（如 panic）时显示错误信息，而不是让窗口直接消失
+    const unlistenError = listen<AppError>("app-error", (event) => {
+      const { message, log_path } = event.payload;
+      setOperationResults([log_path ? `${message} (${log_path})` : message]);
+    });
+
+    // 通过托盘切换配置组后显示各软件的结果
+    const unlistenTraySwitch = listen<ApplyResult[]>("tray-profile-switched", (event) => {
+      setOperationResults(event.payload);
+    });
+
+    // 后台健康检查发现配置组连续失败
+    const unlistenUnhealthy = listen<{ profile: string; failures: number }>("profile-unhealthy", (event) => {
+      const { profile, failures } = event.payload;
+      setOperationResults([`配置组 ${profile} 已连续 ${failures} 次连接失败，请检查代理是否正常`]);
+    });
+
+    // 配置组不可用时，开启了自动切换的软件已切换到其他配置组
+    const unlistenFailover = listen<{ from: string; to: string; results: ApplyResult[] }>("profile-failover", (event) => {
+      setOperationResults(event.payload.results);
+      loadUserConfig();
+    });
+
+    const unlistenSafeMode = listen("safe-mode", () => setSafeMode(true));
+
+    // 启动时自动检查到新版本
+    const unlistenUpdate = listen<{ latest: string; notes_url: string }>("update-available", (event) => {
+      const { latest, notes_url } = event.payload;
+      setOperationResults([`发现新版本 ${latest}，发布说明: ${notes_url}`]);
+    });
+
     return () => {
       unlisten.then(fn => fn());
+      unlistenConfig.then(fn => fn());
+      unlistenInvalid.then(fn => fn());
+      unlistenError.then(fn => fn());
+      unlistenTraySwitch.then(fn => fn());
+      unlistenUnhealthy.then(fn => fn());
+      unlistenFailover.then(fn => fn());
+      unlistenUpdate.then(fn => fn());
+      unlistenSafeMode.then(fn => fn());
     };
   }, []);
 
   async function loadVpnList() {
     try {
-      const list = await invoke<VpnConfig[]>("get_vpn_list");
-      setVpnList(list);
+      const list = await invoke<VpnStatus[]>("get_vpn_list_with_status");
+      // 运行中的 VPN 排在前面
+      setVpnList([...list].sort((a, b) => Number(b.running) - Number(a.running)));
     } catch (e) {
       console.error("Failed to load VPN list:", e);
     }
@@ -138,6 +294,9 @@ function App() {
       setSoftwareList(list);
       const installed = new Set(list.filter((s) => s.installed).map((s) => s.name));
       setSelectedSoftware(installed);
+      // 升级到新版本后配置目录变化的 JetBrains 产品
+      const applied = await invoke<AppliedState>("get_applied_state");
+      setRelocatedSoftware(new Set(applied.entries.filter((e) => e.relocated).map((e) => e.software)));
     } catch (e) {
       console.error("Failed to load software list:", e);
     }
@@ -146,19 +305,28 @@ function App() {
   async function loadUserConfig() {
     try {
       const config = await invoke<UserConfig>("get_user_config");
-      setUserConfig(config);
-
-      // 初始化软件映射
-      const mappings = new Map<string, string>();
-      config.mappings.forEach((m) => {
-        mappings.set(m.software_name, m.profile_name);
-      });
-      setSoftwareMappings(mappings);
+      applyUserConfig(config);
+      // 旧配置中只有大小写或空白不同的重名项
+      const warnings = await invoke<string[]>("get_config_load_status");
+      if (warnings.length > 0) {
+        setOperationResults(warnings);
+      }
     } catch (e) {
       console.error("Failed to load user config:", e);
     }
   }
 
+  function applyUserConfig(config: UserConfig) {
+    setUserConfig(config);
+
+    // 初始化软件映射
+    const mappings = new Map<string, string>();
+    config.mappings.forEach((m) => {
+      mappings.set(m.software_name, m.profile_name);
+    });
+    setSoftwareMappings(mappings);
+  }
+
   async function detectPort() {
     const vpnName = selectedVpn === "custom" ? customVpn : selectedVpn;
     if (!vpnName) return;
@@ -172,7 +340,7 @@ function App() {
     } catch (e) {
       setDetectionResult({
         success: false,
-        message: `检测失败: ${e}`,
+        message: `检测失败: ${errorText(e)}`,
         ports: [],
       });
     } finally {
@@ -192,32 +360,74 @@ function App() {
     try {
       const softwareArray = Array.from(selectedSoftware);
 
+      // 需要管理员权限的配置文件会在最后统一请求一次授权
+      const elevated = await invoke<string[]>("check_elevation_required", {
+        softwareList: softwareArray,
+      });
+      if (
+        elevated.length > 0 &&
+        !window.confirm(`以下软件的配置文件需要管理员权限才能修改，将请求一次授权：\n${elevated.join("\n")}`)
+      ) {
+        return;
+      }
+
       if (isProxyEnabled) {
-        const results = await invoke<string[]>("disable_proxy", {
+        const results = await invoke<ApplyResult[]>("disable_proxy", {
           softwareList: softwareArray,
         });
         setOperationResults(results);
         setIsProxyEnabled(false);
       } else {
         // 使用配置组方式开启代理
+        // 保留已保存映射中的软件级选项
         const mappingsToApply: SoftwareProxyMapping[] = softwareArray.map((name) => ({
+          ...userConfig.mappings.find((m) => m.software_name === name),
           software_name: name,
           profile_name: softwareMappings.get(name) || userConfig.profiles[0]?.name || "",
         }));
 
-        const results = await invoke<string[]>("enable_proxy_with_profiles", {
+        const results = await invoke<ApplyResult[]>("enable_proxy_with_profiles", {
           softwareMappings: mappingsToApply,
+          atomic: atomicApply,
         });
         setOperationResults(results);
         setIsProxyEnabled(true);
       }
     } catch (e) {
-      setOperationResults([`操作失败: ${e}`]);
+      setOperationResults([`操作失败: ${errorText(e)}`]);
     } finally {
       setIsOperating(false);
     }
   }
 
+  async function copyResultsReport() {
+    const results = operationResults.filter((r): r is ApplyResult => typeof r !== "string");
+    try {
+      const report = await invoke<string>("format_results_report", { results });
+      await navigator.clipboard.writeText(report);
+    } catch (e) {
+      console.error("Failed to copy results:", e);
+    }
+  }
+
+  async function openConfigFile(softwareName: string) {
+    try {
+      await invoke("open_config_file", { softwareName });
+    } catch (e) {
+      setOperationResults([`打开文件失败: ${errorText(e)}`]);
+    }
+  }
+
+  async function migrateJetbrainsConfig(softwareName: string) {
+    try {
+      const results = await invoke<ApplyResult[]>("migrate_jetbrains_config", { software: softwareName });
+      setOperationResults(results);
+      await loadSoftwareList();
+    } catch (e) {
+      setOperationResults([`迁移失败: ${errorText(e)}`]);
+    }
+  }
+
   function toggleSoftwareSelection(name: string) {
     const newSet = new Set(selectedSoftware);
     if (newSet.has(name)) {
@@ -239,13 +449,13 @@ function App() {
 
     try {
       const softwareArray = Array.from(selectedSoftware);
-      const results = await invoke<string[]>("reset_proxy", {
+      const results = await invoke<ApplyResult[]>("reset_proxy", {
         softwareList: softwareArray,
       });
       setOperationResults(results);
       setIsProxyEnabled(false);
     } catch (e) {
-      setOperationResults([`重置失败: ${e}`]);
+      setOperationResults([`重置失败: ${errorText(e)}`]);
     } finally {
       setIsOperating(false);
     }
@@ -269,31 +479,42 @@ function App() {
         return;
       }
 
-      if (editingProfile) {
-        // 更新现有配置
-        const updatedProfiles = userConfig.profiles.map((p) =>
-          p.name === editingProfile.name ? profile : p
-        );
-        const newConfig = { ...userConfig, profiles: updatedProfiles };
-        await invoke("save_user_config", { config: newConfig });
-        setUserConfig(newConfig);
-      } else {
-        // 添加新配置
-        const config = await invoke<UserConfig>("add_proxy_profile", { profile });
-        setUserConfig(config);
+      // 非致命的警告（如主机无法解析）不阻止保存，只显示给用户
+      const result = editingProfile
+        ? await invoke<ProfileSaveResult>("update_proxy_profile", {
+            oldName: editingProfileName,
+            profile,
+          })
+        : await invoke<ProfileSaveResult>("add_proxy_profile", { profile });
+      setUserConfig(result.config);
+      if (result.warnings.length > 0) {
+        setOperationResults(result.warnings);
       }
 
       setShowProfileModal(false);
       setEditingProfile(null);
       setNewProfile({ name: "", host: "127.0.0.1", port: 7890 });
     } catch (e) {
-      setOperationResults([`保存失败: ${e}`]);
+      setOperationResults([`保存失败: ${errorText(e)}`]);
     }
   }
 
   async function deleteProfile(profileName: string) {
     try {
-      const config = await invoke<UserConfig>("delete_proxy_profile", { profileName });
+      let config: UserConfig;
+      try {
+        config = await invoke<UserConfig>("delete_proxy_profile", { profileName });
+      } catch (e) {
+        // 配置组仍应用在某些软件上时，确认后先关闭这些软件的代理再删除
+        const error = e as DeleteProfileError;
+        if (error.kind !== "in_use") {
+          throw e;
+        }
+        if (!window.confirm(`配置组 ${profileName} 正在 ${error.software.join("、")} 中使用，删除前将关闭这些软件的代理，是否继续？`)) {
+          return;
+        }
+        config = await invoke<UserConfig>("delete_proxy_profile_force", { profileName });
+      }
       setUserConfig(config);
 
       // 清除使用该配置的映射
@@ -305,7 +526,7 @@ function App() {
       }
       setSoftwareMappings(newMappings);
     } catch (e) {
-      setOperationResults([`删除失败: ${e}`]);
+      setOperationResults([`删除失败: ${errorText(e)}`]);
     }
   }
 
@@ -321,7 +542,7 @@ function App() {
       newMappings.set(softwareName, profileName);
       setSoftwareMappings(newMappings);
     } catch (e) {
-      setOperationResults([`更新映射失败: ${e}`]);
+      setOperationResults([`更新映射失败: ${errorText(e)}`]);
     }
   }
 
@@ -333,12 +554,14 @@ function App() {
 
   function openEditProfileModal(profile: ProxyProfile) {
     setEditingProfile({ ...profile });
+    setEditingProfileName(profile.name);
     setShowProfileModal(true);
   }
 
   // 自定义软件管理函数
   function openAddSoftwareModal() {
     setNewSoftware({ name: "", config_type: "json", config_path: "" });
+    setJsonKeysText("");
     setShowSoftwareModal(true);
   }
 
@@ -353,14 +576,27 @@ function App() {
         return;
       }
 
-      const config = await invoke<UserConfig>("add_custom_software", { software: newSoftware });
+      // electron-json 类型：每行一个 "键路径 = 值模板"
+      const json_keys: Record<string, string> = {};
+      if (newSoftware.config_type === "electron-json") {
+        for (const line of jsonKeysText.split("\n")) {
+          const index = line.indexOf("=");
+          if (index > 0) {
+            json_keys[line.slice(0, index).trim()] = line.slice(index + 1).trim();
+          }
+        }
+      }
+
+      const config = await invoke<UserConfig>("add_custom_software", {
+        software: { ...newSoftware, json_keys },
+      });
       setUserConfig(config);
       setShowSoftwareModal(false);
       setNewSoftware({ name: "", config_type: "json", config_path: "" });
       // 重新加载软件列表
       await loadSoftwareList();
     } catch (e) {
-      setOperationResults([`添加失败: ${e}`]);
+      setOperationResults([`添加失败: ${errorText(e)}`]);
     }
   }
 
@@ -375,7 +611,7 @@ function App() {
       // 重新加载软件列表
       await loadSoftwareList();
     } catch (e) {
-      setOperationResults([`删除失败: ${e}`]);
+      setOperationResults([`删除失败: ${errorText(e)}`]);
     }
   }
 
@@ -413,6 +649,12 @@ function App() {
         <h1>Proxy Manager</h1>
       </header>
 
+      {safeMode && (
+        <div className="safe-mode-banner">
+          安全模式：启动时未执行任何自动操作，配置为只读。可以在此关闭代理，正常启动后再修改设置。
+        </div>
+      )}
+
       <div className="app-content">
         {/* 左侧：VPN 检测和代理配置组 */}
         <section className="card">
@@ -433,8 +675,12 @@ function App() {
               className="select-input"
             >
               <option value="">选择 VPN 软件</option>
-              {vpnList.map((vpn) => (
-                <option key={vpn.name} value={vpn.name}>{vpn.name}</option>
+              {vpnList.map(({ config, running, detected_ports }) => (
+                <option key={config.name} value={config.name}>
+                  {running
+                    ? `${config.name}（运行中${detected_ports.length > 0 ? `: ${detected_ports.join(", ")}` : ""}）`
+                    : config.name}
+                </option>
               ))}
               <option value="custom">其他...</option>
             </select>
@@ -463,11 +709,14 @@ function App() {
           {detectionResult && (
             <div className={`result-box ${detectionResult.success ? "success" : "error"}`}>
               <p>{detectionResult.message}</p>
+              {detectionResult.config_file && <p>配置文件: {detectionResult.config_file}</p>}
               {detectionResult.ports.length > 0 && (
                 <div className="port-list">
                   {detectionResult.ports.map((port, idx) => (
                     <span key={idx} className="port-tag">
-                      {port.port} ({port.port_type})
+                      {port.port} ({port.port_type}
+                      {port.bind_scope?.kind === "any" && ", 局域网可连接"}
+                      {port.bind_scope?.kind === "specific" && `, ${port.bind_scope.addr}`})
                     </span>
                   ))}
                 </div>
@@ -559,6 +808,9 @@ function App() {
                     />
                     <span className="checkbox-custom" />
                     <span className="software-name">{software.name}</span>
+                    {software.warning && (
+                      <span className="software-warning" title={software.warning}>⚠</span>
+                    )}
                   </label>
 
                   <div className="software-actions">
@@ -574,8 +826,18 @@ function App() {
                             {profile.name}
                           </option>
                         ))}
+                        <option value="direct">直连（不使用代理）</option>
                       </select>
                     )}
+                    {relocatedSoftware.has(software.name) && (
+                      <button
+                        onClick={() => migrateJetbrainsConfig(software.name)}
+                        className="toast-link"
+                        title="检测到新版本的配置目录，代理设置仍在旧版本中"
+                      >
+                        迁移到新版本
+                      </button>
+                    )}
                     {software.config_path && (
                       <button
                         onClick={() => setExpandedSoftware(
@@ -643,6 +905,17 @@ function App() {
           </div>
         </div>
         <div className="footer-actions">
+          {!isProxyEnabled && (
+            <label className="checkbox-label" title="任一软件失败时，将本次已修改的软件还原到开启前的状态">
+              <input
+                type="checkbox"
+                checked={atomicApply}
+                onChange={(e) => setAtomicApply(e.target.checked)}
+              />
+              <span className="checkbox-custom" />
+              <span>全部成功才生效</span>
+            </label>
+          )}
           <button
             onClick={toggleProxy}
             disabled={isOperating || selectedSoftware.size === 0}
@@ -658,14 +931,30 @@ function App() {
         <div className="toast">
           <div className="toast-header">
             <span>操作结果</span>
+            {operationResults.some((r) => typeof r !== "string") && (
+              <button onClick={copyResultsReport} className="toast-link">复制</button>
+            )}
             <button onClick={() => setOperationResults([])} className="toast-close">×</button>
           </div>
           <div className="toast-body">
-            {operationResults.map((result, idx) => (
-              <div key={idx} className={`toast-item ${result.startsWith("✓") ? "success" : "error"}`}>
-                {result}
-              </div>
-            ))}
+            {operationResults.map((result, idx) =>
+              typeof result === "string" ? (
+                <div key={idx} className={`toast-item ${result.startsWith("✓") ? "success" : "error"}`}>
+                  {result}
+                </div>
+              ) : (
+                <div key={idx} className={`toast-item ${result.success ? "success" : "error"}`}>
+                  {result.success ? "✓" : "✗"} {result.software}: {result.message}
+                  {result.requires_restart && " (需重启软件后生效)"}
+                  {result.detail && <div className="toast-detail">{result.detail}</div>}
+                  {result.changed_path && (
+                    <button onClick={() => openConfigFile(result.software)} className="toast-link">
+                      打开文件
+                    </button>
+                  )}
+                </div>
+              )
+            )}
           </div>
         </div>
       )}
@@ -696,6 +985,26 @@ function App() {
                 />
               </div>
               <div className="form-row">
+                <div className="form-group" style={{ width: 100 }}>
+                  <label>协议</label>
+                  <select
+                    value={(editingProfile ? editingProfile.proxy_scheme : newProfile.proxy_scheme) || "http"}
+                    onChange={(e) => {
+                      const proxy_scheme = e.target.value as ProxyScheme;
+                      if (editingProfile) {
+                        setEditingProfile({ ...editingProfile, proxy_scheme });
+                      } else {
+                        setNewProfile({ ...newProfile, proxy_scheme });
+                      }
+                    }}
+                    className="profile-select"
+                  >
+                    <option value="http">http</option>
+                    <option value="https">https</option>
+                    <option value="socks5">socks5</option>
+                    <option value="socks5h">socks5h</option>
+                  </select>
+                </div>
                 <div className="form-group flex-1">
                   <label>主机地址</label>
                   <input
@@ -771,8 +1080,24 @@ function App() {
                   <option value="json">JSON</option>
                   <option value="ini">INI</option>
                   <option value="env">ENV</option>
+                  <option value="electron-json">Electron JSON</option>
                 </select>
               </div>
+              {newSoftware.config_type === "electron-json" && (
+                <div className="form-group">
+                  <label>代理键</label>
+                  <textarea
+                    value={jsonKeysText}
+                    onChange={(e) => setJsonKeysText(e.target.value)}
+                    placeholder={"proxy/enabled = {enabled}\nproxy/url = {http_proxy}\nproxy/bypass = {no_proxy}"}
+                    className="text-input"
+                    rows={4}
+                  />
+                  <span className="form-hint">
+                    每行一个“键路径 = 值模板”，/ 表示嵌套；可用 {"{http_proxy}"} {"{https_proxy}"} {"{no_proxy}"} {"{host}"} {"{port}"} {"{enabled}"}
+                  </span>
+                </div>
+              )}
               <div className="form-group">
                 <label>配置文件路径</label>
                 <input